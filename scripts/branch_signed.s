@@ -0,0 +1,43 @@
+# Regression test for the compound branch pseudo-instructions with negative values
+# Expected output: ok
+.data
+bad_msg: .asciiz "BAD\n"
+ok_msg:  .asciiz "ok\n"
+
+.text
+main:
+    li $t0, -3
+    li $t1, 5
+    li $t2, -7
+
+    blt $t1, $t0, bad   # 5 < -3
+    bgt $t0, $t1, bad   # -3 > 5
+    ble $t1, $t2, bad   # 5 <= -7
+    bge $t2, $t0, bad   # -7 >= -3
+    blt $t0, $t1, a     # -3 < 5
+    j bad
+a:
+    bgt $t0, $t2, b     # -3 > -7
+    j bad
+b:
+    ble $t2, $t2, c     # -7 <= -7
+    j bad
+c:
+    bge $t0, $t2, d     # -3 >= -7
+    j bad
+d:
+    bltu $t0, $t1, bad  # 0xfffffffd < 5
+    bgeu $t1, $t0, bad  # 5 >= 0xfffffffd
+
+    li $v0, 4
+    la $a0, ok_msg
+    syscall
+    li $v0, 10
+    syscall
+
+bad:
+    li $v0, 4
+    la $a0, bad_msg
+    syscall
+    li $v0, 10
+    syscall
@@ -0,0 +1,56 @@
+# Takes every kind of branch, printing BAD if one misbehaves
+li $t0, -3
+li $t1, 5
+bltz $t0, l1
+li $a0, 99
+l1:
+bgez $t1, l2
+li $a0, 98
+l2:
+blez $zero, l3
+li $a0, 97
+l3:
+bgtz $t0, bad
+bltz $t1, bad
+bgez $t0, bad
+blez $t1, bad
+beqz $zero, l4
+j bad
+l4:
+bnez $t0, l5
+j bad
+l5:
+bltzal $t0, func
+bgezal $t0, bad
+bltu $t1, $t0, l6
+j bad
+l6:
+bgeu $t0, $t1, l7
+j bad
+l7:
+bgtu $t1, $t0, bad
+bleu $t0, $t1, bad
+b l8
+j bad
+l8:
+b done
+j bad
+func:
+li $v0, 4
+la $a0, inf
+syscall
+jr $ra
+bad:
+li $v0, 4
+la $a0, badm
+syscall
+done:
+li $v0, 4
+la $a0, ok
+syscall
+li $v0, 10
+syscall
+.data
+inf: .asciiz "in func\n"
+badm: .asciiz "BAD\n"
+ok: .asciiz "ok\n"
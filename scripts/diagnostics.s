@@ -0,0 +1,88 @@
+# Regression test for the assembler diagnostics, every problem is reported together
+# Expected diagnostics:
+# line 54, column 1: error: Label `loop` is already defined on line 52
+#     note: rename one of the labels
+# line 55, column 20: error: Immediate 40000 does not fit in 16 bits
+#     note: use a value from -32768 to 32767 or load it into a register with li
+# line 56, column 19: error: Immediate -1 does not fit in 16 bits
+#     note: use a value from 0 to 65535 or load it into a register with li
+# line 57, column 21: error: Undefined label `lopo`
+#     note: did you mean `loop`?
+# line 58, column 7: error: Undefined label `nowhere`
+#     note: define it with `nowhere:` or check its spelling
+# line 59, column 5: error: Unknown Opcode
+#     note: did you mean `addi`?
+# line 60, column 21: error: Branch to `far` is 40002 instructions away, more than a branch can reach
+#     note: branch over a j instruction to reach the label
+# line 65, column 12: error: Global label `mian` is not defined in any file
+#     note: did you mean `main`?
+# line 66, column 5: error: Division by zero in `4 / (far - far)`
+#     note: check the values of the labels and constants it divides by
+# line 67, column 20: error: Immediate -32769 does not fit in 16 bits
+#     note: use a value from -32768 to 32767 or load it into a register with li
+# line 68, column 12: error: Expected a constant expression
+#     note: only numbers and constants defined above can be used here
+# line 73, column 5: error: Immediate 70000 does not fit in 16 bits
+#     note: use a value from -32768 to 32767 or load it into a register with li
+#     note: expanded from line 70 in macro `increment`
+# line 74, column 5: error: Macro `increment` does not take 2 argument(s)
+#     note: it takes 1 argument(s)
+# line 76, column 2: error: Label `far.skip` is already defined on line 75
+#     note: rename one of the labels
+# line 77, column 7: error: Undefined label `2f`
+#     note: define `2:` after it
+# line 79, column 11: warning: Value 300 does not fit in 1 byte(s) and is truncated
+#     note: use a value from -128 to 255 or a larger directive
+# line 81, column 11: warning: Misaligned data at 0x10010009, it has to be aligned to 4 bytes
+#     note: align it with `.align 2` or insert `.space 3` before it
+# line 83, column 19: error: Shift amount out of range
+#     note: use a value from 0 to 31
+# line 84, column 20: error: Condition flag out of range
+#     note: use a value from 0 to 7
+# line 85, column 12: error: Condition flag out of range
+#     note: use a value from 0 to 7
+# line 86, column 13: error: Value does not fit in 32 bits
+#     note: use a value from -2147483648 to 4294967295
+# line 87, column 13: error: Value does not fit in 32 bits
+#     note: use a value from -2147483648 to 4294967295
+# line 88, column 13: error: Value does not fit in 32 bits
+#     note: use a value from -2147483648 to 4294967295
+.text
+main:
+loop:
+    addi $t0, $t0, 1
+loop:
+    addi $t0, $t0, 40000
+    ori $t0, $t0, -1
+    beq $t0, $zero, lopo
+    j nowhere
+    addj $t0, $t0, 1
+    bne $t0, $zero, far
+    .space 160000
+far:
+.data
+    .byte 1
+    .globl mian
+    .byte 4 / (far - far)
+    addi $t0, $t0, -32769
+    .space far
+.macro increment(%r)
+    addi %r, %r, 70000
+.end_macro
+.text
+    increment($t0)
+    increment($t0, $t1)
+.skip:
+.skip:
+    b 2f
+.data
+    .byte 300
+    .align 0
+    .word 5
+.text
+    sll $t0, $t0, 40
+    movt $t0, $t1, 8
+    c.eq.s 9, $f0, $f1
+    li $t0, 0x100000005
+    li $t1, -3000000000
+    lw $t3, 0x100000000($zero)
@@ -0,0 +1,179 @@
+# Regression test for data alignment, .align, .globl, .extern, .eqv, .set, .float and .double
+# Expected output:
+# word: 1234 offset 4
+# half: -2 offset 2
+# float: 3.5 double: 2.25 offset 0
+# align 3: 0 align 0: 1 16909060
+# eqv: 3 set: 2 42
+# extern: 0 7
+.eqv COUNT, 3
+.set STEP, 1
+.set STEP, STEP + 1
+# global data outside the data segment, which starts out zeroed
+.extern counter, 4
+
+.data
+flag:   .byte 7
+# .word and .half are aligned to their size, and so are the labels before them
+value:
+        .word 1234
+bytes:  .byte 1
+half:   .half -2
+pi:     .float 3.5
+        .byte 0
+e:      .double 2.25
+        .align 3
+table:  .space 8
+        .align 0
+        .byte 1
+# without automatic alignment the word is packed after the byte, which the assembler warns about
+packed: .word 0x01020304
+word_msg:   .asciiz "word: "
+half_msg:   .asciiz "half: "
+float_msg:  .asciiz "float: "
+double_msg: .asciiz " double: "
+align_msg:  .asciiz "align 3: "
+packed_msg: .asciiz " align 0: "
+eqv_msg:    .asciiz "eqv: "
+set_msg:    .asciiz " set: "
+extern_msg: .asciiz "extern: "
+offset_msg: .asciiz " offset "
+space:      .asciiz " "
+wrong_msg:  .asciiz "started at the wrong entry point\n"
+
+.text
+# the program starts at the global main instead of here
+wrong:
+    li $v0, 4
+    la $a0, wrong_msg
+    syscall
+    li $v0, 10
+    syscall
+
+.globl main
+main:
+    # values after bytes are aligned
+    la $a0, word_msg
+    li $v0, 4
+    syscall
+    lw $a0, value
+    la $a1, value
+    la $a2, flag
+    jal print_offset
+
+    la $a0, half_msg
+    li $v0, 4
+    syscall
+    lh $a0, half
+    la $a1, half
+    la $a2, bytes
+    jal print_offset
+
+    # floating point data
+    la $a0, float_msg
+    li $v0, 4
+    syscall
+    la $t0, pi
+    lwc1 $f12, 0($t0)
+    li $v0, 2
+    syscall
+    la $a0, double_msg
+    li $v0, 4
+    syscall
+    la $t0, e
+    ldc1 $f12, 0($t0)
+    li $v0, 3
+    syscall
+    la $a0, offset_msg
+    li $v0, 4
+    syscall
+    andi $a0, $t0, 7
+    li $v0, 1
+    syscall
+    jal newline
+
+    # .align 3 aligns to 8 bytes and .align 0 leaves the word after a byte unaligned
+    la $a0, align_msg
+    li $v0, 4
+    syscall
+    la $t0, table
+    andi $a0, $t0, 7
+    li $v0, 1
+    syscall
+    la $a0, packed_msg
+    li $v0, 4
+    syscall
+    la $t0, packed
+    andi $a0, $t0, 3
+    li $v0, 1
+    syscall
+    la $a0, space
+    li $v0, 4
+    syscall
+    ulw $a0, packed
+    li $v0, 1
+    syscall
+    jal newline
+
+    # symbolic constants
+    la $a0, eqv_msg
+    li $v0, 4
+    syscall
+    li $a0, COUNT
+    li $v0, 1
+    syscall
+    la $a0, set_msg
+    li $v0, 4
+    syscall
+    li $a0, STEP
+    li $v0, 1
+    syscall
+.set STEP, 42
+    la $a0, space
+    li $v0, 4
+    syscall
+    li $a0, STEP
+    li $v0, 1
+    syscall
+    jal newline
+
+    # .extern data
+    la $a0, extern_msg
+    li $v0, 4
+    syscall
+    lw $a0, counter
+    li $v0, 1
+    syscall
+    la $a0, space
+    li $v0, 4
+    syscall
+    li $t0, 7
+    sw $t0, counter
+    lw $a0, counter
+    li $v0, 1
+    syscall
+    jal newline
+
+    li $v0, 10
+    syscall
+
+# Prints the integer in a0 followed by the offset of the address in a1 from the one in a2
+print_offset:
+    li $v0, 1
+    syscall
+    la $a0, offset_msg
+    li $v0, 4
+    syscall
+    sub $a0, $a1, $a2
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
@@ -0,0 +1,163 @@
+# Corpus for the disassembler using every instruction the assembler supports, the code after
+# main is never run. Check it with `cargo run --example round_trip -- scripts/disassembly.s`
+# Expected output:
+# corpus assembled
+.data
+message: .asciiz "corpus assembled"
+value:   .word 42
+
+.text
+main:
+        la $a0, message
+        li $v0, 4
+        syscall
+        li $v0, 10
+        syscall
+
+corpus:
+        add $t0, $t1, $t2
+        addu $t0, $t1, $t2
+        sub $t0, $t1, $t2
+        subu $t0, $t1, $t2
+        and $t0, $t1, $t2
+        or $t0, $t1, $t2
+        xor $t0, $t1, $t2
+        nor $t0, $t1, $t2
+        slt $t0, $t1, $t2
+        sltu $t0, $t1, $t2
+        movz $t0, $t1, $t2
+        movn $t0, $t1, $t2
+        mul $t0, $t1, $t2
+        mult $t1, $t2
+        multu $t1, $t2
+        div $t1, $t2
+        divu $t1, $t2
+        madd $t1, $t2
+        maddu $t1, $t2
+        msub $t1, $t2
+        msubu $t1, $t2
+        mfhi $t0
+        mflo $t0
+        mthi $t0
+        mtlo $t0
+        sll $t0, $t1, 3
+        srl $t0, $t1, 31
+        sra $t0, $t1, 1
+        rotr $t0, $t1, 7
+        sllv $t0, $t1, $t2
+        srlv $t0, $t1, $t2
+        srav $t0, $t1, $t2
+        rotrv $t0, $t1, $t2
+        clz $t0, $t1
+        clo $t0, $t1
+        wsbh $t0, $t1
+        seb $t0, $t1
+        seh $t0, $t1
+        ext $t0, $t1, 4, 8
+        ins $t0, $t1, 28, 4
+        addi $t0, $sp, -8
+        addiu $t0, $sp, 32767
+        slti $t0, $t1, -32768
+        sltiu $t0, $t1, 5
+        andi $t0, $t1, 0xffff
+        ori $t0, $t1, 0x8000
+        xori $t0, $t1, 1
+        lui $t0, 0x1001
+        lb $t0, -1($t1)
+        lbu $t0, 0($t1)
+        lh $t0, 2($t1)
+        lhu $t0, 2($t1)
+        lw $t0, 4($sp)
+        lwl $t0, 3($t1)
+        lwr $t0, 0($t1)
+        ll $t0, 0($t1)
+        sb $t0, 0($t1)
+        sh $t0, 2($t1)
+        sw $t0, -4($sp)
+        swl $t0, 3($t1)
+        swr $t0, 0($t1)
+        sc $t0, 0($t1)
+        lwc1 $f2, 0($t1)
+        ldc1 $f4, 8($t1)
+        swc1 $f2, 0($t1)
+        sdc1 $f4, 8($t1)
+.back:
+        beq $t0, $t1, .back
+        bne $t0, $zero, .ahead
+        blez $t0, corpus
+        bgtz $t0, main
+        bltz $t0, .back
+        bgez $t0, .ahead
+        bltzal $t0, .back
+        bgezal $t0, .ahead
+        beq $t0, $t1, 5
+.ahead:
+        j main
+        jal corpus
+        j .ahead
+        jr $ra
+        jalr $t0
+        jalr $t1, $t0
+        tge $t0, $t1
+        tgeu $t0, $t1
+        tlt $t0, $t1
+        tltu $t0, $t1
+        teq $t0, $t1
+        tne $t0, $t1
+        tgei $t0, -1
+        tgeiu $t0, 1
+        tlti $t0, 2
+        tltiu $t0, 3
+        teqi $t0, 4
+        tnei $t0, 5
+        break
+        break 7
+        sync
+        mfc0 $t0, $status
+        mtc0 $t0, $epc
+        mfc1 $t0, $f1
+        mtc1 $t0, $f1
+        cfc1 $t0, $fcsr
+        ctc1 $t0, $fcsr
+        add.s $f0, $f1, $f2
+        sub.s $f0, $f1, $f2
+        mul.s $f0, $f1, $f2
+        div.s $f0, $f1, $f2
+        sqrt.s $f0, $f1
+        abs.s $f0, $f1
+        mov.s $f0, $f1
+        neg.s $f0, $f1
+        add.d $f0, $f2, $f4
+        sub.d $f0, $f2, $f4
+        mul.d $f0, $f2, $f4
+        div.d $f0, $f2, $f4
+        sqrt.d $f0, $f2
+        abs.d $f0, $f2
+        mov.d $f0, $f2
+        neg.d $f0, $f2
+        round.w.s $f0, $f1
+        trunc.w.s $f0, $f1
+        ceil.w.s $f0, $f1
+        floor.w.s $f0, $f1
+        round.w.d $f0, $f2
+        trunc.w.d $f0, $f2
+        ceil.w.d $f0, $f2
+        floor.w.d $f0, $f2
+        cvt.d.s $f0, $f1
+        cvt.w.s $f0, $f1
+        cvt.s.d $f0, $f2
+        cvt.w.d $f0, $f2
+        cvt.s.w $f0, $f1
+        cvt.d.w $f0, $f1
+        c.eq.s $f0, $f1
+        c.lt.s 1, $f0, $f1
+        c.le.s $f0, $f1
+        c.eq.d $f0, $f2
+        c.lt.d $f0, $f2
+        c.le.d 7, $f0, $f2
+        bc1f .back
+        bc1t 3, .ahead
+        movf $t0, $t1
+        movt $t0, $t1, 2
+        eret
+        nop
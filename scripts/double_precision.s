@@ -0,0 +1,187 @@
+# Regression test for double precision floating point
+# Run with the input 2.75
+# Expected output:
+# add: 4.25
+# sub: 1.25
+# mul: 4.125
+# div: 0.5454545454545454
+# sqrt: 1.5
+# abs: 2.75 neg: -1.5
+# cvt.d.w: -7
+# cvt.d.s: 2.5 cvt.s.d: 2.75
+# cvt.w.d: 3 trunc.w.d: 2
+# c.lt.d: taken
+# c.eq.d: not taken
+# high word: 1074135040
+# sdc1: 2.25
+# read: 5.5
+.data
+a:      .double 2.75
+b:      .double 1.5
+c:      .double 2.25
+result: .double 0
+add_msg:   .asciiz "add: "
+sub_msg:   .asciiz "sub: "
+mul_msg:   .asciiz "mul: "
+div_msg:   .asciiz "div: "
+sqrt_msg:  .asciiz "sqrt: "
+abs_msg:   .asciiz "abs: "
+neg_msg:   .asciiz " neg: "
+cvtdw_msg: .asciiz "cvt.d.w: "
+cvtds_msg: .asciiz "cvt.d.s: "
+cvtsd_msg: .asciiz " cvt.s.d: "
+cvtwd_msg: .asciiz "cvt.w.d: "
+trunc_msg: .asciiz " trunc.w.d: "
+lt_msg:    .asciiz "c.lt.d: "
+eq_msg:    .asciiz "c.eq.d: "
+high_msg:  .asciiz "high word: "
+mem_msg:   .asciiz "sdc1: "
+read_msg:  .asciiz "read: "
+taken:     .asciiz "taken\n"
+not_taken: .asciiz "not taken\n"
+
+.text
+main:
+    la $t0, a
+    ldc1 $f2, 0($t0)
+    ldc1 $f4, 8($t0)
+
+    la $a0, add_msg
+    add.d $f12, $f2, $f4
+    jal print_double
+    la $a0, sub_msg
+    sub.d $f12, $f2, $f4
+    jal print_double
+    la $a0, mul_msg
+    mul.d $f12, $f2, $f4
+    jal print_double
+    la $a0, div_msg
+    div.d $f12, $f4, $f2
+    jal print_double
+
+    la $a0, sqrt_msg
+    ldc1 $f6, 16($t0)
+    sqrt.d $f12, $f6
+    jal print_double
+
+    la $a0, abs_msg
+    jal print_str
+    neg.d $f6, $f2
+    abs.d $f12, $f6
+    jal print_f12
+    la $a0, neg_msg
+    neg.d $f6, $f4
+    mov.d $f12, $f6
+    jal print_double
+
+    # conversions between words, singles and doubles
+    la $a0, cvtdw_msg
+    li $t1, -7
+    mtc1 $t1, $f8
+    cvt.d.w $f12, $f8
+    jal print_double
+
+    la $a0, cvtds_msg
+    jal print_str
+    li.s $f8, 2.5
+    cvt.d.s $f12, $f8
+    jal print_f12
+    la $a0, cvtsd_msg
+    li $v0, 4
+    syscall
+    cvt.s.d $f12, $f2
+    li $v0, 2
+    syscall
+    jal newline
+
+    la $a0, cvtwd_msg
+    jal print_str
+    cvt.w.d $f8, $f2
+    mfc1 $a0, $f8
+    li $v0, 1
+    syscall
+    la $a0, trunc_msg
+    li $v0, 4
+    syscall
+    trunc.w.d $f8, $f2
+    mfc1 $a0, $f8
+    li $v0, 1
+    syscall
+    jal newline
+
+    # compare and branch on the condition flag
+    la $a0, lt_msg
+    jal print_str
+    c.lt.d $f4, $f2
+    bc1t lt_taken
+    la $a0, not_taken
+    b lt_done
+lt_taken:
+    la $a0, taken
+lt_done:
+    jal print_str
+
+    la $a0, eq_msg
+    jal print_str
+    c.eq.d $f4, $f2
+    bc1t eq_taken
+    la $a0, not_taken
+    b eq_done
+eq_taken:
+    la $a0, taken
+eq_done:
+    jal print_str
+
+    # the odd register holds the high word of 2.75
+    la $a0, high_msg
+    jal print_str
+    mov.d $f10, $f2
+    mfc1 $a0, $f11
+    li $v0, 1
+    syscall
+    jal newline
+
+    # store and load again
+    la $a0, mem_msg
+    la $t1, result
+    ldc1 $f6, 16($t0)
+    sdc1 $f6, 0($t1)
+    ldc1 $f12, 0($t1)
+    jal print_double
+
+    # read a double and add it to itself
+    li $v0, 7
+    syscall
+    la $a0, read_msg
+    add.d $f12, $f0, $f0
+    jal print_double
+
+    li $v0, 10
+    syscall
+
+# prints the message in $a0 then the double in $f12
+print_double:
+    li $v0, 4
+    syscall
+    li $v0, 3
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+print_f12:
+    li $v0, 3
+    syscall
+    jr $ra
+
+print_str:
+    li $v0, 4
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
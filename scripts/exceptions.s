@@ -0,0 +1,91 @@
+# Regression test for coprocessor 0 and the kernel exception handler
+# Expected output:
+# exception 12
+# exception 4
+# badvaddr 0x1 -> 1
+# exception 5
+# exception 10
+# exception 0
+# timer done
+.data
+bad_msg:   .asciiz "badvaddr 0x1 -> "
+timer_msg: .asciiz "timer done\n"
+
+.text
+main:
+    lui $t0, 0x8000
+    add $t1, $t0, $t0       # overflow
+
+    lw $t2, 1($zero)        # unaligned load
+    la $a0, bad_msg
+    li $v0, 4
+    syscall
+    mfc0 $a0, $8            # BadVAddr
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+
+    sw $t2, 2($zero)        # unaligned store
+    .word 0xfc000000        # reserved instruction
+
+    # raise the timer interrupt after 50 cycles
+    mtc0 $zero, $count
+    li $t0, 50
+    mtc0 $t0, $compare
+    li $t0, 0x8001          # enable interrupts and the timer interrupt
+    mtc0 $t0, $status
+wait:
+    beq $s7, $zero, wait
+
+    la $a0, timer_msg
+    li $v0, 4
+    syscall
+
+    li $v0, 10
+    syscall
+
+.kdata
+save_v0: .word 0
+save_a0: .word 0
+exc_msg: .asciiz "exception "
+
+.ktext
+handler:
+    la $k1, save_v0
+    sw $v0, 0($k1)
+    sw $a0, 4($k1)
+
+    # print the exception code
+    mfc0 $k0, $13
+    srl $k0, $k0, 2
+    andi $k0, $k0, 0x1f
+    li $v0, 4
+    la $a0, exc_msg
+    syscall
+    li $v0, 1
+    move $a0, $k0
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+
+    beq $k0, $zero, interrupt
+
+    # skip the faulting instruction
+    mfc0 $k0, $14
+    addiu $k0, $k0, 4
+    mtc0 $k0, $14
+    b done
+
+interrupt:
+    li $s7, 1
+    mtc0 $zero, $12         # disable interrupts
+    mtc0 $zero, $11         # acknowledge the timer
+
+done:
+    la $k1, save_v0
+    lw $v0, 0($k1)
+    lw $a0, 4($k1)
+    eret
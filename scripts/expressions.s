@@ -0,0 +1,149 @@
+# Regression test for constant expressions in operands and data
+# Expected output:
+# arith: 14 20 2 -8 7 -1
+# bits: 48 3 6 5 -16
+# chars: 65 10 39 92
+# consts: 40 12 -3 44
+# table: 12 3 8
+# hi/lo: 305419896 -32768
+# labels: 30 22 20
+SIZE = 10 * 4
+COUNT = (end - start) / 4
+.data
+start:      .word 10, 20, 30
+end:
+table:      .word end - start, COUNT, start + 8 - start
+chars:      .byte 'A', '\n', '\'', '\\'
+buffer:     .space SIZE
+big:        .word 0x12345678
+arith_msg:  .asciiz "arith: "
+bits_msg:   .asciiz "bits: "
+chars_msg:  .asciiz "chars: "
+consts_msg: .asciiz "consts: "
+table_msg:  .asciiz "table: "
+hilo_msg:   .asciiz "hi/lo: "
+labels_msg: .asciiz "labels: "
+space:      .asciiz " "
+
+.text
+main:
+    # arithmetic with the usual precedence
+    la $s0, arith_msg
+    li $a0, 2 + 3 * 4
+    jal print
+    la $s0, space
+    li $a0, (2 + 3) * 4
+    jal print
+    li $a0, 17 % 5
+    jal print
+    li $a0, -(3 + 5)
+    jal print
+    li $a0, 15 / 2
+    jal print
+    addi $a0, $zero, -7 / 7
+    jal print
+    jal newline
+
+    # bit operations
+    la $s0, bits_msg
+    li $a0, 3 << 4
+    jal print
+    la $s0, space
+    li $a0, 0x30 >> 4
+    jal print
+    li $a0, 7 & 0b110
+    jal print
+    li $a0, 4 | 1 ^ 0
+    jal print
+    li $a0, ~0xF
+    jal print
+    jal newline
+
+    # character literals
+    la $t0, chars
+    lb $a0, 0($t0)
+    la $s0, chars_msg
+    jal print
+    la $s0, space
+    lb $a0, 1($t0)
+    jal print
+    lb $a0, 2($t0)
+    jal print
+    lb $a0, 3($t0)
+    jal print
+    jal newline
+
+    # named constants, defined before and after they are used
+    li $a0, SIZE
+    la $s0, consts_msg
+    jal print
+    la $s0, space
+    addi $a0, $zero, LATER
+    jal print
+    li $a0, COUNT - 6
+    jal print
+    la $t0, buffer + SIZE
+    la $t1, buffer
+    sub $a0, $t0, $t1
+    addi $a0, $a0, 4
+    jal print
+    jal newline
+
+    # data computed from labels
+    la $s0, table_msg
+    lw $a0, table
+    jal print
+    la $s0, space
+    lw $a0, table + 4
+    jal print
+    lw $a0, table + 2 * 4
+    jal print
+    jal newline
+
+    # building an address from its halves
+    lui $t0, %hi(big)
+    lw $a0, %lo(big)($t0)
+    la $s0, hilo_msg
+    jal print
+    la $s0, space
+    li $a0, %lo(0x12348000)
+    jal print
+    jal newline
+
+    # label offsets in loads and branches
+    lw $a0, start + 8
+    la $s0, labels_msg
+    jal print
+    la $s0, space
+    li $t1, 4
+    lw $a0, end - 4($t1)
+    addi $a0, $a0, 10
+    jal print
+    li $a0, 20
+    beq $zero, $zero, skip + 4
+skip:
+    li $a0, 99
+    jal print
+    jal newline
+
+    li $v0, 10
+    syscall
+
+LATER = SIZE / 10 * 3
+
+# Prints the string in s0 followed by the integer in a0
+print:
+    move $t9, $a0
+    li $v0, 4
+    move $a0, $s0
+    syscall
+    li $v0, 1
+    move $a0, $t9
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
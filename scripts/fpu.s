@@ -0,0 +1,199 @@
+# Regression test for the single precision floating point unit
+# Expected output:
+# add: 4
+# sub: 1
+# mul: 3.75
+# div: 0.6
+# sqrt: 1.5
+# abs: 2.5 neg: -1.5
+# cvt.s.w: -7
+# cvt.w.s: 2 trunc.w.s: 2
+# cvt.w.s: -4 trunc.w.s: -3
+# c.lt.s: taken
+# c.le.s: taken
+# c.eq.s: not taken
+# cc 3: taken
+# mtc1/mfc1: 1069547520
+# lwc1/swc1: 6.25
+.data
+value:  .word 0x40c80000    # 6.25
+result: .word 0
+add_msg:   .asciiz "add: "
+sub_msg:   .asciiz "sub: "
+mul_msg:   .asciiz "mul: "
+div_msg:   .asciiz "div: "
+sqrt_msg:  .asciiz "sqrt: "
+abs_msg:   .asciiz "abs: "
+neg_msg:   .asciiz " neg: "
+cvtsw_msg: .asciiz "cvt.s.w: "
+cvtws_msg: .asciiz "cvt.w.s: "
+trunc_msg: .asciiz " trunc.w.s: "
+lt_msg:    .asciiz "c.lt.s: "
+le_msg:    .asciiz "c.le.s: "
+eq_msg:    .asciiz "c.eq.s: "
+cc_msg:    .asciiz "cc 3: "
+move_msg:  .asciiz "mtc1/mfc1: "
+mem_msg:   .asciiz "lwc1/swc1: "
+taken:     .asciiz "taken\n"
+not_taken: .asciiz "not taken\n"
+
+.text
+main:
+    li.s $f1, 2.5
+    li.s $f2, 1.5
+
+    la $a0, add_msg
+    add.s $f12, $f1, $f2
+    jal print_float
+    la $a0, sub_msg
+    sub.s $f12, $f1, $f2
+    jal print_float
+    la $a0, mul_msg
+    mul.s $f12, $f1, $f2
+    jal print_float
+    la $a0, div_msg
+    div.s $f12, $f2, $f1
+    jal print_float
+
+    la $a0, sqrt_msg
+    li.s $f3, 2.25
+    sqrt.s $f12, $f3
+    jal print_float
+
+    la $a0, abs_msg
+    neg.s $f3, $f1
+    abs.s $f12, $f3
+    jal print_str
+    jal print_f12
+    la $a0, neg_msg
+    neg.s $f12, $f2
+    jal print_float
+
+    # convert between words and singles
+    la $a0, cvtsw_msg
+    li $t0, -7
+    mtc1 $t0, $f4
+    cvt.s.w $f12, $f4
+    jal print_float
+
+    la $a0, cvtws_msg
+    cvt.w.s $f5, $f1        # 2.5 rounds to even
+    trunc.w.s $f6, $f1
+    jal print_conversions
+    la $a0, cvtws_msg
+    li.s $f7, -3.5
+    cvt.w.s $f5, $f7        # -3.5 rounds to even
+    trunc.w.s $f6, $f7
+    jal print_conversions
+
+    # compare and branch on the condition flag
+    la $a0, lt_msg
+    jal print_str
+    c.lt.s $f2, $f1
+    bc1t lt_taken
+    la $a0, not_taken
+    b lt_done
+lt_taken:
+    la $a0, taken
+lt_done:
+    jal print_str
+
+    la $a0, le_msg
+    jal print_str
+    c.le.s $f1, $f1
+    bc1f le_not_taken
+    la $a0, taken
+    b le_done
+le_not_taken:
+    la $a0, not_taken
+le_done:
+    jal print_str
+
+    la $a0, eq_msg
+    jal print_str
+    c.eq.s $f1, $f2
+    bc1t eq_taken
+    la $a0, not_taken
+    b eq_done
+eq_taken:
+    la $a0, taken
+eq_done:
+    jal print_str
+
+    # other condition flags are independent of flag 0
+    la $a0, cc_msg
+    jal print_str
+    c.eq.s 3, $f1, $f1
+    c.eq.s $f1, $f2
+    bc1t 3, cc_taken
+    la $a0, not_taken
+    b cc_done
+cc_taken:
+    la $a0, taken
+cc_done:
+    jal print_str
+
+    # move the bits of 1.5 to a general purpose register
+    la $a0, move_msg
+    jal print_str
+    mfc1 $a0, $f2
+    li $v0, 1
+    syscall
+    jal newline
+
+    # load, store and load again
+    la $a0, mem_msg
+    la $t0, value
+    lwc1 $f8, 0($t0)
+    la $t1, result
+    swc1 $f8, 0($t1)
+    lwc1 $f12, 0($t1)
+    jal print_float
+
+    li $v0, 10
+    syscall
+
+# prints the message in $a0 then the float in $f12
+print_float:
+    li $v0, 4
+    syscall
+    li $v0, 2
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+# prints the message in $a0 then the words in $f5 and $f6
+print_conversions:
+    li $v0, 4
+    syscall
+    mfc1 $a0, $f5
+    li $v0, 1
+    syscall
+    la $a0, trunc_msg
+    li $v0, 4
+    syscall
+    mfc1 $a0, $f6
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+print_f12:
+    li $v0, 2
+    syscall
+    jr $ra
+
+print_str:
+    li $v0, 4
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
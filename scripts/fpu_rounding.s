@@ -0,0 +1,287 @@
+# Regression test for FCSR rounding modes and IEEE-754 exception flags
+# Flags are printed as the FCSR flags field: 1 inexact, 2 underflow, 4 overflow,
+# 8 division by zero, 16 invalid operation
+# Expected output:
+# 0.1 + 0.2 = 0.30000000000000004 flags: 1
+# equal to 0.3: no
+# 1.5 + 1.5 + 1.5 flags: 0
+# 1/3 nearest: 0.33333334 zero: 0.3333333 up: 0.33333334 down: 0.3333333
+# -1/3 zero: -0.3333333 down: -0.33333334
+# cvt.w.s 2.5 nearest: 2 zero: 2 up: 3 down: 2
+# -2.5 round: -2 trunc: -2 ceil: -2 floor: -3
+# 1/0 flags: 8
+# overflow flags: 5
+# sqrt(-1) flags: 16
+# exception 15 cause: 8
+# after trap
+# fir formats: 19
+.data
+tenth:      .double 0.1
+fifth:      .double 0.2
+three_tens: .double 0.3
+huge:       .double 1e300
+sum_msg:    .asciiz "0.1 + 0.2 = "
+equal_msg:  .asciiz "equal to 0.3: "
+exact_msg:  .asciiz "1.5 + 1.5 + 1.5"
+third_msg:  .asciiz "1/3 nearest: "
+neg_msg:    .asciiz "-1/3 zero: "
+cvt_msg:    .asciiz "cvt.w.s 2.5 nearest: "
+round_msg:  .asciiz "-2.5 round: "
+trunc_msg:  .asciiz " trunc: "
+ceil_msg:   .asciiz " ceil: "
+floor_msg:  .asciiz " floor: "
+zero_msg:   .asciiz " zero: "
+up_msg:     .asciiz " up: "
+down_msg:   .asciiz " down: "
+div_msg:    .asciiz "1/0"
+over_msg:   .asciiz "overflow"
+sqrt_msg:   .asciiz "sqrt(-1)"
+flags_msg:  .asciiz " flags: "
+yes:        .asciiz "yes\n"
+no:         .asciiz "no\n"
+after_msg:  .asciiz "after trap\n"
+fir_msg:    .asciiz "fir formats: "
+exc_msg:    .asciiz "exception "
+cause_msg:  .asciiz " cause: "
+
+.text
+main:
+    # 0.1 and 0.2 have no exact binary representation, so their sum is inexact
+    la $t0, tenth
+    ldc1 $f2, 0($t0)
+    ldc1 $f4, 8($t0)
+    ldc1 $f6, 16($t0)
+    add.d $f12, $f2, $f4
+    li $v0, 4
+    la $a0, sum_msg
+    syscall
+    li $v0, 3
+    syscall
+    jal print_flags
+
+    li $v0, 4
+    la $a0, equal_msg
+    syscall
+    la $a0, yes
+    c.eq.d $f12, $f6
+    bc1t print_equal
+    la $a0, no
+print_equal:
+    syscall
+
+    # clearing the FCSR resets the sticky flags, exact sums leave them clear
+    ctc1 $zero, $31
+    li $t0, 3
+    mtc1 $t0, $f2
+    cvt.s.w $f2, $f2
+    li $t0, 1
+    mtc1 $t0, $f4
+    cvt.s.w $f4, $f4
+    li $t0, 2
+    mtc1 $t0, $f6
+    cvt.s.w $f6, $f6
+    div.s $f8, $f2, $f6
+    ctc1 $zero, $31
+    add.s $f10, $f8, $f8
+    add.s $f10, $f10, $f8
+    li $v0, 4
+    la $a0, exact_msg
+    syscall
+    jal print_flags
+
+    # 1/3 in each rounding mode
+    la $a0, third_msg
+    li $s0, 0
+    jal third
+    la $a0, zero_msg
+    li $s0, 1
+    jal third
+    la $a0, up_msg
+    li $s0, 2
+    jal third
+    la $a0, down_msg
+    li $s0, 3
+    jal third
+    jal newline
+
+    neg.s $f4, $f4
+    la $a0, neg_msg
+    li $s0, 1
+    jal third
+    la $a0, down_msg
+    li $s0, 3
+    jal third
+    jal newline
+    neg.s $f4, $f4
+
+    # cvt.w.s follows the rounding mode, 2.5 is 5/2
+    li $t0, 5
+    mtc1 $t0, $f2
+    cvt.s.w $f2, $f2
+    div.s $f2, $f2, $f6
+    la $a0, cvt_msg
+    li $s0, 0
+    jal convert
+    la $a0, zero_msg
+    li $s0, 1
+    jal convert
+    la $a0, up_msg
+    li $s0, 2
+    jal convert
+    la $a0, down_msg
+    li $s0, 3
+    jal convert
+    jal newline
+    ctc1 $zero, $31
+
+    # round, trunc, ceil and floor name their own rounding mode
+    neg.s $f2, $f2
+    li $v0, 4
+    la $a0, round_msg
+    syscall
+    round.w.s $f8, $f2
+    jal print_word
+    li $v0, 4
+    la $a0, trunc_msg
+    syscall
+    trunc.w.s $f8, $f2
+    jal print_word
+    li $v0, 4
+    la $a0, ceil_msg
+    syscall
+    ceil.w.s $f8, $f2
+    jal print_word
+    li $v0, 4
+    la $a0, floor_msg
+    syscall
+    floor.w.s $f8, $f2
+    jal print_word
+    jal newline
+
+    # dividing by zero gives infinity
+    ctc1 $zero, $31
+    mtc1 $zero, $f8
+    div.s $f10, $f4, $f8
+    li $v0, 4
+    la $a0, div_msg
+    syscall
+    jal print_flags
+
+    # overflow is also inexact
+    ctc1 $zero, $31
+    la $t0, huge
+    ldc1 $f10, 0($t0)
+    mul.d $f10, $f10, $f10
+    li $v0, 4
+    la $a0, over_msg
+    syscall
+    jal print_flags
+
+    # the square root of a negative number is invalid
+    ctc1 $zero, $31
+    neg.s $f10, $f4
+    sqrt.s $f10, $f10
+    li $v0, 4
+    la $a0, sqrt_msg
+    syscall
+    jal print_flags
+
+    # enabling the division by zero exception makes it trap
+    li $t0, 0x400
+    ctc1 $t0, $31
+    div.s $f10, $f4, $f8
+    ctc1 $zero, $31
+    li $v0, 4
+    la $a0, after_msg
+    syscall
+
+    # the FIR is read only and reports the single, double and word formats
+    ctc1 $zero, $0
+    li $v0, 4
+    la $a0, fir_msg
+    syscall
+    cfc1 $a0, $fir
+    srl $a0, $a0, 16
+    li $v0, 1
+    syscall
+
+    li $v0, 10
+    syscall
+
+# Prints the string in a0 then f4 / f2 rounded in the mode in s0
+third:
+    li $v0, 4
+    syscall
+    ctc1 $s0, $31
+    div.s $f12, $f4, $f2
+    li $v0, 2
+    syscall
+    jr $ra
+
+# Prints the string in a0 then f2 converted to a word in the mode in s0
+convert:
+    li $v0, 4
+    syscall
+    ctc1 $s0, $31
+    cvt.w.s $f8, $f2
+    mfc1 $a0, $f8
+    li $v0, 1
+    syscall
+    jr $ra
+
+# Prints the word in f8
+print_word:
+    mfc1 $a0, $f8
+    li $v0, 1
+    syscall
+    jr $ra
+
+# Prints the FCSR flags field followed by a newline
+print_flags:
+    li $v0, 4
+    la $a0, flags_msg
+    syscall
+    cfc1 $a0, $fcsr
+    srl $a0, $a0, 2
+    andi $a0, $a0, 0x1f
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+.ktext
+handler:
+    # print the exception code and the FCSR cause field
+    li $v0, 4
+    la $a0, exc_msg
+    syscall
+    mfc0 $k0, $13
+    srl $a0, $k0, 2
+    andi $a0, $a0, 0x1f
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, cause_msg
+    syscall
+    cfc1 $k0, $31
+    srl $a0, $k0, 12
+    andi $a0, $a0, 0x1f
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+
+    # skip the faulting instruction
+    mfc0 $k0, $14
+    addiu $k0, $k0, 4
+    mtc0 $k0, $14
+    eret
@@ -0,0 +1,118 @@
+# Regression test for the HI/LO multiply, divide and accumulate instructions
+# Expected output (hi lo):
+# mult:  -1 -42
+# multu: 6 -42
+# wide:  1 0
+# mthi:  7 0
+# madd:  6 -42
+# msub:  7 0
+# maddu: 13 -42
+# msubu: 7 0
+# div:   -6 -2
+# divu:  5 613566753
+# mul:   49
+.data
+mult_msg:  .asciiz "mult:  "
+multu_msg: .asciiz "multu: "
+wide_msg:  .asciiz "wide:  "
+mthi_msg:  .asciiz "mthi:  "
+madd_msg:  .asciiz "madd:  "
+msub_msg:  .asciiz "msub:  "
+maddu_msg: .asciiz "maddu: "
+msubu_msg: .asciiz "msubu: "
+div_msg:   .asciiz "div:   "
+divu_msg:  .asciiz "divu:  "
+mul_msg:   .asciiz "mul:   "
+
+.text
+main:
+    li $s0, -6
+    li $s1, 7
+    li $s2, -20
+
+    la $a0, mult_msg
+    jal print_str
+    mult $s0, $s1       # -6 * 7
+    jal print_hilo
+
+    la $a0, multu_msg
+    jal print_str
+    multu $s0, $s1      # 0xfffffffa * 7
+    jal print_hilo
+
+    la $a0, wide_msg
+    jal print_str
+    li $t0, 0x10000
+    mult $t0, $t0       # 2^32
+    jal print_hilo
+
+    la $a0, mthi_msg
+    jal print_str
+    mthi $s1
+    mtlo $zero
+    jal print_hilo
+
+    la $a0, madd_msg
+    jal print_str
+    mthi $s1
+    mtlo $zero
+    madd $s0, $s1       # (7 << 32) + -42
+    jal print_hilo
+
+    la $a0, msub_msg
+    jal print_str
+    msub $s0, $s1       # back to 7 << 32
+    jal print_hilo
+
+    la $a0, maddu_msg
+    jal print_str
+    maddu $s0, $s1      # (7 << 32) + 0xfffffffa * 7
+    jal print_hilo
+
+    la $a0, msubu_msg
+    jal print_str
+    msubu $s0, $s1      # back to 7 << 32
+    jal print_hilo
+
+    la $a0, div_msg
+    jal print_str
+    div $s2, $s1        # -20 / 7
+    jal print_hilo
+
+    la $a0, divu_msg
+    jal print_str
+    divu $s2, $s1       # 0xffffffec / 7
+    jal print_hilo
+
+    la $a0, mul_msg
+    jal print_str
+    mul $a0, $s1, $s1   # does not touch hi or lo
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+
+    li $v0, 10
+    syscall
+
+print_str:
+    li $v0, 4
+    syscall
+    jr $ra
+
+# prints hi and lo without clobbering them
+print_hilo:
+    li $v0, 1
+    mfhi $a0
+    syscall
+    li $v0, 11
+    li $a0, 0x20
+    syscall
+    li $v0, 1
+    mflo $a0
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
@@ -0,0 +1,207 @@
+# Regression test for register jumps and the variable shift and logical instructions
+# Expected output:
+# double: 14
+# square: 49
+# negate: -7
+# jalr rd: 8
+# jump table: two
+# sllv: 80 srlv: 268435455 srav: -1
+# xori: 65530 ori: 32768 andi: 65535
+# subu: -3 nor: -16
+# li: 305432421
+.data
+table:      .space 12
+funcs:      .space 12
+names:      .space 12
+double_msg: .asciiz "double: "
+square_msg: .asciiz "square: "
+negate_msg: .asciiz "negate: "
+jalr_msg:   .asciiz "jalr rd: "
+table_msg:  .asciiz "jump table: "
+one_msg:    .asciiz "one\n"
+two_msg:    .asciiz "two\n"
+three_msg:  .asciiz "three\n"
+sllv_msg:   .asciiz "sllv: "
+srlv_msg:   .asciiz " srlv: "
+srav_msg:   .asciiz " srav: "
+xori_msg:   .asciiz "xori: "
+ori_msg:    .asciiz " ori: "
+andi_msg:   .asciiz " andi: "
+subu_msg:   .asciiz "subu: "
+nor_msg:    .asciiz " nor: "
+li_msg:     .asciiz "li: "
+
+.text
+main:
+    # fill the tables with code and string addresses
+    la $t0, table
+    la $t1, case_one
+    sw $t1, 0($t0)
+    la $t1, case_two
+    sw $t1, 4($t0)
+    la $t1, case_three
+    sw $t1, 8($t0)
+    la $t0, funcs
+    la $t1, double
+    sw $t1, 0($t0)
+    la $t1, square
+    sw $t1, 4($t0)
+    la $t1, negate
+    sw $t1, 8($t0)
+    la $t0, names
+    la $t1, double_msg
+    sw $t1, 0($t0)
+    la $t1, square_msg
+    sw $t1, 4($t0)
+    la $t1, negate_msg
+    sw $t1, 8($t0)
+
+    # call each function through a table of pointers
+    li $s0, 0
+call_loop:
+    la $t0, names
+    add $t0, $t0, $s0
+    lw $a0, 0($t0)
+    li $v0, 4
+    syscall
+    la $t0, funcs
+    add $t0, $t0, $s0
+    lw $t1, 0($t0)
+    li $a0, 7
+    jalr $t1
+    move $a0, $v0
+    li $v0, 1
+    syscall
+    jal newline
+    addi $s0, $s0, 4
+    li $t0, 12
+    bne $s0, $t0, call_loop
+
+    # link into another register
+    li $v0, 4
+    la $a0, jalr_msg
+    syscall
+    la $t0, add_one
+    li $a0, 7
+    jalr $s1, $t0
+    move $a0, $v0
+    li $v0, 1
+    syscall
+    jal newline
+
+    # jump through a table with the target computed right before the jump
+    li $v0, 4
+    la $a0, table_msg
+    syscall
+    li $t0, 1
+    sll $t0, $t0, 2
+    la $t1, table
+    add $t1, $t1, $t0
+    lw $t1, 0($t1)
+    jr $t1
+case_one:
+    la $a0, one_msg
+    j table_done
+case_two:
+    la $a0, two_msg
+    j table_done
+case_three:
+    la $a0, three_msg
+table_done:
+    li $v0, 4
+    syscall
+
+    # variable shifts take the amount from rs
+    li $t0, 5
+    li $t1, 4
+    li $v0, 4
+    la $a0, sllv_msg
+    syscall
+    sllv $a0, $t0, $t1
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, srlv_msg
+    syscall
+    li $t2, -1
+    srlv $a0, $t2, $t1
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, srav_msg
+    syscall
+    srav $a0, $t2, $t1
+    li $v0, 1
+    syscall
+    jal newline
+
+    # logical immediates are zero extended
+    li $v0, 4
+    la $a0, xori_msg
+    syscall
+    xori $a0, $t0, 0xFFFF
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, ori_msg
+    syscall
+    ori $a0, $zero, 0x8000
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, andi_msg
+    syscall
+    andi $a0, $t2, 0xFFFF
+    li $v0, 1
+    syscall
+    jal newline
+
+    li $v0, 4
+    la $a0, subu_msg
+    syscall
+    li $t3, 2
+    subu $a0, $t3, $t0
+    li $v0, 1
+    syscall
+    li $v0, 4
+    la $a0, nor_msg
+    syscall
+    li $t3, 15
+    nor $a0, $t3, $zero
+    li $v0, 1
+    syscall
+    jal newline
+
+    # the low half of large constants no longer sign extends into the upper half
+    li $v0, 4
+    la $a0, li_msg
+    syscall
+    li $a0, 0x12348765
+    li $v0, 1
+    syscall
+    jal newline
+
+    li $v0, 10
+    syscall
+
+double:
+    add $v0, $a0, $a0
+    jr $ra
+
+square:
+    mul $v0, $a0, $a0
+    jr $ra
+
+negate:
+    sub $v0, $zero, $a0
+    jr $ra
+
+add_one:
+    addi $v0, $a0, 1
+    jr $s1
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
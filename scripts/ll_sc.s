@@ -0,0 +1,122 @@
+# Regression test for load linked, store conditional and sync
+# Each sc result is 1 if it stored and 0 if the reservation was lost
+# Expected output:
+# counter: 10
+# after sw: 0 value: 5
+# other sw: 1
+# trap taken
+# after trap: 0
+# without ll: 0
+# twice: 1 0
+.data
+counter:     .word 0
+other:       .word 0
+counter_msg: .asciiz "counter: "
+sw_msg:      .asciiz "after sw: "
+value_msg:   .asciiz " value: "
+other_msg:   .asciiz "other sw: "
+trap_msg:    .asciiz "after trap: "
+without_msg: .asciiz "without ll: "
+twice_msg:   .asciiz "twice: "
+space_msg:   .asciiz " "
+taken_msg:   .asciiz "trap taken\n"
+
+.text
+main:
+    # atomic increment that retries until the store conditional succeeds
+    la $s0, counter
+    li $s1, 10
+increment:
+    ll $t0, 0($s0)
+    addiu $t0, $t0, 1
+    sc $t0, 0($s0)
+    beq $t0, $zero, increment
+    addi $s1, $s1, -1
+    bne $s1, $zero, increment
+    sync
+    la $a0, counter_msg
+    lw $s2, 0($s0)
+    jal print
+
+    # a store to the reserved word breaks the reservation, the sc result is used right away
+    ll $t0, 0($s0)
+    li $t1, 5
+    sw $t1, 0($s0)
+    li $t1, 7
+    sc $t1, 0($s0)
+    move $s2, $t1
+    la $a0, sw_msg
+    jal print_part
+    lw $s2, 0($s0)
+    la $a0, value_msg
+    jal print
+
+    # a store to another word keeps it
+    la $s3, other
+    ll $t0, 0($s0)
+    sw $t0, 0($s3)
+    sc $t0, 0($s0)
+    move $s2, $t0
+    la $a0, other_msg
+    jal print
+
+    # taking an exception clears the reservation
+    ll $t0, 0($s0)
+    teq $zero, $zero
+    sc $t0, 0($s0)
+    move $s2, $t0
+    la $a0, trap_msg
+    jal print
+
+    # sc needs a reservation made by ll
+    sc $t0, 0($s0)
+    move $s2, $t0
+    la $a0, without_msg
+    jal print
+
+    # a successful sc uses up the reservation
+    ll $t0, 0($s0)
+    sc $t0, 0($s0)
+    move $s2, $t0
+    la $a0, twice_msg
+    jal print_part
+    sc $t0, 0($s0)
+    move $s2, $t0
+    la $a0, space_msg
+    jal print
+
+    li $v0, 10
+    syscall
+
+# Prints the string in a0 followed by the integer in s2
+print_part:
+    li $v0, 4
+    syscall
+    li $v0, 1
+    move $a0, $s2
+    syscall
+    jr $ra
+
+# Prints the string in a0 followed by the integer in s2 and a newline
+print:
+    li $v0, 4
+    syscall
+    li $v0, 1
+    move $a0, $s2
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+.ktext
+handler:
+    li $v0, 4
+    la $a0, taken_msg
+    syscall
+
+    # skip the trapping instruction
+    mfc0 $k0, $14
+    addiu $k0, $k0, 4
+    mtc0 $k0, $14
+    eret
@@ -0,0 +1,91 @@
+# Exercises every integer load and store width
+.data
+bytes:  .byte 0x80 0x7f 0xff 0x01
+halves: .half 0x8001 0x7ffe
+word:   .word 0x11223344
+buffer: .word 0 0
+
+.text
+main:
+    la $s0, bytes
+    la $s1, halves
+    la $s2, word
+    la $s3, buffer
+
+    # signed and unsigned bytes: -128 127 255
+    lb $a0, 0($s0)
+    li $v0, 1
+    syscall
+    jal space
+
+    lb $a0, 1($s0)
+    li $v0, 1
+    syscall
+    jal space
+
+    lbu $a0, 2($s0)
+    li $v0, 1
+    syscall
+    jal newline
+
+    # signed and unsigned halves: -32767 32769 32766
+    lh $a0, 0($s1)
+    li $v0, 1
+    syscall
+    jal space
+
+    lhu $a0, 0($s1)
+    li $v0, 1
+    syscall
+    jal space
+
+    lh $a0, 2($s1)
+    li $v0, 1
+    syscall
+    jal newline
+
+    # store a half and a byte then read the word back: 11aa0055
+    li $t0, 0xaa
+    sb $t0, 2($s2)
+    li $t0, 0x55
+    sh $t0, 0($s2)
+    lw $a0, 0($s2)
+    sh $zero, 0($s2)
+    li $v0, 34
+    syscall
+    jal newline
+
+    # unaligned word load from bytes + 1: 101ff7f
+    lwr $a0, 1($s0)
+    lwl $a0, 4($s0)
+    li $v0, 34
+    syscall
+    jal newline
+
+    # unaligned word store to buffer + 3: 78000000 123456
+    li $t0, 0x12345678
+    swr $t0, 3($s3)
+    swl $t0, 6($s3)
+    lw $a0, 0($s3)
+    li $v0, 34
+    syscall
+    jal space
+    lw $a0, 4($s3)
+    li $v0, 34
+    syscall
+    jal newline
+
+    li $v0, 10
+    syscall
+
+space:
+    li $v0, 11
+    li $a0, 0x20
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
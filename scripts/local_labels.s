@@ -0,0 +1,98 @@
+# Regression test for numeric labels with 1b/1f and local labels scoped to the label before them
+# Expected output:
+# sum: 10
+# count: 3
+# found: 1
+# twice: 12
+.data
+values: .word 4, 3, 2, 1
+table:  .word 1f, 2f
+1:      .asciiz "sum: "
+2:      .asciiz "\ncount: "
+found:  .asciiz "\nfound: "
+
+.text
+main:
+    # numeric labels can be defined again, 1b and 1f find the closest one
+    li $t0, 0
+    li $t1, 4
+    la $t2, values
+1:  lw $t3, 0($t2)
+    add $t0, $t0, $t3
+    addi $t2, $t2, 4
+    addi $t1, $t1, -1
+    bgtz $t1, 1b
+    b 1f
+    li $t0, -1          # skipped
+1:  la $t4, table
+    lw $a0, 0($t4)
+    li $v0, 4
+    syscall
+    move $a0, $t0
+    li $v0, 1
+    syscall
+
+    li $a0, 7
+    jal count
+    move $s0, $v0
+    lw $a0, 4($t4)
+    li $v0, 4
+    syscall
+    move $a0, $s0
+    li $v0, 1
+    syscall
+
+    li $a0, 3
+    jal find
+    move $s0, $v0
+    la $a0, found
+    li $v0, 4
+    syscall
+    move $a0, $s0
+    li $v0, 1
+    syscall
+
+    li $a0, 6
+    jal twice
+    move $s0, $v0
+    la $a0, twice.msg
+    li $v0, 4
+    syscall
+    move $a0, $s0
+    li $v0, 1
+    syscall
+
+    li $v0, 10
+    syscall
+
+# counts the bits set in $a0
+count:
+    li $v0, 0
+.loop:
+    beqz $a0, .done
+    andi $t0, $a0, 1
+    add $v0, $v0, $t0
+    srl $a0, $a0, 1
+    b .loop
+.done:
+    jr $ra
+
+# finds the index of $a0 in values, the same local names belong to this function
+find:
+    li $v0, 0
+    la $t0, values
+.loop:
+    lw $t1, 0($t0)
+    beq $t1, $a0, .done
+    addi $v0, $v0, 1
+    addi $t0, $t0, 4
+    b .loop
+.done:
+    jr $ra
+
+twice:
+    add $v0, $a0, $a0
+    jr $ra
+
+.data
+.msg: .asciiz "\ntwice: "
@@ -0,0 +1,91 @@
+# Regression test for macros with parameters, local labels, overloading and nested calls
+# Expected output:
+# sum: 15
+# max: 9 max: 12
+# done 3 times
+# value: 7 value: 42
+.macro print_str(%label)
+    li $v0, 4
+    la $a0, %label
+    syscall
+.end_macro
+
+.macro print_int (%value)
+    li $v0, 1
+    move $a0, %value
+    syscall
+.end_macro
+
+# overloaded by the number of parameters
+.macro print_int(%label, %value)
+    print_str(%label)
+    print_int(%value)
+.end_macro
+
+.macro newline
+    li $v0, 11
+    li $a0, '\n'
+    syscall
+.end_macro
+
+# labels inside a macro are unique for every call
+.macro max(%dest, %a, %b)
+    move %dest, %a
+    bge %a, %b, done
+    move %dest, %b
+done:
+.end_macro
+
+.macro sum_to(%dest, %n)
+    li %dest, 0
+    li $t9, %n
+loop:
+    add %dest, %dest, $t9
+    addi $t9, $t9, -1
+    bgtz $t9, loop
+.end_macro
+
+.data
+sum_msg:   .asciiz "sum: "
+max_msg:   .asciiz "max: "
+space:     .asciiz " "
+done_msg:  .asciiz "done "
+times_msg: .asciiz " times"
+value_msg: .asciiz "value: "
+
+.text
+main:
+    sum_to($s0, 5)
+    print_int(sum_msg, $s0)
+    newline
+
+    li $t0, 9
+    li $t1, 4
+    max($s1, $t0, $t1)
+    print_int(max_msg, $s1)
+    print_str space
+    li $t2, 12
+    max $s1, $t0, $t2
+    print_int(max_msg, $s1)
+    newline
+
+    # a label before a call labels its first line
+    li $s2, 0
+    li $t3, 3
+again: max($t4, $s2, $zero)
+    addi $s2, $s2, 1
+    blt $s2, $t3, again
+    print_str(done_msg)
+    print_int($s2)
+    print_str(times_msg)
+    newline
+
+    li $s3, 7
+    print_int(value_msg, $s3)   # arguments can be labels or registers
+    print_str(space)
+    li $s3, 42
+    print_int(value_msg, $s3)
+    newline
+
+    li $v0, 10
+    syscall
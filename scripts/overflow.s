@@ -0,0 +1,85 @@
+# Regression test for arithmetic exceptions
+# Expected output:
+# addu:  -2147483648
+# subu:  2147483647
+# addiu: -2147483648
+# div:   7 3
+# div0:  7 3
+# then the machine stops with an arithmetic overflow error on the add at line 54
+.data
+addu_msg:  .asciiz "addu:  "
+subu_msg:  .asciiz "subu:  "
+addiu_msg: .asciiz "addiu: "
+div_msg:   .asciiz "div:   "
+div0_msg:  .asciiz "div0:  "
+
+.text
+main:
+    lui $s1, 0x8000         # smallest negative word
+    li $s2, 1
+    subu $s0, $s1, $s2      # largest positive word
+
+    la $a0, addu_msg
+    jal print_str
+    addu $a0, $s0, $s2      # wraps without trapping
+    jal print_int
+
+    la $a0, subu_msg
+    jal print_str
+    subu $a0, $s1, $s2      # wraps without trapping
+    jal print_int
+
+    la $a0, addiu_msg
+    jal print_str
+    addiu $a0, $s0, 1       # wraps without trapping
+    jal print_int
+
+    la $a0, div_msg
+    jal print_str
+    li $t0, 3
+    li $t1, 7
+    mthi $t0
+    mtlo $t1
+    div $s1, $s1            # lo = 1, hi = 0
+    divu $t1, $zero         # dividing by zero leaves hi and lo unchanged
+    mthi $t0
+    mtlo $t1
+    jal print_hilo
+
+    la $a0, div0_msg
+    jal print_str
+    div $s0, $zero          # dividing by zero leaves hi and lo unchanged
+    jal print_hilo
+
+    add $a0, $s0, $s2       # traps
+
+    li $v0, 10
+    syscall
+
+print_str:
+    li $v0, 4
+    syscall
+    jr $ra
+
+print_int:
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+print_hilo:
+    li $v0, 1
+    mflo $a0
+    syscall
+    li $v0, 11
+    li $a0, 0x20
+    syscall
+    li $v0, 1
+    mfhi $a0
+    syscall
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
@@ -0,0 +1,6 @@
+# Macros shared by the files of the program
+.macro count_call
+    lw $t9, calls
+    addi $t9, $t9, 1
+    sw $t9, calls
+.end_macro
@@ -0,0 +1,42 @@
+# Regression test for programs made of several files
+# Assemble together with print.s, strings.s and macros.asm, which print.s includes
+# Expected output:
+# length: 13
+# hello, world!
+# count: 3
+.data
+greeting: .asciiz "hello, world!"
+count:    .word 0
+length_msg: .asciiz "length: "
+count_msg:  .asciiz "count: "
+
+.text
+# the global main of any file is where the program starts, not the start of the text segment
+exit:
+    li $v0, 10
+    syscall
+
+.globl main
+main:
+    la $a0, greeting
+    jal strlen
+    move $s0, $v0
+    la $a0, length_msg
+    jal print_str
+    move $a0, $s0
+    jal print_int
+    jal newline
+
+    la $a0, greeting
+    jal print_str
+    jal newline
+
+    # labels of other files can be used by data and expressions too
+    lw $t0, calls
+    sw $t0, count
+    la $a0, count_msg
+    jal print_str
+    lw $a0, count
+    jal print_int
+    jal newline
+    j exit
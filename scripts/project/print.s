@@ -0,0 +1,27 @@
+# Printing library
+.include "macros.asm"
+.globl print_int, print_str, newline
+.globl calls
+
+.data
+calls: .word 0
+
+.text
+# every function counts how often the library is called
+print_int:
+    count_call
+    li $v0, 1
+    syscall
+    jr $ra
+
+print_str:
+    count_call
+    li $v0, 4
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, '\n'
+    syscall
+    jr $ra
@@ -0,0 +1,15 @@
+# String utilities
+.globl strlen
+
+.text
+# Returns the length of the string at a0 in v0
+strlen:
+    li $v0, 0
+loop:
+    lb $t0, 0($a0)
+    beqz $t0, done
+    addi $v0, $v0, 1
+    addi $a0, $a0, 1
+    b loop
+done:
+    jr $ra
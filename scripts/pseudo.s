@@ -0,0 +1,244 @@
+# Regression test for the MARS compatible pseudo instructions
+# Expected output:
+# not: -6 neg: -7 negu: 7 abs: 9 9
+# div: 3 rem: 2 -2 divu: 4 remu: 15
+# trap
+# mulo: 1000000 mulou: -294967296
+# trap
+# seq: 1 0 sne: 0 1
+# sge: 0 1 sgt: 0 0 sle: 1 1
+# sgeu: 1 sgtu: 1 sleu: 0
+# li: 32768 65535 -32768 305397760 -1 -100000
+# label: 20 30 40 40 40 45
+# ulw: 305419896 ulh: -292 4660 usw: 305419896
+.data
+words:      .word 10, 20, 30, 40
+bytes:      .byte 0, 0x78, 0x56, 0x34, 0x12, 0xdc, 0xfe, 0
+not_msg:    .asciiz "not: "
+neg_msg:    .asciiz " neg: "
+negu_msg:   .asciiz " negu: "
+abs_msg:    .asciiz " abs: "
+div_msg:    .asciiz "div: "
+rem_msg:    .asciiz " rem: "
+divu_msg:   .asciiz " divu: "
+remu_msg:   .asciiz " remu: "
+mulo_msg:   .asciiz "mulo: "
+mulou_msg:  .asciiz " mulou: "
+seq_msg:    .asciiz "seq: "
+sne_msg:    .asciiz " sne: "
+sge_msg:    .asciiz "sge: "
+sgt_msg:    .asciiz " sgt: "
+sle_msg:    .asciiz " sle: "
+sgeu_msg:   .asciiz "sgeu: "
+sgtu_msg:   .asciiz " sgtu: "
+sleu_msg:   .asciiz " sleu: "
+li_msg:     .asciiz "li: "
+label_msg:  .asciiz "label: "
+ulw_msg:    .asciiz "ulw: "
+ulh_msg:    .asciiz " ulh: "
+usw_msg:    .asciiz " usw: "
+space:      .asciiz " "
+trap_msg:   .asciiz "trap\n"
+
+.text
+main:
+    # single operand arithmetic
+    li $t0, 5
+    not $a0, $t0
+    la $s0, not_msg
+    jal print
+    li $t0, 7
+    neg $a0, $t0
+    la $s0, neg_msg
+    jal print
+    li $t0, -7
+    negu $a0, $t0
+    la $s0, negu_msg
+    jal print
+    li $t0, -9
+    abs $a0, $t0
+    la $s0, abs_msg
+    jal print
+    li $t0, 9
+    abs $a0, $t0
+    la $s0, space
+    jal print
+    jal newline
+
+    # division into a register with registers or constants
+    li $t0, 17
+    li $t1, 5
+    div $a0, $t0, $t1
+    la $s0, div_msg
+    jal print
+    rem $a0, $t0, $t1
+    la $s0, rem_msg
+    jal print
+    li $t0, -17
+    rem $a0, $t0, 5
+    la $s0, space
+    jal print
+    li $t0, 17
+    divu $a0, $t0, 4
+    la $s0, divu_msg
+    jal print
+    li $t0, -1
+    remu $a0, $t0, 16
+    la $s0, remu_msg
+    jal print
+    jal newline
+
+    # dividing by zero traps
+    li $t1, 0
+    div $a0, $t0, $t1
+
+    # multiplies that trap on overflow
+    li $t0, 1000
+    mulo $a0, $t0, $t0
+    la $s0, mulo_msg
+    jal print
+    li $t0, 2000000000
+    mulou $a0, $t0, 2
+    la $s0, mulou_msg
+    jal print
+    jal newline
+    li $t0, 0x10000
+    mulo $a0, $t0, $t0
+
+    # set on comparisons
+    li $t0, -1
+    li $t1, 1
+    seq $a0, $t0, -1
+    la $s0, seq_msg
+    jal print
+    seq $a0, $t0, $t1
+    la $s0, space
+    jal print
+    sne $a0, $t1, 1
+    la $s0, sne_msg
+    jal print
+    sne $a0, $t0, $t1
+    la $s0, space
+    jal print
+    jal newline
+    sge $a0, $t0, $t1
+    la $s0, sge_msg
+    jal print
+    sge $a0, $t1, $t1
+    la $s0, space
+    jal print
+    sgt $a0, $t0, $t1
+    la $s0, sgt_msg
+    jal print
+    sgt $a0, $t1, $t1
+    la $s0, space
+    jal print
+    sle $a0, $t0, $t1
+    la $s0, sle_msg
+    jal print
+    sle $a0, $t1, 1
+    la $s0, space
+    jal print
+    jal newline
+    sgeu $a0, $t0, $t1
+    la $s0, sgeu_msg
+    jal print
+    sgtu $a0, $t0, $t1
+    la $s0, sgtu_msg
+    jal print
+    sleu $a0, $t0, $t1
+    la $s0, sleu_msg
+    jal print
+    jal newline
+
+    # constants that need the upper half or a sign extension
+    li $a0, 0x8000
+    la $s0, li_msg
+    jal print
+    li $a0, 0xFFFF
+    la $s0, space
+    jal print
+    li $a0, -32768
+    jal print
+    li $a0, 0x12340000
+    jal print
+    li $a0, 0xFFFFFFFF
+    jal print
+    li $a0, -100000
+    jal print
+    jal newline
+
+    # loads and stores addressed by labels go through $at
+    lw $a0, words+4
+    la $s0, label_msg
+    jal print
+    li $t1, 8
+    lw $a0, words($t1)
+    la $s0, space
+    jal print
+    la $t0, words+12
+    lw $a0, ($t0)
+    jal print
+    lw $t2, 0($t0)
+    sw $t2, words + 8
+    lw $a0, words+8
+    jal print
+    la $t0, words
+    lw $a0, 12($t0)
+    jal print
+    la $t0, 5($t0)
+    lw $a0, -5($t0)
+    addi $a0, $a0, 35
+    jal print
+    jal newline
+
+    # unaligned accesses
+    la $t0, bytes
+    ulw $a0, 1($t0)
+    la $s0, ulw_msg
+    jal print
+    ulh $a0, 5($t0)
+    la $s0, ulh_msg
+    jal print
+    ulh $a0, bytes+3
+    la $s0, space
+    jal print
+    ulw $t1, 1($t0)
+    usw $t1, 2($t0)
+    ulw $t0, 2($t0)
+    move $a0, $t0
+    la $s0, usw_msg
+    jal print
+    jal newline
+
+    li $v0, 10
+    syscall
+
+# Prints the string in s0 followed by the integer in a0
+print:
+    move $t9, $a0
+    li $v0, 4
+    move $a0, $s0
+    syscall
+    li $v0, 1
+    move $a0, $t9
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
+
+.ktext
+handler:
+    li $v0, 4
+    la $a0, trap_msg
+    syscall
+
+    # skip the trapping instruction
+    mfc0 $k0, $14
+    addiu $k0, $k0, 4
+    mtc0 $k0, $14
+    eret
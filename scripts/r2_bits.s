@@ -0,0 +1,137 @@
+# Regression test for the MIPS32 Release 2 bit manipulation and conditional move instructions
+# Expected output:
+# movz: 7 movn: 3
+# movt: 1 movf: 2
+# clz: 15 clo: 12 clz 0: 32
+# seb: -128 seh: -32768 wsbh: 571556915
+# ext: 35 ins: -3841
+# rotr: -1073741824 rotrv: -2128394905
+.data
+movz_msg:  .asciiz "movz: "
+movn_msg:  .asciiz " movn: "
+movt_msg:  .asciiz "movt: "
+movf_msg:  .asciiz " movf: "
+clz_msg:   .asciiz "clz: "
+clo_msg:   .asciiz " clo: "
+clz0_msg:  .asciiz " clz 0: "
+seb_msg:   .asciiz "seb: "
+seh_msg:   .asciiz " seh: "
+wsbh_msg:  .asciiz " wsbh: "
+ext_msg:   .asciiz "ext: "
+ins_msg:   .asciiz " ins: "
+rotr_msg:  .asciiz "rotr: "
+rotrv_msg: .asciiz " rotrv: "
+
+.text
+main:
+    # conditional moves test the forwarded rt
+    li $t0, 7
+    li $t1, 3
+    li $a0, 0
+    li $t2, 0
+    movz $a0, $t0, $t2
+    li $t2, 5
+    movz $a0, $t1, $t2
+    la $s0, movz_msg
+    jal print
+
+    li $a0, 0
+    li $t2, 0
+    movn $a0, $t0, $t2
+    li $t2, 5
+    movn $a0, $t1, $t2
+    la $s0, movn_msg
+    jal print
+    jal newline
+
+    # moves on a floating point condition flag
+    li $t0, 1
+    li $t1, 2
+    mtc1 $t0, $f0
+    mtc1 $t1, $f2
+    cvt.s.w $f0, $f0
+    cvt.s.w $f2, $f2
+    c.lt.s 3, $f0, $f2
+    li $a0, 0
+    movt $a0, $t0, 3
+    movf $a0, $t1, 3
+    la $s0, movt_msg
+    jal print
+    li $a0, 0
+    movt $a0, $t0
+    movf $a0, $t1
+    la $s0, movf_msg
+    jal print
+    jal newline
+
+    # counting leading bits
+    li $t0, 0x00010000
+    clz $a0, $t0
+    la $s0, clz_msg
+    jal print
+    li $t0, 0xFFF00000
+    clo $a0, $t0
+    la $s0, clo_msg
+    jal print
+    clz $a0, $zero
+    la $s0, clz0_msg
+    jal print
+    jal newline
+
+    # byte shuffles
+    li $t0, 0x80
+    seb $a0, $t0
+    la $s0, seb_msg
+    jal print
+    li $t0, 0x18000
+    seh $a0, $t0
+    la $s0, seh_msg
+    jal print
+    li $t0, 0x11223344
+    wsbh $a0, $t0
+    la $s0, wsbh_msg
+    jal print
+    jal newline
+
+    # bit fields
+    li $t0, 0xABCD1234
+    ext $a0, $t0, 4, 8
+    la $s0, ext_msg
+    jal print
+    li $a0, -1
+    ins $a0, $zero, 8, 4
+    la $s0, ins_msg
+    jal print
+    jal newline
+
+    # rotates
+    li $t0, 0x80000001
+    rotr $a0, $t0, 1
+    la $s0, rotr_msg
+    jal print
+    li $t0, 0x12345678
+    li $t1, 36
+    rotrv $a0, $t0, $t1
+    la $s0, rotrv_msg
+    jal print
+    jal newline
+
+    li $v0, 10
+    syscall
+
+# Prints the string in s0 followed by the integer in a0
+print:
+    move $t9, $a0
+    li $v0, 4
+    move $a0, $s0
+    syscall
+    li $v0, 1
+    move $a0, $t9
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
@@ -0,0 +1,78 @@
+# Regression test for signed and unsigned set-on-less-than
+# Expected output:
+# slt:   1 0 0
+# sltu:  0 1 0
+# slti:  1 0 1
+# sltiu: 0 1 1
+.data
+slt_msg:   .asciiz "slt:   "
+sltu_msg:  .asciiz "sltu:  "
+slti_msg:  .asciiz "slti:  "
+sltiu_msg: .asciiz "sltiu: "
+
+.text
+main:
+    li $s0, -5
+    li $s1, 3
+
+    la $a0, slt_msg
+    jal print_str
+    slt $a0, $s0, $s1   # -5 < 3
+    jal print_int
+    slt $a0, $s1, $s0   # 3 < -5
+    jal print_int
+    slt $a0, $s0, $s0   # -5 < -5
+    jal print_int
+    jal newline
+
+    la $a0, sltu_msg
+    jal print_str
+    sltu $a0, $s0, $s1  # 0xfffffffb < 3
+    jal print_int
+    sltu $a0, $s1, $s0  # 3 < 0xfffffffb
+    jal print_int
+    sltu $a0, $s1, $s1  # 3 < 3
+    jal print_int
+    jal newline
+
+    la $a0, slti_msg
+    jal print_str
+    slti $a0, $s0, -4   # -5 < -4
+    jal print_int
+    slti $a0, $s1, -4   # 3 < -4
+    jal print_int
+    slti $a0, $s0, 0    # -5 < 0
+    jal print_int
+    jal newline
+
+    la $a0, sltiu_msg
+    jal print_str
+    sltiu $a0, $s0, 3   # 0xfffffffb < 3
+    jal print_int
+    sltiu $a0, $s1, -1  # 3 < 0xffffffff
+    jal print_int
+    sltiu $a0, $zero, 1 # 0 < 1
+    jal print_int
+    jal newline
+
+    li $v0, 10
+    syscall
+
+print_str:
+    li $v0, 4
+    syscall
+    jr $ra
+
+print_int:
+    li $v0, 1
+    syscall
+    li $v0, 11
+    li $a0, 0x20
+    syscall
+    jr $ra
+
+newline:
+    li $v0, 11
+    li $a0, 0xA
+    syscall
+    jr $ra
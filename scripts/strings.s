@@ -0,0 +1,111 @@
+# Regression test for string escapes, UTF-8 strings and .ascii packing its strings together,
+# checking the bytes written into memory
+# Expected output:
+# packed: 4 abcd
+# escapes: 9 34 113 34 92 39 65 65 10 0 122 0
+# utf-8: 195 169 226 134 146 0 é→
+# quote: It's "quoted"
+# after: 7 at 0
+.data
+table:   .ascii "ab", "c"
+         .ascii "d"
+escapes: .asciiz "\t\"q\"\\\'\x41\101\012\0z"
+utf:     .asciiz "é→"
+quote:   .asciiz "It's \"quoted\"\n"
+        # .word is still aligned after strings of any length
+number:  .word 7
+end:
+
+.text
+main:
+    # the strings of .ascii follow each other without padding
+    la $a0, packed_msg
+    li $v0, 4
+    syscall
+    la $t0, table
+    la $t1, escapes
+    sub $a0, $t1, $t0
+    li $v0, 1
+    syscall
+    li $a0, ' '
+    li $v0, 11
+    syscall
+    move $s0, $t0
+    move $s1, $t1
+    jal print_chars
+
+    la $a0, escapes_msg
+    li $v0, 4
+    syscall
+    la $s0, escapes
+    la $s1, utf
+    jal print_bytes
+
+    la $a0, utf_msg
+    li $v0, 4
+    syscall
+    la $s0, utf
+    la $s1, quote
+    jal print_bytes
+    li $a0, ' '
+    li $v0, 11
+    syscall
+    la $a0, utf
+    li $v0, 4
+    syscall
+
+    la $a0, quote_msg
+    li $v0, 4
+    syscall
+    la $a0, quote
+    li $v0, 4
+    syscall
+
+    la $a0, after_msg
+    li $v0, 4
+    syscall
+    lw $a0, number
+    li $v0, 1
+    syscall
+    la $a0, at_msg
+    li $v0, 4
+    syscall
+    la $t0, number
+    andi $a0, $t0, 3
+    li $v0, 1
+    syscall
+
+    li $v0, 10
+    syscall
+
+# prints the bytes from $s0 up to $s1 as numbers
+print_bytes:
+    lbu $a0, 0($s0)
+    li $v0, 1
+    syscall
+    addi $s0, $s0, 1
+    beq $s0, $s1, 1f
+    li $a0, ' '
+    li $v0, 11
+    syscall
+    b print_bytes
+1:  jr $ra
+
+# prints the bytes from $s0 up to $s1 as characters
+print_chars:
+    lbu $a0, 0($s0)
+    li $v0, 11
+    syscall
+    addi $s0, $s0, 1
+    bne $s0, $s1, print_chars
+    li $a0, '\n'
+    syscall
+    jr $ra
+
+.data
+packed_msg:  .asciiz "packed: "
+escapes_msg: .asciiz "escapes: "
+utf_msg:     .asciiz "\nutf-8: "
+quote_msg:   .asciiz "\nquote: "
+after_msg:   .asciiz "after: "
+at_msg:      .asciiz " at "
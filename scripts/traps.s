@@ -0,0 +1,69 @@
+# Regression test for the trap instructions and break
+# Every trap below is listed with the number of traps taken so far once it has run.
+# Expected output:
+# traps: 12
+# before break
+# (the machine pauses at the break on line 54, running again continues)
+# after break
+.data
+trap_msg:   .asciiz "traps: "
+before_msg: .asciiz "\nbefore break\n"
+after_msg:  .asciiz "after break\n"
+
+.text
+main:
+    li $s0, -5
+    li $s1, 3
+
+    teq $s1, $s1        # 1
+    teq $s0, $s1
+    tne $s0, $s1        # 2
+    tne $s1, $s1
+    tlt $s0, $s1        # 3
+    tlt $s1, $s0
+    tltu $s1, $s0       # 4
+    tltu $s0, $s1
+    tge $s1, $s0        # 5
+    tge $s0, $s1
+    tgeu $s0, $s1       # 6
+    tgeu $s1, $s0
+
+    teqi $s0, -5        # 7
+    teqi $s0, 5
+    tnei $s1, 4         # 8
+    tnei $s1, 3
+    tlti $s0, -4        # 9
+    tlti $s1, -4
+    tltiu $s1, -1       # 10
+    tltiu $s0, 3
+    tgei $s1, 3         # 11
+    tgei $s0, 3
+    tgeiu $s0, 3        # 12
+    tgeiu $s1, 4
+
+    la $a0, trap_msg
+    li $v0, 4
+    syscall
+    move $a0, $s7
+    li $v0, 1
+    syscall
+
+    la $a0, before_msg
+    li $v0, 4
+    syscall
+    break
+    la $a0, after_msg
+    li $v0, 4
+    syscall
+
+    li $v0, 10
+    syscall
+
+.ktext
+handler:
+    # count the trap and skip the trapping instruction
+    addiu $s7, $s7, 1
+    mfc0 $k0, $14
+    addiu $k0, $k0, 4
+    mtc0 $k0, $14
+    eret
@@ -0,0 +1,61 @@
+//! Assembles a program into an ELF32 MIPS executable
+//!
+//! Usage: `cargo run --example elf -- [--big-endian] <out.elf> <main.s> [<file.s>...]`
+//!
+//! The first script is the main script of the program and the others are linked after it. The
+//! executable is little-endian unless `--big-endian` is given.
+
+use std::process::ExitCode;
+
+use model::{
+    elf::{self, Endian},
+    link_listing, Source,
+};
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let endian = match args.first().map(String::as_str) {
+        Some("--big-endian") => {
+            args.remove(0);
+            Endian::Big
+        }
+        _ => Endian::Little,
+    };
+    if args.len() < 2 {
+        eprintln!("usage: elf [--big-endian] <out.elf> <main.s> [<file.s>...]");
+        return ExitCode::FAILURE;
+    }
+    let out = args.remove(0);
+
+    let mut sources = vec![];
+    for path in args {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => sources.push(Source::new(Some(path.into()), text)),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let (memory, labels, listing) = match link_listing(sources) {
+        Ok((memory, labels, listing, warnings)) => {
+            for warning in warnings {
+                eprintln!("{warning}");
+            }
+            (memory, labels, listing)
+        }
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{diagnostic}");
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    let executable = elf::write(&memory, &labels, &listing, endian);
+    if let Err(err) = std::fs::write(&out, executable) {
+        eprintln!("{out}: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,57 @@
+//! Prints the listing of a program, or its symbol table as text or JSON
+//!
+//! Usage: `cargo run --example listing -- [--symbols | --json] <main.s> [<file.s>...]`
+//!
+//! The first script is the main script of the program and the others are linked after it.
+
+use std::process::ExitCode;
+
+use model::{link_listing, Source, SymbolTable};
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let format = match args.first().map(String::as_str) {
+        Some(flag @ ("--symbols" | "--json")) => {
+            let flag = flag.to_string();
+            args.remove(0);
+            Some(flag)
+        }
+        _ => None,
+    };
+    if args.is_empty() {
+        eprintln!("usage: listing [--symbols | --json] <main.s> [<file.s>...]");
+        return ExitCode::FAILURE;
+    }
+
+    let mut sources = vec![];
+    for path in args {
+        match std::fs::read_to_string(&path) {
+            Ok(text) => sources.push(Source::new(Some(path.into()), text)),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let (labels, listing) = match link_listing(sources) {
+        Ok((_, labels, listing, warnings)) => {
+            for warning in warnings {
+                eprintln!("{warning}");
+            }
+            (labels, listing)
+        }
+        Err(diagnostics) => {
+            for diagnostic in diagnostics {
+                eprintln!("{diagnostic}");
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    match format.as_deref() {
+        Some("--symbols") => print!("{}", SymbolTable::new(&labels)),
+        Some(_) => println!("{}", SymbolTable::new(&labels).to_json()),
+        None => print!("{listing}"),
+    }
+    ExitCode::SUCCESS
+}
@@ -1,4 +1,4 @@
-use crate::{parser::opcode_name, Machine};
+use crate::Machine;
 
 pub struct PipelineView<'a> {
     machine: &'a mut Machine,
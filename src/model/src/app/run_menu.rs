@@ -41,8 +41,9 @@ impl<'a> RunMenu<'a> {
 
             let (mem, sym) = match assembler(self.script) {
                 Ok(asm) => asm,
-                Err(e) => {
-                    self.console.error(&format!("{e}"));
+                Err(diagnostics) => {
+                    let report: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+                    self.console.error(&report.join("\n"));
                     (Memory::default(), LabelTable::default())
                 }
             };
@@ -89,8 +90,9 @@ impl<'a> RunMenu<'a> {
 
             let (mem, sym) = match assembler(self.script) {
                 Ok(asm) => asm,
-                Err(e) => {
-                    self.console.error(&format!("{e}"));
+                Err(diagnostics) => {
+                    let report: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+                    self.console.error(&report.join("\n"));
                     (Memory::default(), LabelTable::default())
                 }
             };
@@ -0,0 +1,520 @@
+//! Turns machine words back into assembly
+//!
+//! Instructions are written the way the assembler reads them, so assembling the text at the same
+//! address gives back the same word. Branch and jump targets are named by the closest label
+//! before them, and words that are not an instruction the assembler makes become `.word`.
+
+use crate::parser::model::{LabelTable, Opcode};
+use crate::Register;
+
+/// The fields of an instruction word
+struct Fields {
+    op: u32,
+    rs: u32,
+    rt: u32,
+    rd: u32,
+    sa: u32,
+    funct: u32,
+    imm: u32,
+    target: u32,
+}
+
+impl Fields {
+    fn new(word: u32) -> Self {
+        Self {
+            op: word >> 26,
+            rs: (word >> 21) & 0x1f,
+            rt: (word >> 16) & 0x1f,
+            rd: (word >> 11) & 0x1f,
+            sa: (word >> 6) & 0x1f,
+            funct: word & 0x3f,
+            imm: word & 0xffff,
+            target: word & 0x03ff_ffff,
+        }
+    }
+
+    /// The immediate sign extended
+    fn simm(&self) -> i32 {
+        self.imm as u16 as i16 as i32
+    }
+}
+
+/// How the operands of an instruction are written
+#[derive(Clone, Copy)]
+enum Format {
+    /// No operands
+    None,
+    /// `<rd>, <rs>, <rt>`
+    Arithmetic,
+    /// `<rs>, <rt>`
+    Pair,
+    /// `<rd>`
+    Destination,
+    /// `<rs>`
+    Source,
+    /// `[<rd>,] <rs>` where rd is left out when it is $ra
+    Jalr,
+    /// `<rd>, <rt>, <sa>`
+    Shift,
+    /// `<rd>, <rt>, <sa>` with rs set to 1
+    Rotate,
+    /// `<rd>, <rt>, <rs>`
+    ShiftVariable,
+    /// `<rd>, <rt>, <rs>` with sa set to 1
+    RotateVariable,
+    /// `<rd>, <rs>` with rt repeating rd
+    CountLeading,
+    /// `<rd>, <rt>`
+    Shuffle,
+    /// `<rt>, <rs>, <pos>, <size>` where rd holds the size less one
+    Extract,
+    /// `<rt>, <rs>, <pos>, <size>` where rd holds the position of the last bit
+    Insert,
+    /// `<rd>, <rs>[, <cc>]` where rt holds the flag and whether to move on true
+    CondMove,
+    /// `<rt>, <rs>, <imm>`
+    Immediate,
+    /// `<rt>, <imm>`
+    Lui,
+    /// `<rt>, <offset>(<rs>)`
+    Memory,
+    /// `<ft>, <offset>(<rs>)`
+    FloatMemory,
+    /// `<rt>, <rs>, <target>`
+    Branch,
+    /// `<rs>, <target>`
+    BranchZero,
+    /// `<rs>, <imm>`
+    TrapImmediate,
+    /// `<target>`
+    Jump,
+    /// `[<code>]`
+    Break,
+    /// `<rt>, <cop0 register>`
+    Cop0Move,
+    /// `<fd>, <fs>, <ft>`
+    FloatArithmetic,
+    /// `<fd>, <fs>`
+    FloatUnary,
+    /// `[<cc>,] <fs>, <ft>`
+    FloatCompare,
+    /// `[<cc>,] <target>`
+    FloatBranch,
+    /// `<rt>, <fs>`
+    Cop1Move,
+    /// `<rt>, <control register>`
+    Cop1Control,
+}
+
+/// Disassembles a word at address `pc`, naming branch and jump targets by the labels in `labels`
+///
+/// Words the assembler can't make from an instruction are written as `.word`.
+pub fn disassemble(word: u32, pc: u32, labels: &LabelTable) -> String {
+    instruction(word, pc, labels).unwrap_or_else(|| format!(".word 0x{word:08x}"))
+}
+
+fn instruction(word: u32, pc: u32, labels: &LabelTable) -> Option<String> {
+    if word == 0 {
+        return Some("nop".to_string());
+    }
+
+    let f = Fields::new(word);
+    let (opcode, name, format) = lookup(&f)?;
+    if encode(opcode, format, &f) != word {
+        return None;
+    }
+
+    let gpr = |n: u32| format!("${}", String::from(Register::from(n)));
+    let fpr = |n: u32| format!("${}", String::from(Register::from(32 + n)));
+    let branch = || {
+        let target = pc.wrapping_add(4).wrapping_add((f.simm() << 2) as u32);
+        // an offset without a label still assembles to the same branch
+        target_name(target, labels).unwrap_or_else(|| f.simm().to_string())
+    };
+    let flag = |cc: u32| match cc {
+        0 => String::new(),
+        cc => format!("{cc}, "),
+    };
+
+    let operands = match format {
+        Format::None => String::new(),
+        Format::Arithmetic => format!("{}, {}, {}", gpr(f.rd), gpr(f.rs), gpr(f.rt)),
+        Format::Pair => format!("{}, {}", gpr(f.rs), gpr(f.rt)),
+        Format::Destination => gpr(f.rd),
+        Format::Source => gpr(f.rs),
+        Format::Jalr if f.rd == Register::RA.number() => gpr(f.rs),
+        Format::Jalr => format!("{}, {}", gpr(f.rd), gpr(f.rs)),
+        Format::Shift | Format::Rotate => format!("{}, {}, {}", gpr(f.rd), gpr(f.rt), f.sa),
+        Format::ShiftVariable | Format::RotateVariable => {
+            format!("{}, {}, {}", gpr(f.rd), gpr(f.rt), gpr(f.rs))
+        }
+        Format::CountLeading => format!("{}, {}", gpr(f.rd), gpr(f.rs)),
+        Format::Shuffle => format!("{}, {}", gpr(f.rd), gpr(f.rt)),
+        Format::Extract | Format::Insert => {
+            let size = match format {
+                Format::Extract => f.rd + 1,
+                _ => (f.rd + 1).checked_sub(f.sa).filter(|size| *size > 0)?,
+            };
+            if f.sa + size > 32 {
+                return None;
+            }
+            format!("{}, {}, {}, {}", gpr(f.rt), gpr(f.rs), f.sa, size)
+        }
+        Format::CondMove => match f.rt >> 2 {
+            0 => format!("{}, {}", gpr(f.rd), gpr(f.rs)),
+            cc => format!("{}, {}, {cc}", gpr(f.rd), gpr(f.rs)),
+        },
+        Format::Immediate if opcode.zero_extends() => {
+            format!("{}, {}, 0x{:x}", gpr(f.rt), gpr(f.rs), f.imm)
+        }
+        Format::Immediate => format!("{}, {}, {}", gpr(f.rt), gpr(f.rs), f.simm()),
+        Format::Lui => format!("{}, 0x{:x}", gpr(f.rt), f.imm),
+        Format::Memory => format!("{}, {}({})", gpr(f.rt), f.simm(), gpr(f.rs)),
+        Format::FloatMemory => format!("{}, {}({})", fpr(f.rt), f.simm(), gpr(f.rs)),
+        Format::Branch => format!("{}, {}, {}", gpr(f.rt), gpr(f.rs), branch()),
+        Format::BranchZero => format!("{}, {}", gpr(f.rs), branch()),
+        Format::TrapImmediate => format!("{}, {}", gpr(f.rs), f.simm()),
+        Format::Jump => {
+            let target = (pc.wrapping_add(4) & 0xF000_0000) | (f.target << 2);
+            target_name(target, labels).unwrap_or_else(|| format!("0x{target:08x}"))
+        }
+        Format::Break => match (word >> 6) & 0xfffff {
+            0 => String::new(),
+            code => code.to_string(),
+        },
+        Format::Cop0Move => {
+            let reg = Register::from_cop0(f.rd)?;
+            format!("{}, ${}", gpr(f.rt), String::from(reg))
+        }
+        Format::FloatArithmetic => format!("{}, {}, {}", fpr(f.sa), fpr(f.rd), fpr(f.rt)),
+        Format::FloatUnary => format!("{}, {}", fpr(f.sa), fpr(f.rd)),
+        Format::FloatCompare => format!("{}{}, {}", flag(f.sa >> 2), fpr(f.rd), fpr(f.rt)),
+        Format::FloatBranch => format!("{}{}", flag(f.rt >> 2), branch()),
+        Format::Cop1Move => format!("{}, {}", gpr(f.rt), fpr(f.rd)),
+        Format::Cop1Control => {
+            let reg = Register::from_fcr(f.rd)?;
+            format!("{}, ${}", gpr(f.rt), String::from(reg))
+        }
+    };
+
+    Some(match operands.is_empty() {
+        true => name.to_string(),
+        false => format!("{name} {operands}"),
+    })
+}
+
+/// Names an address by the closest label before it, like `main` or `main + 0x8`
+fn target_name(address: u32, labels: &LabelTable) -> Option<String> {
+    labels
+        .label_before(address)
+        .map(|(name, offset)| match offset {
+            0 => name.to_string(),
+            offset => format!("{name} + 0x{offset:x}"),
+        })
+}
+
+/// Finds the opcode of a word as the assembler writes it, with its mnemonic and operands
+fn lookup(f: &Fields) -> Option<(Opcode, &'static str, Format)> {
+    // fields that select an instruction but are not part of its opcode are left out, so they
+    // have to be zero when the word is encoded again
+    let opcode = match f.op {
+        0x00 => Opcode::Funct(f.funct as u8),
+        0x01 => Opcode::RegImm(f.rt as u8),
+        0x10 if f.rs == 0x10 => Opcode::Cop0 {
+            rs: f.rs as u8,
+            funct: f.funct as u8,
+        },
+        0x10 => Opcode::Cop0 {
+            rs: f.rs as u8,
+            funct: 0,
+        },
+        // COP1 branches are selected by their true/false bit instead of a funct
+        0x11 if f.rs == 0x08 => Opcode::Cop1 {
+            fmt: f.rs as u8,
+            funct: (f.rt & 1) as u8,
+        },
+        0x11 if f.rs < 0x08 => Opcode::Cop1 {
+            fmt: f.rs as u8,
+            funct: 0,
+        },
+        0x11 => Opcode::Cop1 {
+            fmt: f.rs as u8,
+            funct: f.funct as u8,
+        },
+        0x1c => Opcode::Special2(f.funct as u8),
+        // byte shuffles are selected by sa while ext and ins keep a field position in it
+        0x1f => Opcode::Special3 {
+            funct: f.funct as u8,
+            sa: if f.funct == 0x20 { f.sa as u8 } else { 0 },
+        },
+        op => Opcode::Op(op as u8),
+    };
+
+    let (name, format) = match opcode {
+        Opcode::Funct(funct) => match funct {
+            0x00 => ("sll", Format::Shift),
+            0x01 if f.rt & 1 == 0 => ("movf", Format::CondMove),
+            0x01 => ("movt", Format::CondMove),
+            0x02 if f.rs == 1 => ("rotr", Format::Rotate),
+            0x02 => ("srl", Format::Shift),
+            0x03 => ("sra", Format::Shift),
+            0x04 => ("sllv", Format::ShiftVariable),
+            0x06 if f.sa == 1 => ("rotrv", Format::RotateVariable),
+            0x06 => ("srlv", Format::ShiftVariable),
+            0x07 => ("srav", Format::ShiftVariable),
+            0x08 => ("jr", Format::Source),
+            0x09 => ("jalr", Format::Jalr),
+            0x0a => ("movz", Format::Arithmetic),
+            0x0b => ("movn", Format::Arithmetic),
+            0x0c => ("syscall", Format::None),
+            0x0d => ("break", Format::Break),
+            0x0f => ("sync", Format::None),
+            0x10 => ("mfhi", Format::Destination),
+            0x11 => ("mthi", Format::Source),
+            0x12 => ("mflo", Format::Destination),
+            0x13 => ("mtlo", Format::Source),
+            0x18 => ("mult", Format::Pair),
+            0x19 => ("multu", Format::Pair),
+            0x1a => ("div", Format::Pair),
+            0x1b => ("divu", Format::Pair),
+            0x20 => ("add", Format::Arithmetic),
+            0x21 => ("addu", Format::Arithmetic),
+            0x22 => ("sub", Format::Arithmetic),
+            0x23 => ("subu", Format::Arithmetic),
+            0x24 => ("and", Format::Arithmetic),
+            0x25 => ("or", Format::Arithmetic),
+            0x26 => ("xor", Format::Arithmetic),
+            0x27 => ("nor", Format::Arithmetic),
+            0x2a => ("slt", Format::Arithmetic),
+            0x2b => ("sltu", Format::Arithmetic),
+            0x30 => ("tge", Format::Pair),
+            0x31 => ("tgeu", Format::Pair),
+            0x32 => ("tlt", Format::Pair),
+            0x33 => ("tltu", Format::Pair),
+            0x34 => ("teq", Format::Pair),
+            0x36 => ("tne", Format::Pair),
+            _ => return None,
+        },
+        Opcode::Op(op) => match op {
+            0x02 => ("j", Format::Jump),
+            0x03 => ("jal", Format::Jump),
+            0x04 => ("beq", Format::Branch),
+            0x05 => ("bne", Format::Branch),
+            0x06 => ("blez", Format::BranchZero),
+            0x07 => ("bgtz", Format::BranchZero),
+            0x08 => ("addi", Format::Immediate),
+            0x09 => ("addiu", Format::Immediate),
+            0x0a => ("slti", Format::Immediate),
+            0x0b => ("sltiu", Format::Immediate),
+            0x0c => ("andi", Format::Immediate),
+            0x0d => ("ori", Format::Immediate),
+            0x0e => ("xori", Format::Immediate),
+            0x0f => ("lui", Format::Lui),
+            0x20 => ("lb", Format::Memory),
+            0x21 => ("lh", Format::Memory),
+            0x22 => ("lwl", Format::Memory),
+            0x23 => ("lw", Format::Memory),
+            0x24 => ("lbu", Format::Memory),
+            0x25 => ("lhu", Format::Memory),
+            0x26 => ("lwr", Format::Memory),
+            0x28 => ("sb", Format::Memory),
+            0x29 => ("sh", Format::Memory),
+            0x2a => ("swl", Format::Memory),
+            0x2b => ("sw", Format::Memory),
+            0x2e => ("swr", Format::Memory),
+            0x30 => ("ll", Format::Memory),
+            0x31 => ("lwc1", Format::FloatMemory),
+            0x35 => ("ldc1", Format::FloatMemory),
+            0x38 => ("sc", Format::Memory),
+            0x39 => ("swc1", Format::FloatMemory),
+            0x3d => ("sdc1", Format::FloatMemory),
+            _ => return None,
+        },
+        Opcode::RegImm(rt) => match rt {
+            0x00 => ("bltz", Format::BranchZero),
+            0x01 => ("bgez", Format::BranchZero),
+            0x08 => ("tgei", Format::TrapImmediate),
+            0x09 => ("tgeiu", Format::TrapImmediate),
+            0x0a => ("tlti", Format::TrapImmediate),
+            0x0b => ("tltiu", Format::TrapImmediate),
+            0x0c => ("teqi", Format::TrapImmediate),
+            0x0e => ("tnei", Format::TrapImmediate),
+            0x10 => ("bltzal", Format::BranchZero),
+            0x11 => ("bgezal", Format::BranchZero),
+            _ => return None,
+        },
+        Opcode::Special2(funct) => match funct {
+            0x00 => ("madd", Format::Pair),
+            0x01 => ("maddu", Format::Pair),
+            0x02 => ("mul", Format::Arithmetic),
+            0x04 => ("msub", Format::Pair),
+            0x05 => ("msubu", Format::Pair),
+            0x20 => ("clz", Format::CountLeading),
+            0x21 => ("clo", Format::CountLeading),
+            _ => return None,
+        },
+        Opcode::Special3 { funct, sa } => match (funct, sa) {
+            (0x00, _) => ("ext", Format::Extract),
+            (0x04, _) => ("ins", Format::Insert),
+            (0x20, 0x02) => ("wsbh", Format::Shuffle),
+            (0x20, 0x10) => ("seb", Format::Shuffle),
+            (0x20, 0x18) => ("seh", Format::Shuffle),
+            _ => return None,
+        },
+        Opcode::Cop0 { rs, funct } => match (rs, funct) {
+            (0x00, _) => ("mfc0", Format::Cop0Move),
+            (0x04, _) => ("mtc0", Format::Cop0Move),
+            (0x10, 0x18) => ("eret", Format::None),
+            _ => return None,
+        },
+        Opcode::Cop1 { fmt, funct } => match (fmt, funct) {
+            (0x00, _) => ("mfc1", Format::Cop1Move),
+            (0x02, _) => ("cfc1", Format::Cop1Control),
+            (0x04, _) => ("mtc1", Format::Cop1Move),
+            (0x06, _) => ("ctc1", Format::Cop1Control),
+            (0x08, 0x00) => ("bc1f", Format::FloatBranch),
+            (0x08, 0x01) => ("bc1t", Format::FloatBranch),
+            (0x10, 0x00) => ("add.s", Format::FloatArithmetic),
+            (0x10, 0x01) => ("sub.s", Format::FloatArithmetic),
+            (0x10, 0x02) => ("mul.s", Format::FloatArithmetic),
+            (0x10, 0x03) => ("div.s", Format::FloatArithmetic),
+            (0x10, 0x04) => ("sqrt.s", Format::FloatUnary),
+            (0x10, 0x05) => ("abs.s", Format::FloatUnary),
+            (0x10, 0x06) => ("mov.s", Format::FloatUnary),
+            (0x10, 0x07) => ("neg.s", Format::FloatUnary),
+            (0x10, 0x0c) => ("round.w.s", Format::FloatUnary),
+            (0x10, 0x0d) => ("trunc.w.s", Format::FloatUnary),
+            (0x10, 0x0e) => ("ceil.w.s", Format::FloatUnary),
+            (0x10, 0x0f) => ("floor.w.s", Format::FloatUnary),
+            (0x10, 0x21) => ("cvt.d.s", Format::FloatUnary),
+            (0x10, 0x24) => ("cvt.w.s", Format::FloatUnary),
+            (0x10, 0x32) => ("c.eq.s", Format::FloatCompare),
+            (0x10, 0x3c) => ("c.lt.s", Format::FloatCompare),
+            (0x10, 0x3e) => ("c.le.s", Format::FloatCompare),
+            (0x11, 0x00) => ("add.d", Format::FloatArithmetic),
+            (0x11, 0x01) => ("sub.d", Format::FloatArithmetic),
+            (0x11, 0x02) => ("mul.d", Format::FloatArithmetic),
+            (0x11, 0x03) => ("div.d", Format::FloatArithmetic),
+            (0x11, 0x04) => ("sqrt.d", Format::FloatUnary),
+            (0x11, 0x05) => ("abs.d", Format::FloatUnary),
+            (0x11, 0x06) => ("mov.d", Format::FloatUnary),
+            (0x11, 0x07) => ("neg.d", Format::FloatUnary),
+            (0x11, 0x0c) => ("round.w.d", Format::FloatUnary),
+            (0x11, 0x0d) => ("trunc.w.d", Format::FloatUnary),
+            (0x11, 0x0e) => ("ceil.w.d", Format::FloatUnary),
+            (0x11, 0x0f) => ("floor.w.d", Format::FloatUnary),
+            (0x11, 0x20) => ("cvt.s.d", Format::FloatUnary),
+            (0x11, 0x24) => ("cvt.w.d", Format::FloatUnary),
+            (0x11, 0x32) => ("c.eq.d", Format::FloatCompare),
+            (0x11, 0x3c) => ("c.lt.d", Format::FloatCompare),
+            (0x11, 0x3e) => ("c.le.d", Format::FloatCompare),
+            (0x14, 0x20) => ("cvt.s.w", Format::FloatUnary),
+            (0x14, 0x21) => ("cvt.d.w", Format::FloatUnary),
+            _ => return None,
+        },
+    };
+    Some((opcode, name, format))
+}
+
+/// Encodes the fields an instruction uses like the assembler does, leaving the others zero
+fn encode(opcode: Opcode, format: Format, f: &Fields) -> u32 {
+    let r = |rs: u32, rt: u32, rd: u32, sa: u32| {
+        opcode.r_bits() | (rs << 21) | (rt << 16) | (rd << 11) | (sa << 6)
+    };
+    let i = |rs: u32, rt: u32| opcode.i_bits() | (rs << 21) | (rt << 16) | f.imm;
+
+    match format {
+        Format::None => r(0, 0, 0, 0),
+        Format::Arithmetic | Format::ShiftVariable => r(f.rs, f.rt, f.rd, 0),
+        Format::Pair => r(f.rs, f.rt, 0, 0),
+        Format::Destination => r(0, 0, f.rd, 0),
+        Format::Source => r(f.rs, 0, 0, 0),
+        Format::Jalr => r(f.rs, 0, f.rd, 0),
+        Format::Shift => r(0, f.rt, f.rd, f.sa),
+        Format::Rotate => r(1, f.rt, f.rd, f.sa),
+        Format::RotateVariable => r(f.rs, f.rt, f.rd, 1),
+        Format::CountLeading => r(f.rs, f.rd, f.rd, 0),
+        Format::Shuffle => r(0, f.rt, f.rd, 0),
+        Format::Extract | Format::Insert => r(f.rs, f.rt, f.rd, f.sa),
+        // the flag is held in the upper bits of rt and the lowest bit moves on true
+        Format::CondMove => r(f.rs, f.rt & !0x2, f.rd, 0),
+        Format::Cop0Move | Format::Cop1Move | Format::Cop1Control => r(0, f.rt, f.rd, 0),
+        Format::FloatArithmetic => r(0, f.rt, f.rd, f.sa),
+        Format::FloatUnary => r(0, 0, f.rd, f.sa),
+        Format::FloatCompare => r(0, f.rt, f.rd, f.sa & !0x3),
+        Format::Immediate | Format::Memory | Format::FloatMemory | Format::Branch => i(f.rs, f.rt),
+        Format::Lui => i(0, f.rt),
+        Format::BranchZero | Format::TrapImmediate => i(f.rs, 0),
+        Format::FloatBranch => i(0, f.rt & !0x3),
+        Format::Jump => (opcode.value() << 26) | f.target,
+        // the code fills every field between the opcode and the funct
+        Format::Break => opcode.r_bits() | (f.target & !0x3f),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::disassemble;
+    use crate::assembler;
+    use crate::parser::model::TEXT_BASE;
+
+    /// Operands tried for each mnemonic until one assembles, every format has at least one
+    const OPERANDS: &[&str] = &[
+        "",
+        "$t0",
+        "$t0, $t1",
+        "$t0, $t1, $t2",
+        "$t0, $t1, 3",
+        "$t0, 3",
+        "$t0, $t1, 2, 3",
+        "$t0, $t1, target",
+        "$t0, target",
+        "target",
+        "1, target",
+        "$t0, 8($t1)",
+        "$f2, 8($t1)",
+        "$t0, $12",
+        "$t0, $f2",
+        "$t0, $fcsr",
+        "$f2, $f4",
+        "$f2, $f4, $f6",
+        "1, $f2, $f4",
+        "$t0, $t1, 1",
+        "7",
+    ];
+
+    /// Mnemonics of the assembler that are aliases of other instructions
+    const ALIASES: &[&str] = &["beqz", "bnez"];
+
+    /// Assembles a line followed by a label and disassembles the first word it encodes to
+    fn encode(line: &str) -> Option<(u32, String)> {
+        let (memory, labels) = assembler(format!(".text\n{line}\ntarget: nop\n")).ok()?;
+        let word = memory.get(TEXT_BASE).ok()?;
+        Some((word, disassemble(word, TEXT_BASE, &labels)))
+    }
+
+    #[test]
+    fn every_mnemonic_disassembles_to_itself() {
+        // every instruction the assembler knows, without its pseudo instructions and directives
+        let table = include_str!("parser/opcode.rs");
+        let mnemonics = table.lines().filter_map(|line| {
+            let (name, parser) = line.trim().strip_prefix('"')?.split_once("\" => Ok(")?;
+            let real = parser.starts_with("InstructionParser::new")
+                || parser.starts_with("InstructionParser::with_constants");
+            (real && !ALIASES.contains(&name)).then_some(name)
+        });
+
+        let mut count = 0;
+        for name in mnemonics {
+            let round_trip = OPERANDS.iter().find_map(|operands| {
+                let (word, assembly) = encode(&format!("{name} {operands}"))?;
+                let same = assembly.split_whitespace().next() == Some(name)
+                    && encode(&assembly).map(|(again, _)| again) == Some(word);
+                same.then_some(assembly)
+            });
+            assert!(round_trip.is_some(), "`{name}` doesn't disassemble to itself");
+            count += 1;
+        }
+        assert!(count > 100, "only {count} mnemonics were found in the opcode table");
+    }
+}
@@ -0,0 +1,599 @@
+//! Executables in the ELF32 format
+//!
+//! Assembled programs are written as MIPS executables with a section for each segment they use,
+//! a loadable segment for each of those and a symbol table of their labels and constants. A note
+//! marks them as code for this pipeline, which runs two delay slots after each branch and jump.
+//! Code from other toolchains, like `mips-linux-gnu-gcc`, expects a single delay slot and a return
+//! address 8 bytes past the jump, so executables without the note are refused rather than run
+//! wrong.
+
+use thiserror::Error;
+
+use crate::{
+    linker::insert_guard,
+    listing::Listing,
+    parser::model::{LabelTable, DATA_BASE, EXTERN_BASE, KDATA_BASE, KTEXT_BASE, TEXT_BASE},
+    Memory,
+};
+
+/// The order of the bytes of a value in an executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+/// Reasons an executable can't be loaded
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ElfError {
+    #[error("Not an ELF file")]
+    NotElf,
+    #[error("Only 32 bit executables can be loaded")]
+    Not32Bit,
+    #[error("Not a MIPS executable")]
+    NotMips,
+    #[error("Not an executable, link it first")]
+    NotExecutable,
+    #[error("The file ends in the middle of the executable")]
+    Truncated,
+    #[error("A loadable segment doesn't fit in memory")]
+    BadSegment,
+    #[error(
+        "Not written by this assembler, other toolchains expect one delay slot after branches and \
+         jumps while the pipeline runs two"
+    )]
+    OtherToolchain,
+}
+
+const EHDR_SIZE: u32 = 52;
+const PHDR_SIZE: u32 = 32;
+const SHDR_SIZE: u32 = 40;
+const SYM_SIZE: u32 = 16;
+
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const ELFDATA2MSB: u8 = 2;
+const ET_EXEC: u16 = 2;
+const EM_MIPS: u16 = 8;
+/// MIPS32 release 2 code for the o32 ABI that must not be reordered
+const EF_MIPS: u32 = 0x7000_1001;
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+/// Loadable segments start at the same offset into a page in the file as in memory
+const PAGE_SIZE: u32 = 0x1000;
+/// The largest loadable segment, bigger ones come from a corrupt header rather than a program
+const MAX_SEGMENT_SIZE: u32 = 0x0100_0000;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_NOTE: u32 = 7;
+const SHF_WRITE: u32 = 1;
+const SHF_ALLOC: u32 = 2;
+const SHF_EXECINSTR: u32 = 4;
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xfff1;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+
+/// The owner of the note that marks executables written by this assembler
+const NOTE_OWNER: &str = "RAJA";
+/// The type of the note, which holds the number of delay slots after branches and jumps
+const NT_DELAY_SLOTS: u32 = 1;
+const DELAY_SLOTS: u32 = 2;
+
+/// The segments a program can use, as the name of their section, where they start and whether
+/// they hold code
+const SEGMENTS: [(&str, u32, bool); 5] = [
+    (".text", TEXT_BASE, true),
+    (".extern", EXTERN_BASE, false),
+    (".data", DATA_BASE, false),
+    (".ktext", KTEXT_BASE, true),
+    (".kdata", KDATA_BASE, false),
+];
+
+/// A segment of a program with its contents in the byte order of the executable
+struct Section {
+    name: &'static str,
+    address: u32,
+    data: Vec<u8>,
+    code: bool,
+}
+
+impl Section {
+    /// Whether an address is in the section or just after it, like a label at its end
+    fn contains(&self, address: u32) -> bool {
+        (self.address..=self.address + self.data.len() as u32).contains(&address)
+    }
+}
+
+/// Writes values in the byte order of an executable
+struct Writer {
+    bytes: Vec<u8>,
+    endian: Endian,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        match self.endian {
+            Endian::Little => self.bytes.extend(value.to_le_bytes()),
+            Endian::Big => self.bytes.extend(value.to_be_bytes()),
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        match self.endian {
+            Endian::Little => self.bytes.extend(value.to_le_bytes()),
+            Endian::Big => self.bytes.extend(value.to_be_bytes()),
+        }
+    }
+
+    /// Pads with zeros up to an offset that is `offset` past a multiple of `align`
+    fn pad(&mut self, align: u32, offset: u32) {
+        while self.bytes.len() as u32 % align != offset % align {
+            self.bytes.push(0);
+        }
+    }
+
+    fn offset(&self) -> u32 {
+        self.bytes.len() as u32
+    }
+}
+
+/// Names of sections or symbols, as they are stored in a string table
+#[derive(Default)]
+struct Strings {
+    bytes: Vec<u8>,
+}
+
+impl Strings {
+    /// Adds a name, giving its offset in the table
+    fn add(&mut self, name: &str) -> u32 {
+        if self.bytes.is_empty() {
+            // offset 0 is the empty name
+            self.bytes.push(0);
+        }
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend(name.as_bytes());
+        self.bytes.push(0);
+        offset
+    }
+}
+
+/// A section header
+struct Header {
+    name: u32,
+    kind: u32,
+    flags: u32,
+    address: u32,
+    offset: u32,
+    size: u32,
+    link: u32,
+    info: u32,
+    align: u32,
+    entry_size: u32,
+}
+
+/// Writes a linked program as an executable, using its listing to find what it placed in memory
+///
+/// The program starts at its entry point, which is its global `main` if it has one. Labels are
+/// in the symbol table with the unique names the assembler gives them, and named constants are
+/// absolute symbols. Symbols of files other than the main script are only there if they are
+/// global.
+pub fn write(memory: &Memory, labels: &LabelTable, listing: &Listing, endian: Endian) -> Vec<u8> {
+    let rows: Vec<_> = listing.files.iter().flat_map(|file| &file.rows).collect();
+    let mut sections = vec![];
+    for (i, &(name, base, code)) in SEGMENTS.iter().enumerate() {
+        let limit = SEGMENTS.get(i + 1).map_or(u32::MAX, |segment| segment.1);
+        let in_segment = |address: u32| (base..limit).contains(&address);
+        let Some(end) = rows
+            .iter()
+            .filter(|row| in_segment(row.address))
+            .map(|row| row.address + row.bytes.len() as u32)
+            .max()
+        else {
+            continue;
+        };
+
+        // alignment leaves gaps between rows, which are read from memory too
+        let mut data: Vec<u8> = (base..end)
+            .map(|address| memory.get_byte(address).unwrap_or_default())
+            .collect();
+        if endian == Endian::Big {
+            // memory is little-endian, so every word of the segment is stored the other way around
+            data.resize(data.len().next_multiple_of(4), 0);
+            data.as_chunks_mut::<4>().0.iter_mut().for_each(|word| word.reverse());
+        }
+        sections.push(Section {
+            name,
+            address: base,
+            data,
+            code,
+        });
+    }
+
+    // local symbols have to come before global ones
+    let mut symbols: Vec<(&String, u32, bool, bool)> = labels
+        .labels()
+        .map(|(name, address)| {
+            let global = labels.globals().any(|(global, _)| global == name);
+            (name, address, global, false)
+        })
+        .chain(
+            labels
+                .constants()
+                .map(|(name, value)| (name, value as u32, false, true)),
+        )
+        .collect();
+    symbols.sort_by(|a, b| (a.2, a.1, a.0).cmp(&(b.2, b.1, b.0)));
+    let first_global = 1 + symbols.iter().filter(|symbol| !symbol.2).count() as u32;
+
+    let mut out = Writer {
+        bytes: vec![],
+        endian,
+    };
+    let mut section_names = Strings::default();
+    let mut headers = vec![Header {
+        name: 0,
+        kind: 0,
+        flags: 0,
+        address: 0,
+        offset: 0,
+        size: 0,
+        link: 0,
+        info: 0,
+        align: 0,
+        entry_size: 0,
+    }];
+
+    // the headers are written once the offsets of the sections are known
+    out.bytes
+        .resize((EHDR_SIZE + PHDR_SIZE * sections.len() as u32) as usize, 0);
+    for section in &sections {
+        out.pad(PAGE_SIZE, section.address);
+        headers.push(Header {
+            name: section_names.add(section.name),
+            kind: SHT_PROGBITS,
+            flags: match section.code {
+                true => SHF_ALLOC | SHF_EXECINSTR,
+                false => SHF_ALLOC | SHF_WRITE,
+            },
+            address: section.address,
+            offset: out.offset(),
+            size: section.data.len() as u32,
+            link: 0,
+            info: 0,
+            align: 4,
+            entry_size: 0,
+        });
+        out.bytes.extend(&section.data);
+    }
+
+    out.pad(4, 0);
+    let note = note(endian);
+    headers.push(Header {
+        name: section_names.add(".note.raja"),
+        kind: SHT_NOTE,
+        flags: 0,
+        address: 0,
+        offset: out.offset(),
+        size: note.len() as u32,
+        link: 0,
+        info: 0,
+        align: 4,
+        entry_size: 0,
+    });
+    out.bytes.extend(note);
+
+    let mut symbol_names = Strings::default();
+    let symtab_offset = out.offset();
+    out.bytes.extend([0; SYM_SIZE as usize]);
+    for &(name, value, global, constant) in &symbols {
+        let section = sections
+            .iter()
+            .position(|section| section.contains(value))
+            .filter(|_| !constant);
+        let kind = match section.map(|i| sections[i].code) {
+            Some(true) => STT_FUNC,
+            Some(false) => STT_OBJECT,
+            None => STT_NOTYPE,
+        };
+        let bind = match global {
+            true => STB_GLOBAL,
+            false => STB_LOCAL,
+        };
+        out.u32(symbol_names.add(name));
+        out.u32(value);
+        out.u32(0);
+        out.u8((bind << 4) | kind);
+        out.u8(0);
+        out.u16(section.map_or(SHN_ABS, |i| i as u16 + 1));
+    }
+    let symtab_index = headers.len() as u32;
+    headers.push(Header {
+        name: section_names.add(".symtab"),
+        kind: SHT_SYMTAB,
+        flags: 0,
+        address: 0,
+        offset: symtab_offset,
+        size: out.offset() - symtab_offset,
+        link: symtab_index + 1,
+        info: first_global,
+        align: 4,
+        entry_size: SYM_SIZE,
+    });
+    // the names of the string tables have to be added before they are written
+    let names = [section_names.add(".strtab"), section_names.add(".shstrtab")];
+    for (name, strings) in names.into_iter().zip([&symbol_names, &section_names]) {
+        headers.push(Header {
+            name,
+            kind: SHT_STRTAB,
+            flags: 0,
+            address: 0,
+            offset: out.offset(),
+            size: strings.bytes.len() as u32,
+            link: 0,
+            info: 0,
+            align: 1,
+            entry_size: 0,
+        });
+        out.bytes.extend(&strings.bytes);
+    }
+    out.pad(4, 0);
+    let section_headers = out.offset();
+    for header in &headers {
+        for field in [
+            header.name,
+            header.kind,
+            header.flags,
+            header.address,
+            header.offset,
+            header.size,
+            header.link,
+            header.info,
+            header.align,
+            header.entry_size,
+        ] {
+            out.u32(field);
+        }
+    }
+
+    let mut head = Writer {
+        bytes: vec![0x7f, b'E', b'L', b'F', ELFCLASS32],
+        endian,
+    };
+    head.u8(match endian {
+        Endian::Little => ELFDATA2LSB,
+        Endian::Big => ELFDATA2MSB,
+    });
+    head.u8(1);
+    head.pad(16, 0);
+    head.u16(ET_EXEC);
+    head.u16(EM_MIPS);
+    head.u32(1);
+    head.u32(labels.entry());
+    head.u32(EHDR_SIZE);
+    head.u32(section_headers);
+    head.u32(EF_MIPS);
+    head.u16(EHDR_SIZE as u16);
+    head.u16(PHDR_SIZE as u16);
+    head.u16(sections.len() as u16);
+    head.u16(SHDR_SIZE as u16);
+    head.u16(headers.len() as u16);
+    head.u16(headers.len() as u16 - 1);
+    for (section, header) in sections.iter().zip(&headers[1..]) {
+        let flags = match section.code {
+            true => PF_R | PF_X,
+            false => PF_R | PF_W,
+        };
+        for field in [
+            PT_LOAD,
+            header.offset,
+            header.address,
+            header.address,
+            header.size,
+            header.size,
+            flags,
+            PAGE_SIZE,
+        ] {
+            head.u32(field);
+        }
+    }
+    out.bytes[..head.bytes.len()].copy_from_slice(&head.bytes);
+    out.bytes
+}
+
+/// The note that marks an executable as written by this assembler, holding the number of delay
+/// slots its code has
+fn note(endian: Endian) -> Vec<u8> {
+    let mut note = Writer {
+        bytes: vec![],
+        endian,
+    };
+    note.u32(NOTE_OWNER.len() as u32 + 1);
+    note.u32(4);
+    note.u32(NT_DELAY_SLOTS);
+    note.bytes.extend(NOTE_OWNER.as_bytes());
+    note.u8(0);
+    note.pad(4, 0);
+    note.u32(DELAY_SLOTS);
+    note.bytes
+}
+
+/// Reads values of an executable
+struct Reader<'a> {
+    bytes: &'a [u8],
+    endian: Endian,
+}
+
+impl Reader<'_> {
+    fn slice(&self, offset: u32, size: u32) -> Result<&[u8], ElfError> {
+        let start = offset as usize;
+        let end = start.checked_add(size as usize).ok_or(ElfError::Truncated)?;
+        self.bytes.get(start..end).ok_or(ElfError::Truncated)
+    }
+
+    fn u8(&self, offset: u32) -> Result<u8, ElfError> {
+        Ok(self.slice(offset, 1)?[0])
+    }
+
+    fn u16(&self, offset: u32) -> Result<u16, ElfError> {
+        // the slice has the length asked for
+        let bytes: [u8; 2] = self.slice(offset, 2)?.try_into().unwrap();
+        Ok(match self.endian {
+            Endian::Little => u16::from_le_bytes(bytes),
+            Endian::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(&self, offset: u32) -> Result<u32, ElfError> {
+        // the slice has the length asked for
+        let bytes: [u8; 4] = self.slice(offset, 4)?.try_into().unwrap();
+        Ok(match self.endian {
+            Endian::Little => u32::from_le_bytes(bytes),
+            Endian::Big => u32::from_be_bytes(bytes),
+        })
+    }
+
+    /// Reads the name at `offset` in the string table at `table`
+    fn name(&self, table: u32, offset: u32) -> Result<String, ElfError> {
+        let start = table.checked_add(offset).ok_or(ElfError::Truncated)?;
+        let bytes = self.bytes.get(start as usize..).ok_or(ElfError::Truncated)?;
+        let end = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(ElfError::Truncated)?;
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+/// Loads a MIPS executable into memory, with the symbols of its symbol table
+///
+/// Absolute symbols become named constants and the others labels. Each loadable segment of code
+/// is followed by a guard like the linker puts after the text segment. Only executables written
+/// by [`write`] are loaded, as the note they carry tells them apart from those of other toolchains.
+///
+/// The words of a big-endian executable are byte swapped into the little-endian memory, undoing
+/// the swap [`write`] made.
+pub fn read(bytes: &[u8]) -> Result<(Memory, LabelTable), ElfError> {
+    let mut elf = Reader {
+        bytes,
+        endian: Endian::Little,
+    };
+    if elf.slice(0, 4).ok() != Some(b"\x7fELF".as_slice()) {
+        return Err(ElfError::NotElf);
+    }
+    if elf.u8(4)? != ELFCLASS32 {
+        return Err(ElfError::Not32Bit);
+    }
+    if elf.u8(5)? == ELFDATA2MSB {
+        elf.endian = Endian::Big;
+    }
+    if elf.u16(18)? != EM_MIPS {
+        return Err(ElfError::NotMips);
+    }
+    if elf.u16(16)? != ET_EXEC {
+        return Err(ElfError::NotExecutable);
+    }
+
+    let (section_headers, section_header_size, section_header_count) =
+        (elf.u32(32)?, elf.u16(46)?, elf.u16(48)?);
+    let section = |i: u32| -> Result<u32, ElfError> {
+        let header = (i as usize * section_header_size as usize + section_headers as usize)
+            .try_into()
+            .map_err(|_| ElfError::Truncated)?;
+        elf.slice(header, SHDR_SIZE)?;
+        Ok(header)
+    };
+    let note = note(elf.endian);
+    let mut ours = false;
+    for i in 0..section_header_count as u32 {
+        let header = section(i)?;
+        if elf.u32(header + 4)? == SHT_NOTE && elf.u32(header + 20)? == note.len() as u32 {
+            ours |= elf.slice(elf.u32(header + 16)?, note.len() as u32)? == note;
+        }
+    }
+    if !ours {
+        return Err(ElfError::OtherToolchain);
+    }
+
+    let mut memory = Memory::new();
+    let mut labels = LabelTable::default();
+    labels.set_entry(elf.u32(24)?);
+
+    let (program_headers, program_header_size, program_header_count) =
+        (elf.u32(28)?, elf.u16(42)?, elf.u16(44)?);
+    for i in 0..program_header_count as u32 {
+        let header = (i as usize * program_header_size as usize + program_headers as usize)
+            .try_into()
+            .map_err(|_| ElfError::Truncated)?;
+        // the fields of a header in the file can't overflow
+        elf.slice(header, PHDR_SIZE)?;
+        if elf.u32(header)? != PT_LOAD {
+            continue;
+        }
+        let (offset, address) = (elf.u32(header + 4)?, elf.u32(header + 8)?);
+        let (file_size, memory_size) = (elf.u32(header + 16)?, elf.u32(header + 20)?);
+        if file_size > memory_size || memory_size > MAX_SEGMENT_SIZE {
+            return Err(ElfError::BadSegment);
+        }
+        let end = address
+            .checked_add(memory_size)
+            .ok_or(ElfError::BadSegment)?;
+        let data = elf.slice(offset, file_size)?;
+        // the rest of the segment past the file is zeroed, like .bss
+        for (n, address) in (address..end).enumerate() {
+            let byte = data.get(n).copied().unwrap_or_default();
+            let address = match elf.endian {
+                Endian::Little => address,
+                Endian::Big => address ^ 3,
+            };
+            // writing single bytes can't fail
+            let _ = memory.set_byte(address, byte);
+        }
+        if elf.u32(header + 24)? & PF_X != 0 {
+            // the guard takes two words after the segment
+            let guard = end
+                .checked_next_multiple_of(4)
+                .filter(|guard| guard.checked_add(8).is_some())
+                .ok_or(ElfError::BadSegment)?;
+            insert_guard(&mut memory, guard);
+        }
+    }
+
+    for i in 0..section_header_count as u32 {
+        let header = section(i)?;
+        if elf.u32(header + 4)? != SHT_SYMTAB {
+            continue;
+        }
+        let (offset, size) = (elf.u32(header + 16)?, elf.u32(header + 20)?);
+        let names = elf.u32(section(elf.u32(header + 24)?)? + 16)?;
+        elf.slice(offset, size)?;
+        for symbol in (offset..offset + size).step_by(SYM_SIZE as usize).skip(1) {
+            let kind = elf.u8(symbol + 12)? & 0xf;
+            let index = elf.u16(symbol + 14)?;
+            let name = elf.name(names, elf.u32(symbol)?)?;
+            if name.is_empty() || kind > STT_FUNC || index == SHN_UNDEF {
+                continue;
+            }
+            let value = elf.u32(symbol + 4)?;
+            match index {
+                SHN_ABS => labels.insert_constant(name, value as i32 as i64),
+                _ => labels.insert_label(name, value),
+            }
+        }
+    }
+
+    Ok((memory, labels))
+}
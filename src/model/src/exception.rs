@@ -0,0 +1,114 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::fp_exception_names;
+
+/// Status register bit enabling interrupts
+pub const STATUS_IE: u32 = 1;
+/// Status register bit set while an exception is being handled
+pub const STATUS_EXL: u32 = 1 << 1;
+/// Status register bits masking individual interrupts
+pub const STATUS_IM: u32 = 0xff00;
+/// Cause register bits holding pending interrupts
+pub const CAUSE_IP: u32 = 0xff00;
+/// Cause register bit for the timer interrupt raised when Count reaches Compare
+pub const CAUSE_TIMER: u32 = 1 << 15;
+/// Cause register bits holding the exception code
+pub const CAUSE_EXC_CODE: u32 = 0x7c;
+/// Cause register bit set when the exception was raised in a delay slot of a branch or jump
+pub const CAUSE_BD: u32 = 1 << 31;
+
+/// Architectural exceptions raised by an instruction while it executes
+///
+/// Exceptions are carried down the pipeline in place of the faulting instruction and taken once
+/// every older instruction has finished.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exception {
+    /// An enabled interrupt is pending
+    #[error("Interrupt")]
+    Interrupt,
+    /// An unaligned load or instruction fetch
+    #[error("Address error loading from 0x{0:08x}")]
+    AddressLoad(u32),
+    /// An unaligned store
+    #[error("Address error storing to 0x{0:08x}")]
+    AddressStore(u32),
+    /// A break instruction, which pauses the machine like a breakpoint
+    #[error("Breakpoint")]
+    Breakpoint,
+    /// The instruction could not be decoded
+    #[error("Reserved instruction")]
+    ReservedInstruction,
+    /// A trapping add, addi or sub produced a signed overflow
+    #[error("Arithmetic overflow")]
+    Overflow,
+    /// The condition of a trap instruction held
+    #[error("Trap")]
+    Trap,
+    /// A floating point operation raised IEEE-754 exceptions that are enabled in the FCSR
+    #[error("Floating point exception ({})", fp_exception_names(*.0))]
+    FloatingPoint(u32),
+}
+
+impl Exception {
+    /// The code recorded in the Cause register
+    pub fn code(&self) -> u32 {
+        match self {
+            Exception::Interrupt => 0,
+            Exception::AddressLoad(_) => 4,
+            Exception::AddressStore(_) => 5,
+            Exception::Breakpoint => 9,
+            Exception::ReservedInstruction => 10,
+            Exception::Overflow => 12,
+            Exception::Trap => 13,
+            Exception::FloatingPoint(_) => 15,
+        }
+    }
+
+    /// The address recorded in the BadVAddr register
+    pub fn bad_address(&self) -> Option<u32> {
+        match self {
+            Exception::AddressLoad(address) | Exception::AddressStore(address) => Some(*address),
+            _ => None,
+        }
+    }
+}
+
+/// An error that stopped the machine, tagged with the instruction that caused it
+#[derive(Debug)]
+pub struct RuntimeError {
+    /// What went wrong, either an [`Exception`] or a simulator error
+    pub error: anyhow::Error,
+    /// Address of the faulting instruction
+    pub pc: u32,
+    /// Source line of the faulting instruction (0 indexed) if it is known
+    pub line: Option<usize>,
+}
+
+impl RuntimeError {
+    pub fn new(error: anyhow::Error, pc: u32) -> Self {
+        Self {
+            error,
+            pc,
+            line: None,
+        }
+    }
+
+    /// Gets the architectural exception behind this error if there is one
+    pub fn exception(&self) -> Option<Exception> {
+        self.error.downcast_ref::<Exception>().copied()
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at 0x{:08x}", self.error, self.pc)?;
+        if let Some(line) = self.line {
+            write!(f, " (line {})", line + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RuntimeError {}
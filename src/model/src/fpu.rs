@@ -0,0 +1,333 @@
+//! State of the floating point unit (coprocessor 1) kept in its control and status register
+//!
+//! Operations are computed exactly enough to round their results in any of the four IEEE-754
+//! rounding modes and to raise the five IEEE-754 exceptions.
+
+use std::cmp::Ordering;
+
+/// Number of condition flags set by floating point comparisons
+pub const FP_CONDITIONS: u32 = 8;
+
+/// FCSR bits selecting the rounding mode
+pub const FCSR_RM: u32 = 0x3;
+/// Position of the FCSR flags, which accumulate the exceptions raised since they were cleared
+pub const FCSR_FLAGS_SHIFT: u32 = 2;
+/// Position of the FCSR enables, which make exceptions trap
+pub const FCSR_ENABLES_SHIFT: u32 = 7;
+/// Position of the FCSR cause, which holds the exceptions raised by the last operation
+pub const FCSR_CAUSE_SHIFT: u32 = 12;
+
+/// IEEE-754 exceptions in the order they appear in the flags, enables and cause fields
+pub const FP_INEXACT: u32 = 1 << 0;
+pub const FP_UNDERFLOW: u32 = 1 << 1;
+pub const FP_OVERFLOW: u32 = 1 << 2;
+pub const FP_DIV_ZERO: u32 = 1 << 3;
+pub const FP_INVALID: u32 = 1 << 4;
+const FP_EXCEPTIONS: u32 = 0x1f;
+
+/// Value of the read only FIR, which reports support for the single, double and word formats
+pub const FIR_VALUE: u32 = 1 << 16 | 1 << 17 | 1 << 20;
+
+
+/// Gets the FCSR bit holding the given condition flag
+///
+/// Flag 0 lives in bit 23 while flags 1-7 were added later in bits 25-31.
+pub fn fcsr_condition_bit(cc: u32) -> u32 {
+    match cc {
+        0 => 1 << 23,
+        cc => 1 << (24 + cc),
+    }
+}
+
+/// Checks if the given condition flag is set in the FCSR
+pub fn fp_condition(fcsr: u32, cc: u32) -> bool {
+    fcsr & fcsr_condition_bit(cc) != 0
+}
+
+/// Sets or clears the given condition flag of the FCSR
+pub fn set_fp_condition(fcsr: u32, cc: u32, value: bool) -> u32 {
+    if value {
+        fcsr | fcsr_condition_bit(cc)
+    } else {
+        fcsr & !fcsr_condition_bit(cc)
+    }
+}
+
+/// Gathers the condition flags of the FCSR with flag 0 in the lowest bit
+pub fn fp_conditions(fcsr: u32) -> u8 {
+    (0..FP_CONDITIONS)
+        .filter(|cc| fp_condition(fcsr, *cc))
+        .fold(0, |flags, cc| flags | 1 << cc)
+}
+
+/// Gets the exceptions that trap instead of being recorded because the FCSR enables them
+pub fn fp_traps(fcsr: u32, raised: u32) -> u32 {
+    (fcsr >> FCSR_ENABLES_SHIFT) & raised & FP_EXCEPTIONS
+}
+
+/// Replaces the FCSR cause field with the exceptions raised by an operation
+pub fn record_fp_cause(fcsr: u32, raised: u32) -> u32 {
+    (fcsr & !(FP_EXCEPTIONS << FCSR_CAUSE_SHIFT)) | (raised << FCSR_CAUSE_SHIFT)
+}
+
+/// Records the exceptions raised by an operation that didn't trap in the FCSR
+///
+/// The cause field only keeps this operation's exceptions while the flags accumulate them.
+pub fn record_fp_exceptions(fcsr: u32, raised: u32) -> u32 {
+    record_fp_cause(fcsr, raised) | (raised << FCSR_FLAGS_SHIFT)
+}
+
+/// Names the given exceptions, most severe first
+pub fn fp_exception_names(raised: u32) -> String {
+    [
+        (FP_INVALID, "invalid operation"),
+        (FP_DIV_ZERO, "division by zero"),
+        (FP_OVERFLOW, "overflow"),
+        (FP_UNDERFLOW, "underflow"),
+        (FP_INEXACT, "inexact"),
+    ]
+    .iter()
+    .filter(|(exception, _)| raised & exception != 0)
+    .map(|(_, name)| *name)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// The rounding modes selected by the FCSR
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RoundingMode {
+    /// Round to nearest, ties to even
+    #[default]
+    Nearest,
+    /// Round toward zero
+    Zero,
+    /// Round toward positive infinity
+    Up,
+    /// Round toward negative infinity
+    Down,
+}
+
+impl RoundingMode {
+    pub fn from_fcsr(fcsr: u32) -> Self {
+        match fcsr & FCSR_RM {
+            0 => RoundingMode::Nearest,
+            1 => RoundingMode::Zero,
+            2 => RoundingMode::Up,
+            _ => RoundingMode::Down,
+        }
+    }
+}
+
+/// Floating point formats results are rounded to
+trait Format: Copy {
+    /// Smallest normal magnitude, smaller results are tiny
+    const MIN_POSITIVE: f64;
+    /// Smallest magnitude that overflows whatever the rounding mode
+    const OVERFLOW: f64;
+
+    /// Rounds to nearest, ties to even
+    fn narrow(value: f64) -> Self;
+    fn widen(self) -> f64;
+    fn next_up(self) -> Self;
+    fn next_down(self) -> Self;
+}
+
+impl Format for f32 {
+    const MIN_POSITIVE: f64 = f32::MIN_POSITIVE as f64;
+    const OVERFLOW: f64 = 3.402823669209385e38; // 2^128
+
+    fn narrow(value: f64) -> Self {
+        value as f32
+    }
+
+    fn widen(self) -> f64 {
+        self as f64
+    }
+
+    fn next_up(self) -> Self {
+        f32::next_up(self)
+    }
+
+    fn next_down(self) -> Self {
+        f32::next_down(self)
+    }
+}
+
+impl Format for f64 {
+    const MIN_POSITIVE: f64 = f64::MIN_POSITIVE;
+    const OVERFLOW: f64 = f64::INFINITY;
+
+    fn narrow(value: f64) -> Self {
+        value
+    }
+
+    fn widen(self) -> f64 {
+        self
+    }
+
+    fn next_up(self) -> Self {
+        f64::next_up(self)
+    }
+
+    fn next_down(self) -> Self {
+        f64::next_down(self)
+    }
+}
+
+/// The exact result of an operation, held as the result rounded to the nearest double together
+/// with the sign of the error that rounding made.
+///
+/// This is enough to round the exact result to a single or double in any rounding mode, since
+/// the error is never larger than the gap between neighbouring doubles.
+#[derive(Debug, Clone, Copy)]
+pub struct Unrounded {
+    value: f64,
+    /// How the exact result compares to `value`
+    error: Ordering,
+    /// Exceptions raised before rounding
+    raised: u32,
+}
+
+impl Unrounded {
+    /// A result that is already exact as a double
+    pub fn exact(value: f64) -> Self {
+        Self {
+            value,
+            error: Ordering::Equal,
+            raised: 0,
+        }
+    }
+
+    /// Builds a result from the rounded `value` and the `remainder` the exact result differs by
+    fn new(value: f64, remainder: f64, operands: &[f64]) -> Self {
+        let finite = operands.iter().all(|operand| operand.is_finite());
+        let error = if value.is_infinite() && finite {
+            // the double overflowed so the exact result is a finite number closer to zero
+            if value > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            }
+        } else if value.is_finite() {
+            remainder.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+        } else {
+            Ordering::Equal
+        };
+
+        // a NaN from operands that are numbers is an invalid operation like 0/0 or inf - inf
+        let invalid = value.is_nan() && !operands.iter().any(|operand| operand.is_nan());
+        Self {
+            value,
+            error,
+            raised: if invalid { FP_INVALID } else { 0 },
+        }
+    }
+
+    pub fn add(a: f64, b: f64) -> Self {
+        // the error of the sum is exact (Knuth's TwoSum)
+        let sum = a + b;
+        let b_part = sum - a;
+        let remainder = (a - (sum - b_part)) + (b - b_part);
+        Self::new(sum, remainder, &[a, b])
+    }
+
+    pub fn sub(a: f64, b: f64) -> Self {
+        Self::add(a, -b)
+    }
+
+    pub fn mul(a: f64, b: f64) -> Self {
+        let product = a * b;
+        Self::new(product, a.mul_add(b, -product), &[a, b])
+    }
+
+    pub fn div(a: f64, b: f64) -> Self {
+        let quotient = a / b;
+        // the exact quotient differs from the rounded one by (a - quotient * b) / b
+        let remainder = (-quotient).mul_add(b, a) * b.signum();
+        let mut result = Self::new(quotient, remainder, &[a, b]);
+        if b == 0.0 && a.is_finite() && a != 0.0 {
+            result.error = Ordering::Equal;
+            result.raised |= FP_DIV_ZERO;
+        }
+        result
+    }
+
+    pub fn sqrt(a: f64) -> Self {
+        let root = a.sqrt();
+        Self::new(root, (-root).mul_add(root, a), &[a])
+    }
+
+    /// Rounds to a single, returning it with the exceptions the operation raised
+    pub fn round_single(self, mode: RoundingMode) -> (f32, u32) {
+        self.round(mode)
+    }
+
+    /// Rounds to a double, returning it with the exceptions the operation raised
+    pub fn round_double(self, mode: RoundingMode) -> (f64, u32) {
+        self.round(mode)
+    }
+
+    fn round<T: Format>(self, mode: RoundingMode) -> (T, u32) {
+        let value = self.value;
+        let nearest = T::narrow(value);
+        if (nearest.widen() == value && self.error == Ordering::Equal) || value.is_nan() {
+            return (nearest, self.raised);
+        }
+
+        // the representable numbers either side of the exact result
+        let (below, above) = match nearest.widen().partial_cmp(&value) {
+            Some(Ordering::Less) => (nearest, nearest.next_up()),
+            Some(Ordering::Greater) => (nearest.next_down(), nearest),
+            _ if self.error == Ordering::Less => (nearest.next_down(), nearest),
+            _ => (nearest, nearest.next_up()),
+        };
+        let positive = value > 0.0 || (value == 0.0 && self.error == Ordering::Greater);
+        let halfway = nearest.widen() != value && value == (below.widen() + above.widen()) / 2.0;
+
+        let result = match mode {
+            // a double exactly halfway between two singles is only a tie if it is exact
+            RoundingMode::Nearest if halfway => {
+                match self.error {
+                    Ordering::Less => below,
+                    Ordering::Greater => above,
+                    Ordering::Equal => nearest,
+                }
+            }
+            RoundingMode::Nearest => nearest,
+            RoundingMode::Zero if positive => below,
+            RoundingMode::Zero => above,
+            RoundingMode::Up => above,
+            RoundingMode::Down => below,
+        };
+
+        let mut raised = self.raised | FP_INEXACT;
+        if result.widen().is_infinite() || value.abs() >= T::OVERFLOW {
+            raised |= FP_OVERFLOW;
+        }
+        if value.abs() < T::MIN_POSITIVE {
+            raised |= FP_UNDERFLOW;
+        }
+        (result, raised)
+    }
+}
+
+/// Rounds to an integral value and converts it to a word, returning it with the exceptions the
+/// conversion raised
+///
+/// NaN and values out of range are invalid and give 2^31 - 1.
+pub fn round_to_word(value: f64, mode: RoundingMode) -> (u32, u32) {
+    let rounded = match mode {
+        RoundingMode::Nearest => value.round_ties_even(),
+        RoundingMode::Zero => value.trunc(),
+        RoundingMode::Up => value.ceil(),
+        RoundingMode::Down => value.floor(),
+    };
+
+    if !(rounded >= -(2f64.powi(31)) && rounded < 2f64.powi(31)) {
+        (i32::MAX as u32, FP_INVALID)
+    } else if rounded != value {
+        (rounded as i32 as u32, FP_INEXACT)
+    } else {
+        (rounded as i32 as u32, 0)
+    }
+}
@@ -1,5 +1,8 @@
 pub mod parser;
 pub mod machine;
+pub mod linker;
+mod exception;
+mod fpu;
 mod memory;
 mod pipeline;
 pub mod syscall;
@@ -8,6 +11,9 @@ pub mod callback;
 mod register_file;
 mod register;
 mod data_types;
+pub mod disassembler;
+pub mod elf;
+pub mod listing;
 
 pub mod stages {
     pub mod writeback;
@@ -35,9 +41,14 @@ pub mod stages {
 
 pub use parser::*;
 pub use machine::*;
+pub use linker::*;
+pub use exception::*;
+pub use fpu::*;
 pub use memory::*;
 pub use register::*;
 pub use register_file::*;
+pub use disassembler::disassemble;
+pub use listing::{Listing, SymbolTable};
 
 // ----------------------------------------------------------------------------
 // When compiling for web:
@@ -0,0 +1,405 @@
+//! Links the files of a program, which share the labels they make global with `.globl`
+//!
+//! The files are placed one after another in each segment and assembled on their own, keeping
+//! the instructions that use labels of other files as relocations. Once every file is assembled
+//! the global labels are known and the relocations are filled in.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    disassemble,
+    listing::{FileListing, Listing, Row},
+    parser::{
+        self,
+        model::{LabelTable, Line, Segment, Segments},
+        AsmError, Diagnostic, Expansion, Severity,
+    },
+    Memory,
+};
+
+/// A file of a program
+pub struct Source {
+    /// Where the file is saved, which the files it includes are relative to
+    pub path: Option<PathBuf>,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(path: Option<PathBuf>, text: String) -> Self {
+        Self { path, text }
+    }
+}
+
+/// An instruction that uses a label of another file
+struct Relocation {
+    /// Index of the line of the instruction
+    line: usize,
+    /// Index of the instruction within its line
+    word: usize,
+    address: u32,
+}
+
+/// Where an instruction or piece of data was placed in memory
+struct Placement {
+    /// Index of the line of the expanded script it is on
+    line: usize,
+    address: u32,
+    size: usize,
+    /// Whether it is in a text segment
+    code: bool,
+}
+
+/// A file of a program as it is assembled
+struct Object {
+    path: Option<PathBuf>,
+    /// Whether this is the main script of the program
+    main: bool,
+    script: String,
+    expansion: Expansion,
+    lines: Vec<(usize, Line)>,
+    labels: LabelTable,
+    relocations: Vec<Relocation>,
+    placements: Vec<Placement>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Object {
+    /// Expands and parses a file
+    fn parse(source: Source, main: bool) -> Self {
+        // Replace CRLF line endings
+        let mut script = source.text.replace("\r\n", "\n");
+        // Ensure newline to prevent assembler error
+        script.push('\n');
+
+        // files are included relative to the file including them
+        let dir = match source.path.as_deref().and_then(Path::parent) {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let (expansion, mut diagnostics) = parser::preprocess(&script, dir);
+        let (lines, errors) = parser::parse_string(&expansion.text);
+        diagnostics.extend(
+            errors
+                .into_iter()
+                .map(|error| expansion.locate(&script, error)),
+        );
+
+        Self {
+            path: source.path,
+            main,
+            script,
+            expansion,
+            lines,
+            labels: LabelTable::default(),
+            relocations: vec![],
+            placements: vec![],
+            diagnostics,
+        }
+    }
+
+    /// A name for the file in messages
+    fn name(&self) -> String {
+        match &self.path {
+            Some(path) if !self.main => format!("`{}`", path.display()),
+            _ => "the main script".to_string(),
+        }
+    }
+
+    /// Reports a problem on a line of the expanded script
+    fn report(&mut self, line: usize, error: AsmError) {
+        let error = match error {
+            AsmError::DuplicateLabel { name, first } => AsmError::DuplicateLabel {
+                name,
+                first: self.expansion.source_line(first),
+            },
+            error => error,
+        };
+        let diagnostic = error.locate(&self.expansion.text, line, self.labels.label_names());
+        let diagnostic = self.expansion.locate(&self.script, diagnostic);
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Finds the labels of the file, placing it after the files before it in `segments`
+    fn find_labels(&mut self, segments: &mut Segments) {
+        let (labels, errors) = parser::compute_labels(&mut self.lines, segments);
+        self.labels = labels;
+        for (line, error) in errors {
+            self.report(line, error);
+        }
+    }
+
+    /// Encodes the instructions of the file into memory, keeping the ones that use labels it
+    /// does not define as relocations
+    fn assemble(&mut self, memory: &mut Memory, segments: &mut Segments) {
+        let mut errors = vec![];
+        segments.switch(Segment::Text);
+        let mut code = true;
+        for (index, (line, parsed)) in self.lines.iter().enumerate() {
+            match parsed {
+                Line::Instruction(ins) => {
+                    // data is only misaligned once `.align 0` turned off automatic alignment
+                    let mut misaligned = None;
+                    for (word, ins) in ins.iter().enumerate() {
+                        let address = segments.place(ins);
+                        let size = ins.alignment();
+                        if misaligned.is_none() && !address.is_multiple_of(size) {
+                            misaligned = Some(AsmError::MisalignedData { size, address });
+                        }
+                        self.placements.push(Placement {
+                            line: *line,
+                            address,
+                            size: ins.size(),
+                            code,
+                        });
+                        match ins.asm(&self.labels, address) {
+                            Ok((bin, _)) => {
+                                write(memory, address, bin);
+                                errors.extend(ins.truncation(&self.labels).map(|w| (*line, w)));
+                            }
+                            // the label may be in another file
+                            Err(AsmError::UndefinedLabel(_)) => self.relocations.push(Relocation {
+                                line: index,
+                                word,
+                                address,
+                            }),
+                            Err(error) => errors.push((*line, error)),
+                        }
+                    }
+                    errors.extend(misaligned.map(|warning| (*line, warning)));
+                }
+                Line::Segment(seg) => {
+                    segments.switch(*seg);
+                    code = matches!(seg, Segment::Text | Segment::KText);
+                }
+                Line::Align(n) => segments.align(*n),
+                Line::Extern { size, .. } => {
+                    let address = segments.reserve_extern(*size);
+                    self.placements.push(Placement {
+                        line: *line,
+                        address,
+                        size: *size as usize,
+                        code: false,
+                    });
+                    write(memory, address, vec![0; *size as usize]);
+                }
+                _ => {}
+            }
+        }
+        for (line, error) in errors {
+            self.report(line, error);
+        }
+    }
+
+    /// Fills in the relocations once the labels of other files are imported
+    fn relocate(&mut self, memory: &mut Memory) {
+        let mut errors = vec![];
+        for relocation in &self.relocations {
+            let (line, Line::Instruction(ins)) = &self.lines[relocation.line] else {
+                continue;
+            };
+            let ins = &ins[relocation.word];
+            match ins.asm(&self.labels, relocation.address) {
+                Ok((bin, _)) => {
+                    write(memory, relocation.address, bin);
+                    errors.extend(ins.truncation(&self.labels).map(|w| (*line, w)));
+                }
+                Err(error) => errors.push((*line, error)),
+            }
+        }
+        for (line, error) in errors {
+            self.report(line, error);
+        }
+    }
+
+    /// Lists what each source line of the file placed in memory once it is linked
+    ///
+    /// Rows end on word boundaries so every instruction has a row of its own.
+    fn listing(&self, memory: &Memory) -> FileListing {
+        let mut rows = vec![];
+        for placement in &self.placements {
+            let line = self.expansion.source_line(placement.line);
+            let end = placement.address + placement.size as u32;
+            let mut address = placement.address;
+            while address < end {
+                let next = (address + 1).next_multiple_of(4).min(end);
+                let bytes: Vec<u8> = (address..next)
+                    .map(|address| memory.get_byte(address).unwrap_or_default())
+                    .collect();
+                let instruction = match bytes[..] {
+                    [a, b, c, d] if placement.code => {
+                        let word = u32::from_le_bytes([a, b, c, d]);
+                        Some(disassemble(word, address, &self.labels))
+                    }
+                    _ => None,
+                };
+                rows.push(Row {
+                    address,
+                    bytes,
+                    instruction,
+                    line,
+                });
+                address = next;
+            }
+        }
+        FileListing {
+            path: self.path.clone(),
+            source: self.script.clone(),
+            rows,
+        }
+    }
+}
+
+/// Inserts a guard at the end of the code at `address`, which stops the program with an error if
+/// it runs past its last instruction
+pub(crate) fn insert_guard(memory: &mut Memory, address: u32) {
+    for (address, word) in [(address, 0x3402DEAD), (address + 4, 0xC)] {
+        if let Ok(slot) = memory.get_mut(address) {
+            *slot = word;
+        }
+    }
+}
+
+fn write(memory: &mut Memory, address: u32, bin: Vec<u8>) {
+    for (offset, byte) in bin.into_iter().enumerate() {
+        // writing single bytes can't fail
+        let _ = memory.set_byte(address + offset as u32, byte);
+    }
+}
+
+/// Assembles the files of a program and links them into one memory image
+///
+/// The first file is the main script of the program, the others are placed after it in each
+/// segment. Files that another file includes are only assembled where they are included. The
+/// program starts at the global `main` of any file.
+///
+/// The returned symbols are those of the main script together with the global labels of the
+/// other files, so source lines are only known for the main script. Every problem found in any
+/// file is returned together, the problems of other files than the main script name their file.
+/// A program with only warnings still links, returning its warnings with it.
+pub fn link(
+    sources: Vec<Source>,
+) -> Result<(Memory, LabelTable, Vec<Diagnostic>), Vec<Diagnostic>> {
+    link_listing(sources).map(|(memory, labels, _, warnings)| (memory, labels, warnings))
+}
+
+/// Links a program like [`link`], also listing what each source line of every file assembled to
+pub fn link_listing(
+    sources: Vec<Source>,
+) -> Result<(Memory, LabelTable, Listing, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let mut objects: Vec<Object> = sources
+        .into_iter()
+        .enumerate()
+        .map(|(i, source)| Object::parse(source, i == 0))
+        .collect();
+
+    let included: HashSet<PathBuf> = objects
+        .iter()
+        .flat_map(|object| object.expansion.includes())
+        .cloned()
+        .collect();
+    objects.retain(|object| match &object.path {
+        Some(path) if !object.main => {
+            !included.contains(&path.canonicalize().unwrap_or(path.clone()))
+        }
+        _ => true,
+    });
+
+    let mut segments = Segments::default();
+    for object in &mut objects {
+        object.find_labels(&mut segments);
+    }
+
+    let mut memory = Memory::new();
+    let mut segments = Segments::default();
+    for object in &mut objects {
+        object.assemble(&mut memory, &mut segments);
+    }
+
+    // the labels each file makes global, with the file defining them
+    let names: Vec<String> = objects.iter().map(Object::name).collect();
+    let mut exports: HashMap<String, (u32, usize)> = HashMap::new();
+    for (i, object) in objects.iter_mut().enumerate() {
+        let mut errors = vec![];
+        for (name, line) in object.labels.globals() {
+            let Some(address) = object.labels.get_label(name) else {
+                continue;
+            };
+            match exports.get(name) {
+                Some(&(_, first)) => {
+                    let file = names[first].clone();
+                    errors.push((
+                        line,
+                        AsmError::DuplicateGlobal {
+                            name: name.clone(),
+                            file,
+                        },
+                    ));
+                }
+                None => {
+                    exports.insert(name.clone(), (address, i));
+                }
+            }
+        }
+        for (line, error) in errors {
+            object.report(line, error);
+        }
+    }
+
+    for object in &mut objects {
+        // a global label that is not defined has to come from another file
+        let unresolved: Vec<(usize, String)> = object
+            .labels
+            .globals()
+            .filter(|(name, _)| {
+                object.labels.get_symbol(name).is_none() && !exports.contains_key(*name)
+            })
+            .map(|(name, line)| (line, name.clone()))
+            .collect();
+        for (line, name) in unresolved {
+            object.report(line, AsmError::UnresolvedGlobal(name));
+        }
+
+        for (name, (address, _)) in &exports {
+            if object.labels.get_symbol(name).is_none() {
+                object.labels.insert_label(name.clone(), *address);
+            }
+        }
+        object.relocate(&mut memory);
+    }
+
+    let mut diagnostics: Vec<Diagnostic> = objects
+        .iter_mut()
+        .flat_map(|object| {
+            let path = object.path.clone().filter(|_| !object.main);
+            object
+                .diagnostics
+                .drain(..)
+                .map(move |diagnostic| match &path {
+                    Some(path) => diagnostic.in_file(path.clone()),
+                    None => diagnostic,
+                })
+        })
+        .collect();
+    diagnostics.sort_by(|a, b| (&a.file, a.span.start).cmp(&(&b.file, b.span.start)));
+    if diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        return Err(diagnostics);
+    }
+
+    insert_guard(&mut memory, *segments.switch(Segment::Text));
+
+    let listing = Listing {
+        files: objects
+            .iter()
+            .map(|object| object.listing(&memory))
+            .collect(),
+    };
+
+    let main = objects.swap_remove(0);
+    let mut labels = main.labels;
+    labels.map_lines(|line| main.expansion.source_line(line));
+    if let Some((address, _)) = exports.get("main") {
+        labels.set_entry(*address);
+    }
+    Ok((memory, labels, listing, diagnostics))
+}
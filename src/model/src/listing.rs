@@ -0,0 +1,172 @@
+//! Listings of assembled programs and exports of their symbol tables
+//!
+//! A listing shows every source line of a program next to the address and encoding of what it
+//! placed in memory, with the instructions it expands to. Listings and symbol tables are made by
+//! [`link_listing`](crate::link_listing).
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use crate::parser::model::LabelTable;
+
+/// Part of what a source line placed in memory, at most one word
+pub struct Row {
+    pub address: u32,
+    pub bytes: Vec<u8>,
+    /// The instruction the bytes encode when they are a word of a text segment
+    pub instruction: Option<String>,
+    /// Source line (0 indexed) in the file
+    pub line: usize,
+}
+
+/// The listing of one file of a program
+pub struct FileListing {
+    pub path: Option<PathBuf>,
+    pub source: String,
+    /// What the lines of the file placed in memory, in the order they were assembled
+    pub rows: Vec<Row>,
+}
+
+/// The listing of a program, one file after another
+pub struct Listing {
+    pub files: Vec<FileListing>,
+}
+
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut write_row =
+            |address: &str, code: &str, instruction: &str, line: &str, text: &str| {
+                let row =
+                    format!("{address:<10}  {code:<11}  {instruction:<32}  {line:>5}  {text}");
+                writeln!(f, "{}", row.trim_end())
+            };
+        write_row("Address", "Code", "Instruction", "Line", "Source")?;
+
+        for file in &self.files {
+            if let Some(path) = file.path.as_ref().filter(|_| self.files.len() > 1) {
+                write_row("", "", "", "", "")?;
+                write_row(&path.display().to_string(), "", "", "", "")?;
+            }
+
+            let mut rows: BTreeMap<usize, Vec<&Row>> = BTreeMap::new();
+            for row in &file.rows {
+                rows.entry(row.line).or_default().push(row);
+            }
+            for (line, text) in file.source.lines().enumerate() {
+                let number = (line + 1).to_string();
+                let rows = rows.get(&line).map(Vec::as_slice).unwrap_or_default();
+                if rows.is_empty() {
+                    write_row("", "", "", &number, text)?;
+                }
+                // the source is only shown next to the first row of its line
+                for (n, row) in rows.iter().enumerate() {
+                    let code = match (&row.instruction, row.bytes.as_slice()) {
+                        (Some(_), &[a, b, c, d]) => {
+                            format!("0x{:08x}", u32::from_le_bytes([a, b, c, d]))
+                        }
+                        _ => row
+                            .bytes
+                            .iter()
+                            .map(|byte| format!("{byte:02x}"))
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    };
+                    let address = format!("0x{:08x}", row.address);
+                    let instruction = row.instruction.as_deref().unwrap_or_default();
+                    match n {
+                        0 => write_row(&address, &code, instruction, &number, text)?,
+                        _ => write_row(&address, &code, instruction, "", "")?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A label in an exported symbol table
+#[derive(Serialize)]
+pub struct LabelSymbol {
+    pub name: String,
+    pub address: u32,
+    /// Whether it is named by `.globl`
+    pub global: bool,
+}
+
+/// A named constant in an exported symbol table
+#[derive(Serialize)]
+pub struct ConstantSymbol {
+    pub name: String,
+    pub value: i64,
+}
+
+/// The symbols of a program, which can be exported as text or JSON
+///
+/// Labels are sorted by address and constants by name. Local and numeric labels appear under the
+/// unique names the assembler gives them, like `main.loop`.
+#[derive(Serialize)]
+pub struct SymbolTable {
+    /// The address the program starts at
+    pub entry: u32,
+    pub labels: Vec<LabelSymbol>,
+    pub constants: Vec<ConstantSymbol>,
+}
+
+impl SymbolTable {
+    pub fn new(labels: &LabelTable) -> Self {
+        let globals: Vec<&String> = labels.globals().map(|(name, _)| name).collect();
+        let mut label_symbols: Vec<LabelSymbol> = labels
+            .labels()
+            .map(|(name, address)| LabelSymbol {
+                name: name.clone(),
+                address,
+                global: globals.contains(&name),
+            })
+            .collect();
+        label_symbols.sort_by(|a, b| (a.address, &a.name).cmp(&(b.address, &b.name)));
+
+        let mut constants: Vec<ConstantSymbol> = labels
+            .constants()
+            .map(|(name, value)| ConstantSymbol {
+                name: name.clone(),
+                value,
+            })
+            .collect();
+        constants.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            entry: labels.entry(),
+            labels: label_symbols,
+            constants,
+        }
+    }
+
+    /// Exports the symbol table as pretty printed JSON
+    pub fn to_json(&self) -> String {
+        // every field serializes to a JSON value
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl fmt::Display for SymbolTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Entry: 0x{:08x}", self.entry)?;
+        writeln!(f, "\nLabels:")?;
+        for label in &self.labels {
+            match label.global {
+                true => writeln!(f, "0x{:08x}  {} (global)", label.address, label.name)?,
+                false => writeln!(f, "0x{:08x}  {}", label.address, label.name)?,
+            }
+        }
+        if !self.constants.is_empty() {
+            writeln!(f, "\nConstants:")?;
+            for constant in &self.constants {
+                writeln!(f, "{} = {}", constant.name, constant.value)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -4,12 +4,12 @@ use std::u32;
 
 use anyhow::Result;
 
-use crate::{Memory, parser::{
-    self,
-    model::{LabelTable, Line, Segment, Segments, STACK_BASE, TEXT_BASE},
-}, pipeline::{self, PipelineState}, syscall::{resolve_syscall, Syscall}};
+use crate::{Memory, linker::{link, Source}, parser::{
+    Diagnostic,
+    model::{LabelTable, STACK_BASE, TEXT_BASE},
+}, pipeline::{self, PipelineState}, syscall::{resolve_syscall, Syscall}, RuntimeError};
 
-use crate::{RegisterFile, Register};
+use crate::{disassemble, elf::{self, ElfError}, RegisterFile, Register};
 
 use crate::callback::Callback;
 use crate::syscall::SyscallDiscriminants;
@@ -25,6 +25,8 @@ pub struct Machine {
     pending_syscall: Option<Syscall>,
     callbacks: HashMap<SyscallDiscriminants, Callback>,
     input: Option<String>,
+    error: Option<RuntimeError>,
+    breakpoint: Option<u32>,
 }
 
 impl Machine {
@@ -57,11 +59,13 @@ impl Machine {
     /// Note that this will not reset the contents of memory or registers for that see
     /// [`hard_reset`]
     pub fn reset(&mut self) {
-        self.pc = TEXT_BASE;
+        self.pc = self.symbols.entry();
         self.state = PipelineState::default();
         self.regs = RegisterFile::default();
         self.pending_syscall = None;
         self.input = None;
+        self.error = None;
+        self.breakpoint = None;
     }
 
     /// Fully resets this machine including memory contents and registers
@@ -72,9 +76,21 @@ impl Machine {
     }
 
     /// Set the contents of this machines memory to `mem`
+    ///
+    /// The machine starts over at the entry point of the program
     pub fn flash(&mut self, mem: Memory, syms: LabelTable) {
         self.memory = mem;
         self.symbols = syms;
+        self.pc = self.symbols.entry();
+    }
+
+    /// Loads an executable into this machines memory like [`flash`](Self::flash)
+    ///
+    /// Executables have no source, so no source lines are known for their instructions.
+    pub fn load_elf(&mut self, bytes: &[u8]) -> Result<(), ElfError> {
+        let (mem, syms) = elf::read(bytes)?;
+        self.flash(mem, syms);
+        Ok(())
     }
 
     /// Gets the current source code line
@@ -88,6 +104,24 @@ impl Machine {
         ]
     }
 
+    /// Disassembles the word at an address, naming targets by the labels of the program
+    pub fn disassemble(&self, address: u32) -> Option<String> {
+        let word = self.memory.get(address).ok()?;
+        Some(disassemble(word, address, &self.symbols))
+    }
+
+    /// Gets the instruction in each stage of the pipeline as assembly
+    pub fn current_instructions(&self) -> [Option<String>; 5] {
+        [
+            self.state.if_id.pc,
+            self.state.id_ex.pc,
+            self.state.ex_mem.pc,
+            self.state.mem_wb.pc,
+            self.state.pipe_out.pc,
+        ]
+        .map(|pc| (pc >= TEXT_BASE).then(|| self.disassemble(pc)).flatten())
+    }
+
     /// Gets the current pipeline stages
     pub fn pipeline(&self) -> &PipelineState {
         &self.state
@@ -112,6 +146,21 @@ impl Machine {
         Ok(())
     }
 
+    /// Gets the error that stopped this machine if there was one
+    pub fn error(&self) -> Option<&RuntimeError> {
+        self.error.as_ref()
+    }
+
+    /// Checks if this machine is paused at a break instruction
+    pub fn at_breakpoint(&self) -> bool {
+        self.breakpoint.is_some()
+    }
+
+    /// Gets the source line (0 indexed) of the break instruction this machine is paused at
+    pub fn breakpoint_line(&self) -> Option<usize> {
+        self.breakpoint.and_then(|pc| self.symbols.get_line(pc))
+    }
+
     /// Checks if there is a pending syscall
     ///
     /// # Returns
@@ -128,6 +177,8 @@ impl Machine {
     pub fn cycle(&mut self) -> ControlFlow<()> {
         return match self.pending_syscall.clone() {
             None => {
+                self.breakpoint = None;
+
                 match pipeline::pipe_cycle(
                     &mut self.pc,
                     &mut self.regs,
@@ -144,9 +195,16 @@ impl Machine {
                         ControlFlow::Continue(())
                     }
 
-                    Err(err) => {
-                        println!("{}", err.backtrace());
-                        println!("{}", err.to_string());
+                    Err(mut err) => {
+                        err.line = self.symbols.get_line(err.pc);
+
+                        // report the error like a failed syscall
+                        let message = err.to_string();
+                        if let Some(callback) = self.callbacks.get_mut(&SyscallDiscriminants::Error) {
+                            callback.call(Some(&message));
+                        }
+
+                        self.error = Some(err);
                         ControlFlow::Break(())
                     }
                 }
@@ -171,6 +229,8 @@ impl Machine {
         let mut resolved = true;
         // Whether to run the callback for the given syscall
         let mut run_callback = true;
+        // Message describing where a breakpoint paused the machine
+        let breakpoint_message;
 
         // Handle calls internally and obtain any message to pass to callbacks
         let (flow, info) = match syscall {
@@ -178,8 +238,22 @@ impl Machine {
             Syscall::Error(message) => (ControlFlow::Break(()), Some(message)),
             Syscall::Quit => (ControlFlow::Break(()), None),
 
+            Syscall::Breakpoint => {
+                // pause at the break instruction which has just left the pipeline
+                let pc = self.state.pipe_out.pc;
+                self.breakpoint = Some(pc);
+                breakpoint_message = match self.symbols.get_line(pc) {
+                    Some(line) => format!("Breakpoint at 0x{pc:08x} (line {})", line + 1),
+                    None => format!("Breakpoint at 0x{pc:08x}"),
+                };
+                (ControlFlow::Break(()), Some(&breakpoint_message))
+            }
+
             // TODO: Consolidate to some function to make more readable
-            Syscall::ReadInt | Syscall::ReadFloat | Syscall::ReadString => {
+            Syscall::ReadInt
+            | Syscall::ReadFloat
+            | Syscall::ReadDouble
+            | Syscall::ReadString => {
                 match &self.input.clone() {
                     None => {
                         // No value is present, so stop cycling and mark syscall as unresolved
@@ -255,49 +329,97 @@ impl Machine {
 }
 
 /// Method that create a memory instance from a script file
-pub fn assembler(mut script: String) -> Result<(Memory, LabelTable)> {
-    // Replace CRLF line endings
-    script = script.replace("\r\n", "\n");
-    // Ensure newline to prevent assembler error
-    script.push('\n');
-
-    // parse assembly
-    let lines = parser::parse_string(&script)?;
-    let labels = parser::compute_labels(&lines);
-
-    // for each line in the parsed assembly assemble that line and add the result to a vec
-    let mut memory = Memory::new();
-    let mut segments = Segments::default();
-    // current segement pc
-    let mut pc = segments.switch(Segment::Text);
-    for line in &lines {
-        match line {
-            Line::Instruction(ins) => {
-                for word in ins {
-                    let (bin, _) = word.asm(&labels, *pc);
-
-                    /* DEBUG
-                    let mut s = String::new();
-                    for byte in bin.clone() {
-                        s.push_str(&format!("{:08b} ", byte));
-                    }
-                    println!("{pc:x} {s}\t{word:?}");
-                     */
+///
+/// The script is the only file of the program, the files it includes are relative to the
+/// working directory. Warnings are left out, see [`link`] for them and for programs made of
+/// several files.
+pub fn assembler(script: String) -> Result<(Memory, LabelTable), Vec<Diagnostic>> {
+    link(vec![Source::new(None, script)]).map(|(memory, labels, _)| (memory, labels))
+}
 
-                    for byte in bin {
-                        memory.set_byte(*pc, byte)?;
-                        *pc += 1;
-                    }
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::Machine;
+    use crate::callback::Callback;
+    use crate::elf::{self, ElfError, Endian};
+    use crate::syscall::SyscallDiscriminants;
+    use crate::{link_listing, Source};
+
+    const PROGRAM: &str = "
+.data
+message: .asciiz \"Hello, ELF!\\n\"
+bytes: .byte 1, 2, 3
+half: .half -2
+word: .word 123456
+.text
+.globl main
+    nop
+main:
+    la $a0, message
+    li $v0, 4
+    syscall
+    lb $a0, bytes + 2
+    li $v0, 1
+    syscall
+    lh $a0, half
+    syscall
+    lw $a0, word
+    syscall
+    li $v0, 10
+    syscall
+";
+
+    /// Assembles the program into an executable
+    fn executable(endian: Endian) -> Vec<u8> {
+        let (memory, labels, listing, _) =
+            link_listing(vec![Source::new(None, PROGRAM.to_string())]).unwrap();
+        elf::write(&memory, &labels, &listing, endian)
+    }
+
+    #[test]
+    fn load_elf_runs_program() {
+        for endian in [Endian::Little, Endian::Big] {
+            let mut machine = Machine::default();
+            machine.load_elf(&executable(endian)).unwrap();
+            assert_eq!(machine.symbols.get_label("main"), Some(machine.pc), "{endian:?}");
+
+            let output = Rc::new(RefCell::new(String::new()));
+            let printed = output.clone();
+            machine.get_callbacks().insert(
+                SyscallDiscriminants::Print,
+                Callback::new(Box::new(move |info| {
+                    printed.borrow_mut().push_str(info.unwrap());
+                })),
+            );
+            for _ in 0..1000 {
+                if machine.cycle().is_break() {
+                    break;
                 }
             }
-            Line::Segment(seg) => pc = segments.switch(*seg),
-            _ => {}
+            assert!(machine.error.is_none(), "{endian:?}");
+            assert_eq!(*output.borrow(), "Hello, ELF!\n3-2123456", "{endian:?}");
         }
     }
-    // insert guard instruction that causes the program to crash if it is encountered
-    pc = segments.switch(Segment::Text);
-    *memory.get_mut(*pc)? = 0x3402DEAD;
-    *memory.get_mut(*pc + 4)? = 0xC;
 
-    Ok((memory, labels))
+    #[test]
+    fn load_elf_rejects_other_toolchains() {
+        let mut bytes = executable(Endian::Big);
+        // the owner of the note that marks executables of this assembler
+        let owner = bytes.windows(4).position(|owner| owner == b"RAJA").unwrap();
+        bytes[owner] = b'G';
+        let mut machine = Machine::default();
+        assert_eq!(machine.load_elf(&bytes), Err(ElfError::OtherToolchain));
+    }
+
+    #[test]
+    fn load_elf_rejects_segments_past_memory() {
+        let mut bytes = executable(Endian::Little);
+        // the size in memory of the first program header, which follows the file header
+        bytes[72..76].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut machine = Machine::default();
+        assert_eq!(machine.load_elf(&bytes), Err(ElfError::BadSegment));
+    }
 }
@@ -16,6 +16,21 @@ impl Align {
     }
 }
 
+/// The width of a load or store performed by the memory pipeline stage
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessWidth {
+    Byte,
+    Half,
+    #[default]
+    Word,
+    /// The most significant bytes of an unaligned word (lwl/swl)
+    WordLeft,
+    /// The least significant bytes of an unaligned word (lwr/swr)
+    WordRight,
+    /// An aligned pair of words holding a double (ldc1/sdc1)
+    Double,
+}
+
 /// Handles memory
 ///
 /// Memory is allocated in pages of words
@@ -25,6 +40,8 @@ impl Align {
 pub struct Memory {
     data: HashMap<u32, Vec<u32>>,
     page_size: usize,
+    /// Word reserved by the last ll, which a following sc needs to store
+    link: Option<u32>,
 }
 
 impl Default for Memory {
@@ -39,9 +56,36 @@ impl Memory {
         Self {
             data: HashMap::new(),
             page_size: 1024,
+            link: None,
         }
     }
 
+    /// Reserves the word at an address for a following store conditional
+    pub fn load_linked(&mut self, address: u32) {
+        self.link = Some(address & !3);
+    }
+
+    /// Checks if the reservation on the word at an address still holds, clearing it either way.
+    ///
+    /// A store conditional only stores when this returns true.
+    pub fn store_conditional(&mut self, address: u32) -> bool {
+        self.link.take() == Some(address & !3)
+    }
+
+    /// Clears the reservation if a store of `size` bytes at an address overlaps the reserved word
+    pub fn invalidate_link(&mut self, address: u32, size: u32) {
+        let first = address & !3;
+        let last = address.wrapping_add(size - 1) & !3;
+        if self.link.is_some_and(|link| (first..=last).contains(&link)) {
+            self.link = None;
+        }
+    }
+
+    /// Clears the reservation, which happens whenever an exception is taken or returned from
+    pub fn clear_link(&mut self) {
+        self.link = None;
+    }
+
     /// Sets a single byte
     pub fn set_byte(&mut self, address: u32, val: u8) -> Result<()> {
         let aligned_address = address / 4;
@@ -68,7 +112,7 @@ impl Memory {
     }
 
     /// Get a single byte
-    pub fn get_byte(&mut self, address: u32) -> Result<u8> {
+    pub fn get_byte(&self, address: u32) -> Result<u8> {
         let aligned_address = address / 4;
         let align_offset = address % 4;
         let page_num = aligned_address / self.page_size as u32;
@@ -81,6 +125,33 @@ impl Memory {
         })
     }
 
+    /// Sets a halfword aligned halfword
+    pub fn set_half(&mut self, address: u32, val: u16) -> Result<()> {
+        if !address.is_multiple_of(2) {
+            bail!(format!(
+                "Unaligned memory access: {address:08X} expected to be aligned to 2 bytes"
+            ));
+        }
+
+        let [low, high] = val.to_le_bytes();
+        self.set_byte(address, low)?;
+        self.set_byte(address + 1, high)
+    }
+
+    /// Gets a halfword aligned halfword
+    pub fn get_half(&mut self, address: u32) -> Result<u16> {
+        if !address.is_multiple_of(2) {
+            bail!(format!(
+                "Unaligned memory access: {address:08X} expected to be aligned to 2 bytes"
+            ));
+        }
+
+        Ok(u16::from_le_bytes([
+            self.get_byte(address)?,
+            self.get_byte(address + 1)?,
+        ]))
+    }
+
     /// Gets the value of an aligned memory location.
     ///
     /// Note: If the address is not word aligned it will read unaligned but cannot read past the
@@ -1,64 +1,43 @@
 //! TODO: Needs to be able to handle pseudo-instructions and comments
 
-use std::ops::Deref;
+use std::collections::HashMap;
 
-use anyhow::{anyhow, Result};
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_while},
     character::complete::space0,
-    combinator::{eof, map, opt},
-    error::{context, VerboseError, VerboseErrorKind},
-    multi::many_till,
-    sequence::{delimited, preceded, terminated},
+    combinator::{consumed, eof, map, opt},
+    error::{context, VerboseError},
+    sequence::{delimited, preceded, separated_pair, terminated},
     Finish, IResult,
 };
 
+mod diagnostic;
 mod directives;
+mod expression;
 mod instruction;
 mod label;
 pub mod model;
 mod numbers;
 mod opcode;
+mod preprocess;
+mod pseudo;
 mod register;
 
+pub use diagnostic::{AsmError, Diagnostic, Severity};
+pub use expression::{constant, expression, Constants, Expr, Operator};
 pub use instruction::instruction;
 pub use label::label;
 pub use numbers::*;
 pub use opcode::opcode;
-pub use opcode::opcode_name;
-pub use register::register;
+pub use preprocess::{preprocess, Expansion};
+pub use register::{cop0_register, fcr_register, register};
 
+use diagnostic::syntax_error;
 use model::{LabelTable, Line};
 
 use self::model::{Segment, Segments};
 
-/// Converts an error trace into a usable error message
-fn convert_error<I>(input: I, error: VerboseError<I>) -> String
-where
-    I: Deref<Target = str>,
-{
-    // remove nom-specific messages
-    let mut errors = vec![];
-
-    for error in error.errors {
-        match error.1 {
-            VerboseErrorKind::Context(_) => {
-                errors.push(error);
-            }
-            VerboseErrorKind::Char(_) => {
-                errors.push(error);
-            }
-            VerboseErrorKind::Nom(_) => {
-                // only use nom errors in debug mode
-                #[cfg(debug_assertions)]
-                errors.push(error);
-            }
-        }
-    }
-    nom::error::convert_error(input, VerboseError { errors })
-}
-
 pub fn blank(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
     let (input, _) = preceded(space0, alt((tag("\n"), eof, map(comment, |_| ""))))(input)?;
     Ok((input, Line::Blank))
@@ -78,12 +57,15 @@ pub fn comment(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
     )(input)
 }
 
-pub fn parse_line(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
+pub fn parse_line<'a>(
+    input: &'a str,
+    constants: &Constants,
+) -> IResult<&'a str, Line, VerboseError<&'a str>> {
     context(
         "Parsing Line",
         delimited(
             space0,
-            instruction,
+            |input| instruction(input, constants),
             context(
                 "Instructions must be on their own lines",
                 preceded(space0, alt((tag("\n"), eof, map(comment, |_| "")))),
@@ -92,47 +74,236 @@ pub fn parse_line(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
     )(input)
 }
 
-pub fn parse_string(input: &str) -> Result<Vec<Line>> {
-    let (_, (output, _)) = many_till(
-        alt((
+/// Parses the definition of a named constant
+/// `<name> = <expression>`
+pub fn constant_line<'a>(
+    input: &'a str,
+    constants: &Constants,
+) -> IResult<&'a str, Line, VerboseError<&'a str>> {
+    map(
+        delimited(
+            space0,
+            separated_pair(
+                label::identifier,
+                delimited(space0, tag("="), space0),
+                context("Expected the value of the constant", expression(constants)),
+            ),
+            preceded(space0, alt((tag("\n"), eof, map(comment, |_| "")))),
+        ),
+        |(name, expr)| Line::Constant {
+            name: name.to_string(),
+            expr,
+            redefinable: false,
+        },
+    )(input)
+}
+
+/// Parses a script into lines, each paired with the source line it starts on (0 indexed)
+///
+/// Lines that fail to parse are skipped and reported as diagnostics, so every syntax error in the
+/// script is found at once.
+pub fn parse_string(input: &str) -> (Vec<(usize, Line)>, Vec<Diagnostic>) {
+    let mut lines = vec![];
+    let mut diagnostics = vec![];
+    let mut rest = input;
+    let mut constants = Constants::new();
+
+    // a label can share its source line with an instruction so count the newlines consumed
+    let mut source_line = 0;
+    while !rest.is_empty() {
+        let parsed = consumed(alt((
             comment,
             blank,
             terminated(label, preceded(space0, opt(tag("\n")))),
-            parse_line,
-        )),
-        eof,
-    )(input)
-    .finish()
-    .map_err(|e| anyhow!("{}", convert_error(input, e)))?;
-    Ok(output)
+            |input| constant_line(input, &constants),
+            |input| parse_line(input, &constants),
+        )))(rest)
+        .finish();
+
+        match parsed {
+            Ok((remaining, (text, line))) if !text.is_empty() => {
+                // constants are folded into the lines after them
+                if let Line::Constant {
+                    name,
+                    expr: Expr::Value(value),
+                    ..
+                } = &line
+                {
+                    constants.insert(name.clone(), *value);
+                }
+                lines.push((source_line, line));
+                source_line += text.matches('\n').count();
+                rest = remaining;
+            }
+            result => {
+                if let Err(error) = result {
+                    diagnostics.push(syntax_error(input, error));
+                }
+                // skip to the next line
+                let end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+                source_line += 1;
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    (lines, diagnostics)
 }
 
-pub fn compute_labels(input: &[Line]) -> LabelTable {
-    let mut labels = LabelTable::default();
-    let mut segments = Segments::default();
-    let mut pc = segments.switch(Segment::Text);
+/// Gives local and numeric labels the unique names they are known by in the symbol table
+///
+/// A local label `.loop` belongs to the closest label before it that is neither local nor
+/// numeric, so after `main:` it becomes `main.loop`. The numeric label `1` becomes `main.1_0` the
+/// first time it is defined after `main:`, `main.1_1` the second time and so on. References are
+/// renamed to match, `1b` naming the closest `1` before it and `1f` the closest one after it.
+fn qualify_labels(input: &mut [(usize, Line)]) {
+    // the label each line belongs to
+    let mut scopes = Vec::with_capacity(input.len());
+    let mut scope = String::new();
+    // the lines each numeric label is defined on, with its qualified name
+    let mut numeric: HashMap<String, Vec<(usize, String)>> = HashMap::new();
+    let mut count: HashMap<(String, String), usize> = HashMap::new();
 
-    for (i, line) in input.iter().enumerate() {
-        match line {
-            Line::Label(name) => {
-                labels.insert_label(name.clone(), *pc);
+    for (index, (_, line)) in input.iter_mut().enumerate() {
+        if let Line::Label(name) = line {
+            if let Some(local) = name.strip_prefix('.') {
+                *name = format!("{scope}.{local}");
+            } else if name.starts_with(|c: char| c.is_ascii_digit()) {
+                let n = count.entry((scope.clone(), name.clone())).or_default();
+                let qualified = format!("{scope}.{name}_{n}");
+                *n += 1;
+                numeric
+                    .entry(name.clone())
+                    .or_default()
+                    .push((index, qualified.clone()));
+                *name = qualified;
+            } else {
+                scope = name.clone();
             }
+        }
+        scopes.push(scope.clone());
+    }
+
+    for (index, (_, line)) in input.iter_mut().enumerate() {
+        line.symbols_mut(&mut |name| {
+            if let Some(local) = name.strip_prefix('.') {
+                *name = format!("{}.{local}", scopes[index]);
+                return;
+            }
+            let (number, direction) = name.split_at(name.len() - 1);
+            if !number.starts_with(|c: char| c.is_ascii_digit()) {
+                return;
+            }
+            let defined = numeric.get(number).into_iter().flatten();
+            let found = match direction {
+                "b" => defined.rev().find(|(line, _)| *line < index),
+                _ => defined.clone().find(|(line, _)| *line > index),
+            };
+            // a numeric label that is not defined is reported by its reference
+            if let Some((_, qualified)) = found {
+                *name = qualified.clone();
+            }
+        });
+    }
+}
+
+/// Finds the address of every label and the value of every named constant
+///
+/// Labels and constants that are defined more than once keep their first definition and are
+/// reported with the source line that redefines them, except for `.set` constants which keep
+/// their last definition. Constants are computed once every label is known, so they can use
+/// labels defined anywhere.
+///
+/// A label takes the address of the data after it, once that is aligned. The lines are placed
+/// from the current position in `segments`, which is left after them so the next file of a
+/// program follows on. Local and numeric labels are renamed to be unique first, which is how
+/// they appear in the returned labels.
+pub fn compute_labels(
+    input: &mut [(usize, Line)],
+    segments: &mut Segments,
+) -> (LabelTable, Vec<(usize, AsmError)>) {
+    qualify_labels(input);
+    let input = &*input;
+    let mut labels = LabelTable::default();
+    // the first line each name is defined on and whether it can be defined again
+    let mut defined: HashMap<&String, (usize, bool)> = HashMap::new();
+    let mut constants = vec![];
+    let mut errors = vec![];
+    segments.switch(Segment::Text);
+    // labels waiting for the address of the next instruction
+    let mut pending = vec![];
+
+    for (i, line) in input {
+        let (name, redefinable) = match line {
+            Line::Label(name) | Line::Extern { name, .. } => (name, false),
+            Line::Constant {
+                name, redefinable, ..
+            } => (name, *redefinable),
             Line::Instruction(ins) => {
-                labels.insert_line(i, *pc);
-                let mut len = 0;
-                for inst in ins {
-                    len += match inst {
-                        model::Instruction::Literal { data } => data.len(),
-                        _ => 4,
-                    };
+                for (n, word) in ins.iter().enumerate() {
+                    let address = segments.place(word);
+                    if n == 0 {
+                        labels.insert_line(*i, address);
+                        for name in pending.drain(..) {
+                            labels.insert_label(name, address);
+                        }
+                    }
+                }
+                continue;
+            }
+            Line::Segment(seg) => {
+                let pc = *segments.pc();
+                for name in pending.drain(..) {
+                    labels.insert_label(name, pc);
                 }
-                *pc += len as u32;
+                segments.switch(*seg);
+                continue;
             }
-            Line::Segment(seg) => pc = segments.switch(*seg),
-            _ => {}
+            Line::Align(n) => {
+                segments.align(*n);
+                continue;
+            }
+            Line::Global(names) => {
+                for name in names {
+                    labels.insert_global(name.clone(), *i);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        match defined.get(name) {
+            Some(&(_, true)) if redefinable => {}
+            Some(&(first, _)) => {
+                let name = name.clone();
+                errors.push((*i, AsmError::DuplicateLabel { name, first }));
+                continue;
+            }
+            None => {
+                defined.insert(name, (*i, redefinable));
+            }
+        }
+        match line {
+            Line::Constant { expr, .. } => constants.push((*i, name, expr)),
+            Line::Extern { size, .. } => {
+                labels.insert_label(name.clone(), segments.reserve_extern(*size));
+                labels.insert_global(name.clone(), *i);
+            }
+            _ => pending.push(name.clone()),
+        }
+    }
+    let pc = *segments.pc();
+    for name in pending {
+        labels.insert_label(name, pc);
+    }
+
+    // in order, so a constant can use the constants before it
+    for (i, name, expr) in constants {
+        match expr.eval(&labels) {
+            Ok(value) => labels.insert_constant(name.clone(), value),
+            Err(error) => errors.push((i, error)),
         }
     }
 
-    std::fs::write("data/labels.txt", format!("{:#?}", input)).unwrap();
-    labels
+    (labels, errors)
 }
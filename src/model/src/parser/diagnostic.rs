@@ -0,0 +1,404 @@
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use nom::branch::alt;
+use nom::character::complete::{digit1, one_of};
+use nom::combinator::recognize;
+use nom::error::{VerboseError, VerboseErrorKind};
+use nom::sequence::pair;
+use nom::IResult;
+use thiserror::Error;
+
+use super::label::identifier;
+use super::opcode;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found while assembling, located in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Byte range of the problem within the script
+    pub span: Range<usize>,
+    /// Source line (0 indexed)
+    pub line: usize,
+    /// Column in characters (0 indexed)
+    pub column: usize,
+    pub message: String,
+    /// Extra information about the problem, like how it could be fixed
+    pub notes: Vec<String>,
+    /// The file of the problem, when it is not in the main script of the program
+    pub file: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    /// Creates an error covering `span` of `script`
+    pub fn error(script: &str, span: Range<usize>, message: String, notes: Vec<String>) -> Self {
+        let before = &script[..span.start];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Self {
+            severity: Severity::Error,
+            line: before.matches('\n').count(),
+            column: before[line_start..].chars().count(),
+            span,
+            message,
+            notes,
+            file: None,
+        }
+    }
+
+    /// Creates a warning covering `span` of `script`, which does not stop the program from
+    /// assembling
+    pub fn warning(script: &str, span: Range<usize>, message: String, notes: Vec<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(script, span, message, notes)
+        }
+    }
+
+    /// Places the diagnostic in another file than the main script
+    pub fn in_file(mut self, file: PathBuf) -> Self {
+        self.file = Some(file);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file.display())?;
+        }
+        write!(
+            f,
+            "line {}, column {}: {}: {}",
+            self.line + 1,
+            self.column + 1,
+            self.severity,
+            self.message
+        )?;
+        for note in &self.notes {
+            write!(f, "\n    note: {note}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Problems with the operands of an instruction that are only found once every label is known
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AsmError {
+    #[error("Undefined label `{0}`")]
+    UndefinedLabel(String),
+    #[error("Label `{name}` is already defined on line {}", .first + 1)]
+    DuplicateLabel { name: String, first: usize },
+    #[error("Immediate {value} does not fit in 16 bits")]
+    ImmediateRange { value: i64, signed: bool },
+    #[error("Branch to `{label}` is {offset} instructions away, more than a branch can reach")]
+    BranchRange { label: String, offset: i64 },
+    #[error("Jump to `{0}` leaves the 256MB region of the jump")]
+    JumpRange(String),
+    #[error("Division by zero in `{0}`")]
+    DivisionByZero(String),
+    #[error("Global label `{name}` is already defined in {file}")]
+    DuplicateGlobal { name: String, file: String },
+    #[error("Global label `{0}` is not defined in any file")]
+    UnresolvedGlobal(String),
+    #[error("Value {value} does not fit in {size} byte(s) and is truncated")]
+    Truncated { value: i64, size: usize },
+    #[error("Misaligned data at 0x{address:08x}, it has to be aligned to {size} bytes")]
+    MisalignedData { size: u32, address: u32 },
+}
+
+impl AsmError {
+    /// How serious the problem is, only truncated values and data misaligned after `.align 0`
+    /// still assemble
+    pub fn severity(&self) -> Severity {
+        match self {
+            AsmError::Truncated { .. } | AsmError::MisalignedData { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// Locates the problem in line `line` of `script` and suggests a fix
+    ///
+    /// `labels` holds every defined label, used to suggest a fix for misspelled labels.
+    pub fn locate<'a>(
+        &self,
+        script: &str,
+        line: usize,
+        labels: impl Iterator<Item = &'a String>,
+    ) -> Diagnostic {
+        let start: usize = script.split_inclusive('\n').take(line).map(str::len).sum();
+        let text = script[start..].split('\n').next().unwrap_or("");
+
+        let (span, note) = match self {
+            AsmError::UndefinedLabel(name) => {
+                // local labels are written without the label they belong to
+                let local = name.find('.').map_or(&name[..], |i| &name[i..]);
+                let numeric = name.strip_suffix(['b', 'f']).filter(|number| {
+                    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+                });
+                let note = match (numeric, closest(name, labels)) {
+                    (Some(number), _) if name.ends_with('b') => {
+                        format!("define `{number}:` before it")
+                    }
+                    (Some(number), _) => format!("define `{number}:` after it"),
+                    (None, Some(label)) => format!("did you mean `{label}`?"),
+                    (None, None) => format!("define it with `{local}:` or check its spelling"),
+                };
+                (word_span(text, local.trim_start_matches('.')), note)
+            }
+            AsmError::DuplicateLabel { name, .. } => (
+                word_span(text, name.rsplit('.').next().unwrap_or(name)),
+                "rename one of the labels".to_string(),
+            ),
+            AsmError::ImmediateRange { signed, .. } => {
+                let range = if *signed {
+                    "-32768 to 32767"
+                } else {
+                    "0 to 65535"
+                };
+                (
+                    last_operand_span(text),
+                    format!("use a value from {range} or load it into a register with li"),
+                )
+            }
+            AsmError::BranchRange { label, .. } => (
+                word_span(text, label),
+                "branch over a j instruction to reach the label".to_string(),
+            ),
+            AsmError::JumpRange(label) => (
+                word_span(text, label),
+                "load the address with la and jump to it with jr".to_string(),
+            ),
+            AsmError::DivisionByZero(_) => (
+                last_operand_span(text),
+                "check the values of the labels and constants it divides by".to_string(),
+            ),
+            AsmError::DuplicateGlobal { name, .. } => (
+                word_span(text, name),
+                "remove one of the labels from `.globl` to keep it in its file".to_string(),
+            ),
+            AsmError::UnresolvedGlobal(name) => {
+                let note = match closest(name, labels) {
+                    Some(label) => format!("did you mean `{label}`?"),
+                    None => format!("define it with `{name}:` in one of the files"),
+                };
+                (word_span(text, name), note)
+            }
+            AsmError::Truncated { size, .. } => {
+                let bits = 8 * *size as u32;
+                let (min, max) = (-(1_i64 << (bits - 1)), (1_i64 << bits) - 1);
+                (
+                    operands_span(text),
+                    format!("use a value from {min} to {max} or a larger directive"),
+                )
+            }
+            AsmError::MisalignedData { size, address } => (
+                operands_span(text),
+                format!(
+                    "align it with `.align {}` or insert `.space {}` before it",
+                    size.trailing_zeros(),
+                    size - address % size
+                ),
+            ),
+        };
+        let span = start + span.start..start + span.end;
+        match self.severity() {
+            Severity::Error => Diagnostic::error(script, span, self.to_string(), vec![note]),
+            Severity::Warning => Diagnostic::warning(script, span, self.to_string(), vec![note]),
+        }
+    }
+}
+
+/// Converts a parser error into a diagnostic
+///
+/// `script` is the whole source being parsed, which locates the error.
+pub fn syntax_error(script: &str, error: VerboseError<&str>) -> Diagnostic {
+    // the innermost error locates the problem and the innermost context describes it
+    let rest = error.errors.first().map_or("", |(rest, _)| *rest);
+    let message = error
+        .errors
+        .iter()
+        .find_map(|(_, kind)| match kind {
+            VerboseErrorKind::Context(context) => Some(context.to_string()),
+            VerboseErrorKind::Char(c) => Some(format!("Expected '{c}'")),
+            VerboseErrorKind::Nom(_) => None,
+        })
+        .unwrap_or_else(|| "Syntax error".to_string());
+
+    let word_end = rest
+        .find(|c: char| c.is_whitespace() || ",#()".contains(c))
+        .unwrap_or(rest.len());
+    let word = &rest[..word_end];
+    let note = match message.as_str() {
+        "Unknown Opcode" => match similar_mnemonic(word) {
+            Some(mnemonic) => format!("did you mean `{mnemonic}`?"),
+            None => "check the spelling of the instruction".to_string(),
+        },
+        "Unknown register" | "Expected '$' to prepend register" => {
+            "registers are written like `$t0` or `$8`".to_string()
+        }
+        "separator" => "separate operands with a comma".to_string(),
+        "Expected a constant expression" => {
+            "only numbers and constants defined above can be used here".to_string()
+        }
+        "Shift amount out of range" => "use a value from 0 to 31".to_string(),
+        "Condition flag out of range" => "use a value from 0 to 7".to_string(),
+        "Value does not fit in 32 bits" => {
+            "use a value from -2147483648 to 4294967295".to_string()
+        }
+        "Unknown escape sequence" => {
+            "use one of \\n \\t \\r \\\\ \\\" \\' \\xNN or \\NNN".to_string()
+        }
+        "Expected \" to close the string" => {
+            "end the string on the same line and write a quote inside it as \\\"".to_string()
+        }
+        "Instructions must be on their own lines" => {
+            "move the rest of the line onto a new line or start a comment with #".to_string()
+        }
+        _ => "check the operands of the instruction".to_string(),
+    };
+
+    let start = script.len() - rest.len();
+    let span = if word.is_empty() {
+        // nothing to point at, so cover the code of the whole line instead
+        let line_start = script[..start].rfind('\n').map_or(0, |i| i + 1);
+        let text = script[line_start..].split('\n').next().unwrap_or("");
+        let code = code_span(text);
+        line_start + code.start..line_start + code.end
+    } else {
+        start..start + word.len()
+    };
+    Diagnostic::error(script, span, message, vec![note])
+}
+
+/// Finds a word in a line, matching whole identifiers or numeric label references like `2f` only
+fn word_span(text: &str, word: &str) -> Range<usize> {
+    let token = |input| -> IResult<&str, &str, VerboseError<&str>> {
+        alt((identifier, recognize(pair(digit1, one_of("bf")))))(input)
+    };
+    let mut rest = text;
+    while !rest.is_empty() {
+        match token(rest) {
+            Ok((_, found)) if found == word => {
+                let start = text.len() - rest.len();
+                return start..start + word.len();
+            }
+            Ok((after, _)) => rest = after,
+            Err(_) => {
+                let skip = rest.chars().next().map_or(1, char::len_utf8);
+                rest = &rest[skip..];
+            }
+        }
+    }
+    code_span(text)
+}
+
+/// Finds the last operand of an instruction
+fn last_operand_span(text: &str) -> Range<usize> {
+    let code = code_span(text);
+    match text[code.clone()].rfind(',') {
+        Some(i) => {
+            let operand = &text[code.start + i + 1..code.end];
+            code.end - operand.trim_start().len()..code.end
+        }
+        None => code,
+    }
+}
+
+/// Finds the operands of an instruction or directive, after its name and any labels before it
+fn operands_span(text: &str) -> Range<usize> {
+    let code = code_span(text);
+    let mut operands = &text[code.clone()];
+    loop {
+        let end = operands.find(char::is_whitespace).unwrap_or(operands.len());
+        let label = operands[..end].ends_with(':');
+        operands = operands[end..].trim_start();
+        if !label {
+            break;
+        }
+    }
+    code.end - operands.len()..code.end
+}
+
+/// Finds the code of a line, without the surrounding whitespace or a comment
+pub(super) fn code_span(text: &str) -> Range<usize> {
+    let code = text.split('#').next().unwrap_or("").trim_end();
+    code.len() - code.trim_start().len()..code.len()
+}
+
+/// Counts the single character edits between two words
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitute = diagonal + (ca != *cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitute.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Finds the label closest to a misspelled one, if any is close enough to be a typo
+fn closest<'a>(name: &str, labels: impl Iterator<Item = &'a String>) -> Option<&'a String> {
+    labels
+        .map(|label| (edit_distance(name, label), label))
+        .filter(|(distance, label)| *distance <= 2 && *distance < label.len())
+        .min()
+        .map(|(_, label)| label)
+}
+
+/// Finds a mnemonic one edit away from an unknown one
+fn similar_mnemonic(word: &str) -> Option<String> {
+    const ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz0123456789.";
+    let word = word.to_lowercase();
+    let chars: Vec<char> = word.chars().collect();
+
+    let mut candidates = vec![];
+    for i in 0..=chars.len() {
+        for c in ALPHABET.chars() {
+            if i < chars.len() {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                candidates.push(replaced);
+            }
+            let mut inserted = chars.clone();
+            inserted.insert(i, c);
+            candidates.push(inserted);
+        }
+        if i < chars.len() {
+            let mut removed = chars.clone();
+            removed.remove(i);
+            candidates.push(removed);
+        }
+        if i + 1 < chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i, i + 1);
+            candidates.push(swapped);
+        }
+    }
+
+    candidates
+        .into_iter()
+        .map(|chars| chars.into_iter().collect::<String>())
+        .find(|candidate| !candidate.is_empty() && opcode(candidate).is_ok())
+}
@@ -1,128 +1,297 @@
 use nom::{
-    bytes::complete::{tag, take_till},
-    character::complete::multispace0,
-    combinator::{map, opt},
-    error::context,
-    multi::many1,
-    sequence::delimited,
+    branch::alt,
+    bytes::complete::tag,
+    character::complete::{multispace0, none_of, space0, space1},
+    combinator::{map, map_res, verify},
+    error::{context, VerboseError},
+    multi::{many0, separated_list1},
+    number::complete::recognize_float_or_exceptions,
+    sequence::{delimited, preceded, separated_pair},
+    IResult,
 };
 
-use crate::parser;
+use std::str::FromStr;
 
 use super::{
+    constant, expression,
+    expression::escape,
     instruction::ParserOutput,
-    model::{Instruction, Line, Segment},
+    label::identifier,
+    model::{fits_in_bytes, Instruction, Line, Segment},
+    Constants, Expr,
 };
 
+/// Parses the strings of `.ascii`, which are stored one after another without padding
 pub fn ascii_lit(input: &str) -> ParserOutput {
+    strings(input, false)
+}
+
+/// Parses the strings of `.asciiz`, each followed by a null byte
+pub fn asciiz_lit(input: &str) -> ParserOutput<'_> {
+    strings(input, true)
+}
+
+fn strings(input: &str, null_terminated: bool) -> ParserOutput<'_> {
+    map(
+        separated_list1(
+            data_separator,
+            preceded(space0, context("Expected a string in double quotes", string)),
+        ),
+        move |strings| {
+            let mut data = vec![];
+            for string in strings {
+                data.extend(string);
+                if null_terminated {
+                    data.push(0);
+                }
+            }
+            Line::Instruction(vec![Instruction::Literal { data }])
+        },
+    )(input)
+}
+
+/// Parses a string in double quotes into its UTF-8 bytes, with its escape sequences translated
+fn string(input: &str) -> IResult<&str, Vec<u8>, VerboseError<&str>> {
     map(
         delimited(
-            multispace0,
+            tag("\""),
+            many0(alt((
+                map(escape, |byte| vec![byte]),
+                map(none_of("\\\"\n"), |c: char| c.to_string().into_bytes()),
+            ))),
+            context("Expected \" to close the string", tag("\"")),
+        ),
+        |parts| parts.concat(),
+    )(input)
+}
+
+pub fn word_lit<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    data_lit(input, 4, constants)
+}
+
+pub fn half_lit<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    data_lit(input, 2, constants)
+}
+
+pub fn byte_lit<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    data_lit(input, 1, constants)
+}
+
+/// Parses the values of `.word`, `.half` or `.byte`, each stored in `size` bytes
+///
+/// Values that don't fit are kept as expressions so they are reported when they are assembled.
+fn data_lit<'a>(input: &'a str, size: usize, constants: &Constants) -> ParserOutput<'a> {
+    map(
+        separated_list1(
+            data_separator,
+            map(expression(constants), |expr| match expr {
+                Expr::Value(i) if size == 1 && fits_in_bytes(i, size) => Instruction::Literal {
+                    data: vec![i as u8],
+                },
+                Expr::Value(i) if fits_in_bytes(i, size) => Instruction::Data {
+                    data: i.to_le_bytes()[..size].to_vec(),
+                },
+                expr => Instruction::Expr { expr, size },
+            }),
+        ),
+        |x| Line::Instruction(x),
+    )(input)
+}
+
+/// Separates the values of data directives by commas or spaces, a list can continue on the next
+/// line after a comma
+fn data_separator(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    alt((delimited(space0, tag(","), multispace0), space1))(input)
+}
+
+pub fn float_lit<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    map(
+        separated_list1(
+            data_separator,
             map(
-                delimited(tag("\""), take_till(|c: char| c == '"'), tag("\"")),
-                |s: &str| {
-                    // escape stuff
-                    let s = s.replace("\\n", "\n");
-                    let s = s.replace("\\0", "\0");
-                    // convert to bytes
-                    let bytes = s.as_bytes();
-                    let mut words = vec![];
-                    while words.len() * 4 < bytes.len() {
-                        let i = words.len() * 4;
-                        words.push(Instruction::Literal {
-                            data: vec![
-                                *bytes.get(i).unwrap_or(&0),
-                                *bytes.get(i + 1).unwrap_or(&0),
-                                *bytes.get(i + 2).unwrap_or(&0),
-                                *bytes.get(i + 3).unwrap_or(&0),
-                            ],
-                        });
-                    }
-                    words
+                |i| float_value(i, constants, |i| i as f32),
+                |f: f32| Instruction::Data {
+                    data: f.to_bits().to_le_bytes().to_vec(),
                 },
             ),
-            opt(tag(",")),
         ),
-        |x| Line::Instruction(x),
+        Line::Instruction,
     )(input)
 }
 
-pub fn asciiz_lit(input: &str) -> ParserOutput {
+pub fn double_lit<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
     map(
-        delimited(
-            multispace0,
+        separated_list1(
+            data_separator,
             map(
-                delimited(tag("\""), take_till(|c: char| c == '"'), tag("\"")),
-                |s: &str| {
-                    // escape stuff
-                    let s = s.replace("\\n", "\n");
-                    let s = s.replace("\\0", "\0");
-                    let s = &format!("{s}\0");
-                    // convert to bytes
-                    let bytes = s.as_bytes();
-                    let mut words = vec![];
-                    while words.len() * 4 < bytes.len() {
-                        let i = words.len() * 4;
-                        words.push(Instruction::Literal {
-                            data: vec![
-                                *bytes.get(i).unwrap_or(&0),
-                                *bytes.get(i + 1).unwrap_or(&0),
-                                *bytes.get(i + 2).unwrap_or(&0),
-                                *bytes.get(i + 3).unwrap_or(&0),
-                            ],
-                        });
-                    }
-                    words
+                |i| float_value(i, constants, |i| i as f64),
+                |f: f64| Instruction::Data {
+                    data: f.to_bits().to_le_bytes().to_vec(),
                 },
             ),
-            opt(tag(",")),
         ),
-        |x| Line::Instruction(x),
+        Line::Instruction,
     )(input)
 }
 
-pub fn word_lit(input: &str) -> ParserOutput {
+/// Parses a value of `.float` or `.double`, which is a number with a fraction or an exponent,
+/// `inf`, `nan` or a constant expression converted with `from_int`
+fn float_value<'a, T: FromStr>(
+    input: &'a str,
+    constants: &Constants,
+    from_int: fn(i64) -> T,
+) -> IResult<&'a str, T, VerboseError<&'a str>> {
+    alt((
+        map_res(
+            verify(preceded(space0, recognize_float_or_exceptions), |f: &str| {
+                f.contains(['.', 'e', 'E', 'i', 'I', 'n', 'N'])
+            }),
+            str::parse,
+        ),
+        map(constant(constants), from_int),
+    ))(input)
+}
+
+pub fn space<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
     map(
-        many1(map(
-            delimited(multispace0, parser::int, opt(tag(","))),
-            |i: i64| Instruction::Literal {
-                data: (i as u32).to_le_bytes().to_vec(),
-            },
-        )),
-        |x| Line::Instruction(x),
+        context(
+            "Expected amount to space",
+            verify(constant(constants), |i| (0..=u32::MAX as i64).contains(i)),
+        ),
+        |i| Line::Instruction(vec![Instruction::Literal { data: vec![0; i as usize] }]),
     )(input)
 }
 
-pub fn half_lit(input: &str) -> ParserOutput {
+pub fn segment(input: &str, seg: Segment) -> ParserOutput {
+    Ok((input, Line::Segment(seg)))
+}
+
+/// Parses `.align <n>`, which aligns the next data to 2^n bytes
+pub fn align<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
     map(
-        many1(map(
-            delimited(multispace0, parser::int, opt(tag(","))),
-            |i: i64| Instruction::Literal {
-                data: (i as u16).to_le_bytes().to_vec(),
-            },
-        )),
-        |x| Line::Instruction(x),
+        context(
+            "Expected a power of two from 0 to 16",
+            verify(constant(constants), |n| (0..=16).contains(n)),
+        ),
+        |n| Line::Align(n as u32),
     )(input)
 }
 
-pub fn byte_lit(input: &str) -> ParserOutput {
+/// Parses `.globl <label>, ...`
+pub fn globl(input: &str) -> ParserOutput<'_> {
     map(
-        many1(map(
-            delimited(multispace0, parser::int, opt(tag(","))),
-            |i: i64| Instruction::Literal {
-                data: (i as u8).to_le_bytes().to_vec(),
-            },
-        )),
-        |x| Line::Instruction(x),
+        context(
+            "Expected a label",
+            separated_list1(data_separator, preceded(space0, identifier)),
+        ),
+        |names| Line::Global(names.into_iter().map(str::to_string).collect()),
     )(input)
 }
 
-pub fn space(input: &str) -> ParserOutput {
+/// Parses `.extern <label>, <size>`
+pub fn external<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
     map(
-        context("Expected amount to space", parser::int),
-        |i: usize| Line::Instruction(vec![Instruction::Literal { data: vec![0; i] }]),
+        separated_pair(
+            context("Expected a label", preceded(space0, identifier)),
+            data_separator,
+            context(
+                "Expected the size of the data",
+                verify(constant(constants), |i| (0..=u32::MAX as i64).contains(i)),
+            ),
+        ),
+        |(name, size)| Line::Extern {
+            name: name.to_string(),
+            size: size as u32,
+        },
     )(input)
 }
-pub fn segment(input: &str, seg: Segment) -> ParserOutput {
-    Ok((input, Line::Segment(seg)))
+
+/// Parses `.eqv <name>, <expression>` or `.set <name>, <expression>`, only constants made by
+/// `.set` can be defined again
+pub fn symbolic_constant<'a>(
+    input: &'a str,
+    redefinable: bool,
+    constants: &Constants,
+) -> ParserOutput<'a> {
+    map(
+        separated_pair(
+            context("Expected the name of the constant", preceded(space0, identifier)),
+            data_separator,
+            context("Expected the value of the constant", expression(constants)),
+        ),
+        move |(name, expr)| Line::Constant {
+            name: name.to_string(),
+            expr,
+            redefinable,
+        },
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assembler;
+    use crate::parser::model::{DATA_BASE, EXTERN_BASE};
+
+    /// Assembles a data segment and reads back its first `len` bytes and the address of `next`
+    fn data(lines: &str, len: u32) -> (Vec<u8>, u32) {
+        let (memory, labels) = assembler(format!(".data\n{lines}\nnext: .byte 0xff\n")).unwrap();
+        let bytes = (0..len)
+            .map(|n| memory.get_byte(DATA_BASE + n).unwrap())
+            .collect();
+        (bytes, labels.get_label("next").unwrap())
+    }
+
+    #[test]
+    fn ascii_escapes() {
+        let (bytes, next) = data(r#".ascii "a\nb\tc\0d\x41\x7e\101\7""#, 12);
+        assert_eq!(bytes, b"a\nb\tc\0dA~A\x07\xff");
+        assert_eq!(next, DATA_BASE + 11);
+    }
+
+    #[test]
+    fn asciiz_terminates_every_string() {
+        let (bytes, next) = data(r#".asciiz "ab", "\\\"", """#, 9);
+        assert_eq!(bytes, b"ab\0\\\"\0\0\xff\0");
+        assert_eq!(next, DATA_BASE + 7);
+    }
+
+    #[test]
+    fn utf8_strings_are_stored_as_bytes() {
+        let (bytes, next) = data(".asciiz \"é→\"", 7);
+        assert_eq!(bytes, [0xc3, 0xa9, 0xe2, 0x86, 0x92, 0x00, 0xff]);
+        assert_eq!(next, DATA_BASE + 6);
+    }
+
+    #[test]
+    fn strings_are_packed_without_padding() {
+        // a word after the strings is aligned, the strings themselves are not
+        let (memory, labels) = assembler(
+            ".data\nfirst: .ascii \"abc\"\nsecond: .asciiz \"de\"\nword: .word 1\n".to_string(),
+        )
+        .unwrap();
+        assert_eq!(labels.get_label("first"), Some(DATA_BASE));
+        assert_eq!(labels.get_label("second"), Some(DATA_BASE + 3));
+        assert_eq!(labels.get_label("word"), Some(DATA_BASE + 8));
+        assert_eq!(memory.get_byte(DATA_BASE + 3).unwrap(), b'd');
+        assert_eq!(memory.get_byte(DATA_BASE + 5).unwrap(), 0);
+    }
+
+    #[test]
+    fn floats_take_constant_expressions() {
+        let (bytes, _) = data(".eqv HALF, 2\n.float 1.5, HALF * 3\n.double -2.5e-1, -HALF", 24);
+        let mut expected = [1.5_f32.to_le_bytes(), 6.0_f32.to_le_bytes()].concat();
+        expected.extend([(-0.25_f64).to_le_bytes(), (-2.0_f64).to_le_bytes()].concat());
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn extern_reserves_global_data_before_the_data_segment() {
+        let (_, labels) =
+            assembler(".extern first, 3\n.extern second 4\n.data\nword: .word 1\n".to_string())
+                .unwrap();
+        assert_eq!(labels.get_label("first"), Some(EXTERN_BASE));
+        assert_eq!(labels.get_label("second"), Some(EXTERN_BASE + 4));
+        assert_eq!(labels.get_label("word"), Some(DATA_BASE));
+        assert!(labels.globals().any(|(name, _)| name == "second"));
+    }
 }
@@ -0,0 +1,384 @@
+//! Constant expressions used as operands and data, which are folded into values while parsing
+//! when they don't depend on a label
+
+use std::collections::HashMap;
+use std::fmt;
+
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_while, take_while_m_n},
+    character::complete::{anychar, char, digit1, none_of, one_of, satisfy, space0},
+    combinator::{cut, map, map_opt, map_res, not, opt, recognize},
+    error::{context, VerboseError},
+    sequence::{delimited, pair, preceded, terminated},
+    IResult,
+};
+
+use super::{label::identifier, model::LabelTable, AsmError};
+
+/// Named constants defined so far in the script being parsed, which fold into the expressions
+/// parsed after them
+pub type Constants = HashMap<String, i64>;
+
+/// Binary operators, from the lowest to the highest precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Or,
+    Xor,
+    And,
+    Shl,
+    Shr,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// The operators of each precedence level, from the lowest to the highest precedence
+const LEVELS: [&[(&str, Operator)]; 6] = [
+    &[("|", Operator::Or)],
+    &[("^", Operator::Xor)],
+    &[("&", Operator::And)],
+    &[("<<", Operator::Shl), (">>", Operator::Shr)],
+    &[("+", Operator::Add), ("-", Operator::Sub)],
+    &[("*", Operator::Mul), ("/", Operator::Div), ("%", Operator::Rem)],
+];
+
+impl Operator {
+    fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Or => "|",
+            Operator::Xor => "^",
+            Operator::And => "&",
+            Operator::Shl => "<<",
+            Operator::Shr => ">>",
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+            Operator::Rem => "%",
+        }
+    }
+
+    /// Applies the operator, which fails when dividing by zero
+    fn apply(&self, lhs: i64, rhs: i64) -> Option<i64> {
+        Some(match self {
+            Operator::Or => lhs | rhs,
+            Operator::Xor => lhs ^ rhs,
+            Operator::And => lhs & rhs,
+            Operator::Shl => lhs.wrapping_shl(rhs as u32),
+            Operator::Shr => lhs.wrapping_shr(rhs as u32),
+            Operator::Add => lhs.wrapping_add(rhs),
+            Operator::Sub => lhs.wrapping_sub(rhs),
+            Operator::Mul => lhs.wrapping_mul(rhs),
+            Operator::Div if rhs == 0 => return None,
+            Operator::Div => lhs.wrapping_div(rhs),
+            Operator::Rem if rhs == 0 => return None,
+            Operator::Rem => lhs.wrapping_rem(rhs),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Value(i64),
+    /// A label or a named constant
+    Symbol(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+    /// `%hi(<expr>)`, the upper half rounded up when the lower half is negative once sign
+    /// extended
+    High(Box<Expr>),
+    /// `%lo(<expr>)`, the sign extended lower half
+    Low(Box<Expr>),
+}
+
+impl Expr {
+    pub fn negate(expr: Expr) -> Self {
+        match expr {
+            Expr::Value(value) => Expr::Value(value.wrapping_neg()),
+            expr => Expr::Negate(Box::new(expr)),
+        }
+    }
+
+    pub fn complement(expr: Expr) -> Self {
+        match expr {
+            Expr::Value(value) => Expr::Value(!value),
+            expr => Expr::Not(Box::new(expr)),
+        }
+    }
+
+    pub fn binary(op: Operator, lhs: Expr, rhs: Expr) -> Self {
+        // dividing by zero is kept to be reported once the expression is evaluated
+        let value = match (&lhs, &rhs) {
+            (Expr::Value(l), Expr::Value(r)) => op.apply(*l, *r),
+            _ => None,
+        };
+        match value {
+            Some(value) => Expr::Value(value),
+            None => Expr::Binary(op, Box::new(lhs), Box::new(rhs)),
+        }
+    }
+
+    pub fn high(expr: Expr) -> Self {
+        match expr {
+            Expr::Value(value) => Expr::Value(high(value)),
+            expr => Expr::High(Box::new(expr)),
+        }
+    }
+
+    pub fn low(expr: Expr) -> Self {
+        match expr {
+            Expr::Value(value) => Expr::Value(low(value)),
+            expr => Expr::Low(Box::new(expr)),
+        }
+    }
+
+    /// Whether the expression is half of a value, which always fits in an immediate
+    pub fn is_half(&self) -> bool {
+        matches!(self, Expr::High(_) | Expr::Low(_))
+    }
+
+    /// Calls `f` on the name of every label and named constant in the expression, which can
+    /// rename them
+    pub fn symbols_mut(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Expr::Value(_) => {}
+            Expr::Symbol(name) => f(name),
+            Expr::Negate(expr) | Expr::Not(expr) | Expr::High(expr) | Expr::Low(expr) => {
+                expr.symbols_mut(f)
+            }
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.symbols_mut(f);
+                rhs.symbols_mut(f);
+            }
+        }
+    }
+
+    /// Computes the value of the expression once every label is known
+    pub fn eval(&self, labels: &LabelTable) -> Result<i64, AsmError> {
+        Ok(match self {
+            Expr::Value(value) => *value,
+            Expr::Symbol(name) => labels
+                .get_symbol(name)
+                .ok_or_else(|| AsmError::UndefinedLabel(name.clone()))?,
+            Expr::Negate(expr) => expr.eval(labels)?.wrapping_neg(),
+            Expr::Not(expr) => !expr.eval(labels)?,
+            Expr::Binary(op, lhs, rhs) => op
+                .apply(lhs.eval(labels)?, rhs.eval(labels)?)
+                .ok_or_else(|| AsmError::DivisionByZero(self.to_string()))?,
+            Expr::High(expr) => high(expr.eval(labels)?),
+            Expr::Low(expr) => low(expr.eval(labels)?),
+        })
+    }
+}
+
+/// The upper half of a value that is added to its sign extended lower half
+fn high(value: i64) -> i64 {
+    ((value as u32).wrapping_add(0x8000) >> 16) as i64
+}
+
+/// The sign extended lower half of a value
+fn low(value: i64) -> i64 {
+    value as u16 as i16 as i64
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // operands that are operations themselves are wrapped in parentheses
+        let operand = |expr: &Expr| match expr {
+            Expr::Negate(_) | Expr::Not(_) | Expr::Binary(..) => format!("({expr})"),
+            expr => expr.to_string(),
+        };
+        match self {
+            Expr::Value(value) => write!(f, "{value}"),
+            Expr::Symbol(name) => write!(f, "{name}"),
+            Expr::Negate(expr) => write!(f, "-{}", operand(expr)),
+            Expr::Not(expr) => write!(f, "~{}", operand(expr)),
+            Expr::Binary(op, lhs, rhs) => {
+                write!(f, "{} {} {}", operand(lhs), op.symbol(), operand(rhs))
+            }
+            Expr::High(expr) => write!(f, "%hi({expr})"),
+            Expr::Low(expr) => write!(f, "%lo({expr})"),
+        }
+    }
+}
+
+/// Parses an expression made of numbers, characters, labels and named constants combined with
+/// `| ^ & << >> + - * / %`, the unary `- ~`, `%hi()`, `%lo()` and parentheses
+///
+/// Named constants in `constants` are folded into their values.
+pub fn expression(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, Expr, VerboseError<&str>> + '_ {
+    move |input| preceded(space0, |input| binary(input, 0, constants))(input)
+}
+
+/// Parses an expression that has to be a constant
+pub fn constant(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, i64, VerboseError<&str>> + '_ {
+    move |input| {
+        context(
+            "Expected a constant expression",
+            map_opt(expression(constants), |expr| match expr {
+                Expr::Value(value) => Some(value),
+                _ => None,
+            }),
+        )(input)
+    }
+}
+
+/// Parses operations at a precedence level and above, which group from the left
+fn binary<'a>(
+    input: &'a str,
+    level: usize,
+    constants: &Constants,
+) -> IResult<&'a str, Expr, VerboseError<&'a str>> {
+    if level == LEVELS.len() {
+        return unary(input, constants);
+    }
+
+    let (mut input, mut lhs) = binary(input, level + 1, constants)?;
+    loop {
+        let rest = input.trim_start_matches([' ', '\t']);
+        let op = LEVELS[level]
+            .iter()
+            .find_map(|(symbol, op)| Some((rest.strip_prefix(symbol)?, *op)));
+        match op {
+            Some((rest, op)) => {
+                let (rest, rhs) =
+                    preceded(space0, |input| binary(input, level + 1, constants))(rest)?;
+                lhs = Expr::binary(op, lhs, rhs);
+                input = rest;
+            }
+            None => return Ok((input, lhs)),
+        }
+    }
+}
+
+fn unary<'a>(
+    input: &'a str,
+    constants: &Constants,
+) -> IResult<&'a str, Expr, VerboseError<&'a str>> {
+    let operand = |input| unary(input, constants);
+    alt((
+        map(preceded(pair(char('-'), space0), operand), Expr::negate),
+        map(preceded(pair(char('~'), space0), operand), Expr::complement),
+        preceded(pair(char('+'), space0), operand),
+        |input| primary(input, constants),
+    ))(input)
+}
+
+fn primary<'a>(
+    input: &'a str,
+    constants: &Constants,
+) -> IResult<&'a str, Expr, VerboseError<&'a str>> {
+    alt((
+        map(number, Expr::Value),
+        map(character, |c| Expr::Value(c as i64)),
+        map(half("%hi", constants), Expr::high),
+        map(half("%lo", constants), Expr::low),
+        delimited(
+            pair(char('('), space0),
+            expression(constants),
+            pair(space0, char(')')),
+        ),
+        map(label_reference, |name| symbol(name, constants)),
+    ))(input)
+}
+
+/// Parses a label or named constant, a local label `.loop` of the label before it, the local
+/// label of another label `main.loop` or the numeric label `1` before (`1b`) or after (`1f`) it
+fn label_reference(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    alt((
+        recognize(pair(identifier, opt(pair(char('.'), identifier)))),
+        recognize(pair(char('.'), identifier)),
+        recognize(terminated(
+            pair(digit1, one_of("bf")),
+            not(satisfy(|c| c.is_alphanumeric() || c == '_')),
+        )),
+    ))(input)
+}
+
+/// Parses `%hi(<expr>)` or `%lo(<expr>)`
+fn half<'c>(
+    name: &'static str,
+    constants: &'c Constants,
+) -> impl Fn(&str) -> IResult<&str, Expr, VerboseError<&str>> + 'c {
+    move |input| {
+        delimited(
+            pair(tag(name), pair(space0, char('('))),
+            expression(constants),
+            pair(space0, char(')')),
+        )(input)
+    }
+}
+
+/// Folds a named constant that is already defined into its value
+fn symbol(name: &str, constants: &Constants) -> Expr {
+    match constants.get(name) {
+        Some(value) => Expr::Value(*value),
+        None => Expr::Symbol(name.to_string()),
+    }
+}
+
+/// Parses a decimal, hexadecimal `0x`, octal `0o` or binary `0b` number, which may separate its
+/// digits with underscores
+fn number(input: &str) -> IResult<&str, i64, VerboseError<&str>> {
+    map_res(
+        recognize(pair(
+            digit1,
+            take_while(|c: char| c.is_ascii_alphanumeric() || c == '_'),
+        )),
+        |digits: &str| {
+            let digits = digits.replace('_', "");
+            let (digits, radix) = match digits.get(..2) {
+                Some("0x") => (&digits[2..], 16),
+                Some("0o") => (&digits[2..], 8),
+                Some("0b") => (&digits[2..], 2),
+                _ => (&digits[..], 10),
+            };
+            i64::from_str_radix(digits, radix)
+        },
+    )(input)
+}
+
+/// Parses a character literal like `'A'` or `'\n'`
+fn character(input: &str) -> IResult<&str, char, VerboseError<&str>> {
+    delimited(
+        char('\''),
+        alt((map(escape, char::from), none_of("\\'\n"))),
+        context("Expected ' to close the character", char('\'')),
+    )(input)
+}
+
+/// Parses an escape sequence of a character or string: `\n \t \r \\ \" \'`, a byte in
+/// hexadecimal `\xNN` or a byte in octal `\NNN`, which includes `\0`
+pub(super) fn escape(input: &str) -> IResult<&str, u8, VerboseError<&str>> {
+    preceded(
+        char('\\'),
+        cut(context(
+            "Unknown escape sequence",
+            alt((
+                map_res(
+                    preceded(char('x'), take_while_m_n(1, 2, |c: char| c.is_ascii_hexdigit())),
+                    |digits| u8::from_str_radix(digits, 16),
+                ),
+                map_res(take_while_m_n(1, 3, |c: char| c.is_digit(8)), |digits| {
+                    u8::from_str_radix(digits, 8)
+                }),
+                map_opt(anychar, |c| match c {
+                    'n' => Some(b'\n'),
+                    't' => Some(b'\t'),
+                    'r' => Some(b'\r'),
+                    '\\' | '\'' | '"' => Some(c as u8),
+                    _ => None,
+                }),
+            )),
+        )),
+    )(input)
+}
+
@@ -2,53 +2,78 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{space0, space1},
-    combinator::{map, peek},
+    combinator::{cut, fail, map, opt, peek, verify},
     error::{context, VerboseError},
-    sequence::{delimited, preceded, terminated, tuple},
+    number::complete::float,
+    sequence::{delimited, pair, preceded, terminated, tuple},
     IResult,
 };
 
 use crate::parser::{self, model::Opcode};
-use crate::Register;
 
+use crate::Register;
 use crate::Register::{AT, ZERO};
 
 use super::{
-    int,
-    label::identifier,
+    constant, expression,
     model::{Imm, Instruction, Line, Symbol},
+    Constants, Expr,
 };
 
-fn immediate(input: &str) -> IResult<&str, Imm, VerboseError<&str>> {
-    preceded(
-        space0,
-        alt((
-            map(int, |x: i64| Imm::Value(x)),
-            map(identifier, |x: &str| Imm::Label(x.to_string())),
-        )),
-    )(input)
+fn immediate(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, Imm, VerboseError<&str>> + '_ {
+    move |input| {
+        map(expression(constants), |expr| match expr {
+            Expr::Value(x) => Imm::Value(x),
+            expr => Imm::Expr(expr),
+        })(input)
+    }
 }
 
-fn separator(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+pub(crate) fn separator(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
     context("separator", delimited(space0, tag(","), space0))(input)
 }
 
-fn symbol(input: &str) -> IResult<&str, Symbol, VerboseError<&str>> {
-    preceded(
-        space0,
-        alt((
-            map(int, |x: u32| Symbol::Address(x)),
-            map(identifier, |x: &str| Symbol::Label(x.to_string())),
-        )),
-    )(input)
+fn symbol(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, Symbol, VerboseError<&str>> + '_ {
+    move |input| {
+        map(expression(constants), |expr| match expr {
+            Expr::Value(x) => Symbol::Address(x as u32),
+            expr => Symbol::Expr(expr),
+        })(input)
+    }
+}
+
+/// Parses the target of a branch making labels pc relative
+fn branch_target(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, Imm, VerboseError<&str>> + '_ {
+    move |input| {
+        map(immediate(constants), |imm| match imm {
+            Imm::Expr(expr) => Imm::PcRelative(expr),
+            imm => imm,
+        })(input)
+    }
+}
+
+/// Appends the two nops that fill the slots executed while a branch is resolved
+fn with_branch_slots(mut ins: Vec<Instruction>) -> Vec<Instruction> {
+    for _ in 0..2 {
+        ins.push(Instruction::Literal {
+            data: vec![0, 0, 0, 0],
+        });
+    }
+    ins
 }
 
 pub(crate) type ParserOutput<'a> = IResult<&'a str, Line, VerboseError<&'a str>>;
 
 /// Parse jump instructions
 /// <OP> <label>
-pub fn j_type(input: &str, op: Opcode) -> ParserOutput {
-    let (input, addr) = context("Expected label", symbol)(input)?;
+pub fn j_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, addr) = context("Expected label", symbol(constants))(input)?;
     Ok((
         input,
         Line::Instruction(vec![
@@ -88,6 +113,35 @@ pub fn jr_type(input: &str, op: Opcode) -> ParserOutput {
     ))
 }
 
+/// Parses jalr instructions using the format
+/// `jalr [<rd>,] <rs>` where rd defaults to $ra
+pub fn jalr_type(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, first) = context("Expected register", parser::register)(input)?;
+    let (input, second) = opt(preceded(separator, parser::register))(input)?;
+    let (rd, rs) = match second {
+        Some(rs) => (first, rs),
+        None => (Register::RA, first),
+    };
+    Ok((
+        input,
+        Line::Instruction(vec![
+            Instruction::R {
+                op,
+                rd,
+                rs,
+                rt: ZERO,
+                shamt: 0,
+            },
+            Instruction::Literal {
+                data: vec![0, 0, 0, 0],
+            },
+            Instruction::Literal {
+                data: vec![0, 0, 0, 0],
+            },
+        ]),
+    ))
+}
+
 /// Parses simple R-type instructions using the format
 /// `<OP> <rd>, <rs>, <rt>`
 pub fn r_type(input: &str, op: Opcode) -> ParserOutput {
@@ -132,24 +186,235 @@ pub fn hilo(input: &str, op: Opcode) -> ParserOutput {
         Line::Instruction(vec![Instruction::R {
             op,
             rd,
-            rs: if op.value() == 0x10 { Register::HI } else { Register::LO },
+            rs: ZERO,
             rt: ZERO,
             shamt: 0,
         }]),
     ))
 }
 
+/// Parses mthi and mtlo instructions
+/// `<OP> <rs>`
+pub fn to_hilo(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rs) = context("Source Register", parser::register)(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: ZERO,
+            rs,
+            rt: ZERO,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses floating point instructions using the format
+/// `<OP> <fd>, <fs>, <ft>`
+pub fn float_r_type(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, fd) = context("Destination Register", parser::register)(input)?;
+    let (input, fs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    let (input, ft) = context("Target Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: fs,
+            rs: ZERO,
+            rt: ft,
+            shamt: fd.id(),
+        }]),
+    ))
+}
+
+/// Parses floating point instructions with a single source using the format
+/// `<OP> <fd>, <fs>`
+pub fn float_unary(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, fd) = context("Destination Register", parser::register)(input)?;
+    let (input, fs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: fs,
+            rs: ZERO,
+            rt: ZERO,
+            shamt: fd.id(),
+        }]),
+    ))
+}
+
+/// Parses the optional condition flag of floating point compares and branches
+/// `<cc>,`
+fn condition_flag(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, u32, VerboseError<&str>> + '_ {
+    move |input| match opt(terminated(constant(constants), separator))(input)? {
+        (_, Some(cc)) if !(0..=7).contains(&cc) => {
+            out_of_range(input, "Condition flag out of range")
+        }
+        (rest, cc) => Ok((rest, cc.unwrap_or(0) as u32)),
+    }
+}
+
+/// Parses the condition flag operand of movf and movt, one of the eight flags 0 to 7
+fn condition_code(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, u32, VerboseError<&str>> + '_ {
+    move |input| match constant(constants)(input)? {
+        (rest, cc @ 0..=7) => Ok((rest, cc as u32)),
+        _ => out_of_range(input, "Condition flag out of range"),
+    }
+}
+
+/// Parses the shift amount of a shift or rotate, which has to fit in the 5 bits of shamt
+fn shift_amount(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, u32, VerboseError<&str>> + '_ {
+    move |input| match constant(constants)(input)? {
+        (rest, shamt @ 0..=31) => Ok((rest, shamt as u32)),
+        _ => out_of_range(input, "Shift amount out of range"),
+    }
+}
+
+/// Parses an expression whose value has to fit in a register, either signed or unsigned
+pub(crate) fn word_expression(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, Expr, VerboseError<&str>> + '_ {
+    move |input| match expression(constants)(input)? {
+        (_, Expr::Value(value)) if !(i32::MIN as i64..=u32::MAX as i64).contains(&value) => {
+            out_of_range(input, "Value does not fit in 32 bits")
+        }
+        result => Ok(result),
+    }
+}
+
+/// Fails on a constant operand at `input` whose value does not fit its field, without trying
+/// other parsers that would only report a less helpful error
+fn out_of_range<'a, T>(
+    input: &'a str,
+    message: &'static str,
+) -> IResult<&'a str, T, VerboseError<&'a str>> {
+    cut(context(message, fail))(input)
+}
+
+/// Parses floating point comparisons using the format
+/// `<OP> [cc,] <fs>, <ft>`
+pub fn float_compare<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, cc) = context("Condition Flag", condition_flag(constants))(input)?;
+    let (input, fs) = context("Source Register", parser::register)(input)?;
+    let (input, ft) = context("Target Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: fs,
+            rs: ZERO,
+            rt: ft,
+            // the flag is held in the upper bits of fd
+            shamt: cc << 2,
+        }]),
+    ))
+}
+
+/// Parses branches on a floating point condition flag using the format
+/// `<OP> [cc,] <label>`
+pub fn float_branch<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, cc) = context("Condition Flag", condition_flag(constants))(input)?;
+    let (input, imm) = context("Expected label", branch_target(constants))(input)?;
+    Ok((
+        input,
+        Line::Instruction(with_branch_slots(vec![Instruction::I {
+            op,
+            // the flag is held in the upper bits of rt
+            rt: Register::from(cc << 2),
+            rs: ZERO,
+            imm,
+        }])),
+    ))
+}
+
+/// Parses moves between general purpose and floating point registers
+/// `<OP> <rt>, <fs>`
+pub fn cop1_move(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, fs) = context("Float Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: fs,
+            rs: ZERO,
+            rt,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses cfc1 and ctc1 instructions
+/// `<OP> <rt>, <fs>` where fs is a floating point control register
+pub fn cop1_control_move(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, fs) = context(
+        "Floating Point Control Register",
+        preceded(separator, parser::fcr_register),
+    )(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: fs,
+            rs: ZERO,
+            rt,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses li.s instructions by loading the bits of the float through $at
+/// `li.s <fd>, <float>`
+pub fn li_s_ins(input: &str) -> ParserOutput<'_> {
+    let (input, fd) = context("Destination Register", parser::register)(input)?;
+    let (input, value) = context("Expected float value", preceded(separator, float))(input)?;
+    let bits = value.to_bits();
+    Ok((
+        input,
+        Line::Instruction(vec![
+            Instruction::I {
+                op: Opcode::Op(0x0f), // lui
+                rt: AT,
+                rs: ZERO,
+                imm: Imm::Value((bits >> 16) as i64),
+            },
+            Instruction::I {
+                op: Opcode::Op(0x0d), // ori
+                rt: AT,
+                rs: AT,
+                imm: Imm::Value((bits & 0xFFFF) as i64),
+            },
+            Instruction::R {
+                op: Opcode::Cop1 { fmt: 0x04, funct: 0 }, // mtc1
+                rd: fd,
+                rs: ZERO,
+                rt: AT,
+                shamt: 0,
+            },
+        ]),
+    ))
+}
+
 /// Parses shift style instructions
 /// `<OP> <rd>, <rs>, shamt`
-pub fn shift_type(input: &str, op: Opcode) -> ParserOutput {
+pub fn shift_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
     let (input, rd) = context("Expected Destination register", parser::register)(input)?;
     let (input, rt) = context(
         "Expected Target register",
         preceded(separator, parser::register),
     )(input)?;
-    let (input, shamt): (&str, i64) =
-        context("Expected shift amount", preceded(separator, int))(input)?;
-    let shamt = shamt as u32;
+    let (input, shamt) = context(
+        "Expected shift amount",
+        preceded(separator, shift_amount(constants)),
+    )(input)?;
     Ok((
         input,
         Line::Instruction(vec![Instruction::R {
@@ -162,15 +427,177 @@ pub fn shift_type(input: &str, op: Opcode) -> ParserOutput {
     ))
 }
 
+/// Parses variable shift instructions using the format
+/// `<OP> <rd>, <rt>, <rs>` where rs holds the shift amount
+pub fn shift_variable(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rt) = context("Target Register", preceded(separator, parser::register))(input)?;
+    let (input, rs) = context("Shift Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd,
+            rs,
+            rt,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses rotates using the format
+/// `<OP> <rd>, <rt>, <sa>`
+pub fn rotate_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rd) = context("Expected Destination register", parser::register)(input)?;
+    let (input, rt) = context(
+        "Expected Target register",
+        preceded(separator, parser::register),
+    )(input)?;
+    let (input, shamt) = context(
+        "Expected shift amount",
+        preceded(separator, shift_amount(constants)),
+    )(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd,
+            // setting the lowest bit of rs selects a rotate instead of srl
+            rs: Register::from(1),
+            rt,
+            shamt,
+        }]),
+    ))
+}
+
+/// Parses variable rotates using the format
+/// `<OP> <rd>, <rt>, <rs>` where rs holds the rotate amount
+pub fn rotate_variable(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rt) = context("Target Register", preceded(separator, parser::register))(input)?;
+    let (input, rs) = context("Shift Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd,
+            rs,
+            rt,
+            // setting the lowest bit of sa selects a rotate instead of srlv
+            shamt: 1,
+        }]),
+    ))
+}
+
+/// Parses clz and clo instructions using the format
+/// `<OP> <rd>, <rs>`
+pub fn count_leading(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd,
+            rs,
+            // MIPS32 expects rt to repeat rd
+            rt: rd,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses the byte shuffles seb, seh and wsbh using the format
+/// `<OP> <rd>, <rt>`
+pub fn bit_shuffle(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rt) = context("Target Register", preceded(separator, parser::register))(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd,
+            rs: ZERO,
+            rt,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses ext and ins instructions using the format
+/// `<OP> <rt>, <rs>, <pos>, <size>`
+pub fn bit_field<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, rs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    let (input, (pos, size)) = context(
+        "Bit field out of range",
+        verify(
+            tuple((
+                preceded(separator, constant(constants)),
+                preceded(separator, constant(constants)),
+            )),
+            |&(pos, size)| (0..32).contains(&pos) && size > 0 && pos + size <= 32,
+        ),
+    )(input)?;
+    let (pos, size) = (pos as u32, size as u32);
+
+    // ext keeps the size of the field in rd while ins keeps the position of its last bit
+    let msb = if op.value() == 0x04 {
+        pos + size - 1
+    } else {
+        size - 1
+    };
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: Register::from(msb),
+            rs,
+            rt,
+            shamt: pos,
+        }]),
+    ))
+}
+
+/// Parses moves on a floating point condition flag using the format
+/// `<OP> <rd>, <rs>[, cc]`
+pub fn float_cond_move<'a>(
+    input: &'a str,
+    on_true: bool,
+    constants: &Constants,
+) -> ParserOutput<'a> {
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    let (input, cc) = context(
+        "Condition Flag",
+        opt(preceded(separator, condition_code(constants))),
+    )(input)?;
+    let cc = cc.unwrap_or(0);
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op: Opcode::Funct(0x01),
+            rd,
+            rs,
+            // the flag is held in the upper bits of rt and the lowest bit moves on true
+            rt: Register::from((cc << 2) | on_true as u32),
+            shamt: 0,
+        }]),
+    ))
+}
+
 /// Parses simple immediate mode instructions using the format
 /// `<OP> <rt> <rs> <imm>`
-pub fn i_type(input: &str, op: Opcode) -> ParserOutput {
+pub fn i_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
     let (input, rt) = context("Expected target register", parser::register)(input)?;
     let (input, rs) = context(
         "Expected source register",
         preceded(separator, parser::register),
     )(input)?;
-    let (input, imm) = context("Expected immediate value", preceded(separator, immediate))(input)?;
+    let (input, imm) = context(
+        "Expected immediate value",
+        preceded(separator, immediate(constants)),
+    )(input)?;
     Ok((
         input,
         Line::Instruction(vec![Instruction::I { op, rt, rs, imm }]),
@@ -179,9 +606,12 @@ pub fn i_type(input: &str, op: Opcode) -> ParserOutput {
 
 /// Parses lui instruction
 /// `<OP> <rt> <imm>`
-pub fn lui(input: &str, op: Opcode) -> ParserOutput {
+pub fn lui<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
     let (input, rt) = context("Expected target register", parser::register)(input)?;
-    let (input, imm) = context("Expected immediate value", preceded(separator, immediate))(input)?;
+    let (input, imm) = context(
+        "Expected immediate value",
+        preceded(separator, immediate(constants)),
+    )(input)?;
     Ok((
         input,
         Line::Instruction(vec![Instruction::I {
@@ -193,68 +623,195 @@ pub fn lui(input: &str, op: Opcode) -> ParserOutput {
     ))
 }
 
+/// Constant or label offset of a memory operand
+#[derive(Debug, Clone)]
+pub enum Offset {
+    Value(i64),
+    Expr(Expr),
+}
+
+/// Parses the memory operand of loads and stores, which can take any of the forms
+/// `<offset>(<base>)`, `(<base>)`, `<expression>[(<base>)]` or `<address>`
+pub fn memory_operand(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, (Offset, Option<Register>), VerboseError<&str>> + '_ {
+    move |input| {
+        let (input, offset) = opt(map(word_expression(constants), |expr| match expr {
+            Expr::Value(value) => Offset::Value(value),
+            expr => Offset::Expr(expr),
+        }))(input)?;
+        let (input, base) = opt(delimited(
+            pair(space0, tag("(")),
+            delimited(space0, parser::register, space0),
+            tag(")"),
+        ))(input)?;
+        match (offset, base) {
+            (None, None) => context("Expected memory address", fail)(input),
+            (offset, base) => Ok((input, (offset.unwrap_or(Offset::Value(0)), base))),
+        }
+    }
+}
+
+/// Checks if a value fits the sign extended immediate of an instruction
+pub fn fits_immediate(value: i64) -> bool {
+    (i16::MIN as i64..=i16::MAX as i64).contains(&value)
+}
+
+/// Expands a load or store of `rt` at a memory operand, computing addresses that don't fit the
+/// offset field in $at
+pub fn memory_access(
+    op: Opcode,
+    rt: Register,
+    offset: Offset,
+    base: Option<Register>,
+) -> Vec<Instruction> {
+    let (high, low) = match offset {
+        Offset::Value(value) if fits_immediate(value) => {
+            return vec![Instruction::I {
+                op,
+                rt,
+                rs: base.unwrap_or(ZERO),
+                imm: Imm::Value(value),
+            }];
+        }
+        Offset::Value(value) => {
+            // the upper half is rounded up when the lower half is negative once sign extended
+            let value = value as u32;
+            (
+                Imm::Value((value.wrapping_add(0x8000) >> 16) as i64),
+                Imm::Value(value as u16 as i16 as i64),
+            )
+        }
+        Offset::Expr(expr) if expr.is_half() => {
+            return vec![Instruction::I {
+                op,
+                rt,
+                rs: base.unwrap_or(ZERO),
+                imm: Imm::Expr(expr),
+            }];
+        }
+        Offset::Expr(expr) => (
+            Imm::Expr(Expr::high(expr.clone())),
+            Imm::Expr(Expr::low(expr)),
+        ),
+    };
+
+    let mut ins = vec![Instruction::I {
+        op: Opcode::Op(0x0f), // lui
+        rt: AT,
+        rs: ZERO,
+        imm: high,
+    }];
+    if let Some(rs) = base {
+        ins.push(Instruction::R {
+            op: Opcode::Funct(0x21), // addu
+            rd: AT,
+            rs: AT,
+            rt: rs,
+            shamt: 0,
+        });
+    }
+    ins.push(Instruction::I {
+        op,
+        rt,
+        rs: AT,
+        imm: low,
+    });
+    ins
+}
+
 /// Parses load and store instructions
-/// `<OP> <rt> <imm>(<rs>)
-pub fn load_type(input: &str, op: Opcode) -> ParserOutput {
+/// `<OP> <rt>, <offset>(<rs>)` or any other memory operand, which is expanded through $at
+pub fn load_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
     let (input, rt) = context("Expected target register", parser::register)(input)?;
-    let (input, imm) = context("Expected offset value", preceded(separator, immediate))(input)?;
-    let (input, rs) = context(
-        "Expected source value",
-        delimited(tag("("), parser::register, tag(")")),
-    )(input)?;
+    let (input, (offset, base)) =
+        context("Expected memory address", preceded(separator, memory_operand(constants)))(
+            input,
+        )?;
     Ok((
         input,
-        Line::Instruction(vec![Instruction::I { op, rt, rs, imm }]),
+        Line::Instruction(memory_access(op, rt, offset, base)),
     ))
 }
 
 /// Parses branch instructions
 /// `<OP> <rt> <rs> <label>`
-pub fn branch_type(input: &str, op: Opcode) -> ParserOutput {
+pub fn branch_type<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
     let (input, rt) = context("Expected first register", parser::register)(input)?;
     let (input, rs) = context(
         "Expected second register",
         preceded(separator, parser::register),
     )(input)?;
-    let (input, mut imm) = context("Expected label", preceded(separator, immediate))(input)?;
+    let (input, imm) = context(
+        "Expected label",
+        preceded(separator, branch_target(constants)),
+    )(input)?;
+    Ok((
+        input,
+        Line::Instruction(with_branch_slots(vec![Instruction::I { op, rt, rs, imm }])),
+    ))
+}
 
-    // if we got a label make it pc relative
-    if let Imm::Label(label) = imm {
-        imm = Imm::PcRelative(label);
-    }
+/// Parses branch instructions that compare a register against zero
+/// `<OP> <rs> <label>`
+pub fn branch_zero<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rs) = context("Expected register", parser::register)(input)?;
+    let (input, imm) = context(
+        "Expected label",
+        preceded(separator, branch_target(constants)),
+    )(input)?;
     Ok((
         input,
-        Line::Instruction(vec![
-            Instruction::I { op, rt, rs, imm },
-            Instruction::Literal {
-                data: vec![0, 0, 0, 0],
-            },
-            Instruction::Literal {
-                data: vec![0, 0, 0, 0],
-            },
-        ]),
+        Line::Instruction(with_branch_slots(vec![Instruction::I {
+            op,
+            rt: ZERO,
+            rs,
+            imm,
+        }])),
+    ))
+}
+
+/// Parses an unconditional branch pseudo instruction
+/// `b <label>`
+pub fn b_ins<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    let (input, imm) = context("Expected label", branch_target(constants))(input)?;
+    Ok((
+        input,
+        Line::Instruction(with_branch_slots(vec![Instruction::I {
+            op: Opcode::Op(0x04), // beq
+            rt: ZERO,
+            rs: ZERO,
+            imm,
+        }])),
     ))
 }
 
 /// Parses branch pseudo instructions
-pub fn multi_branch(input: &str, less_than: bool, equal: bool) -> ParserOutput {
+pub fn multi_branch<'a>(
+    input: &'a str,
+    less_than: bool,
+    equal: bool,
+    unsigned: bool,
+    constants: &Constants,
+) -> ParserOutput<'a> {
     let (input, rt) = context("Expected first register", parser::register)(input)?;
     let (input, rs) = context(
         "Expected second register",
         preceded(separator, parser::register),
     )(input)?;
-
-    let (input, mut imm) = context("Expected label", preceded(separator, immediate))(input)?;
-
-    // if we got a label make it pc relative
-    if let Imm::Label(label) = imm {
-        imm = Imm::PcRelative(label);
-    }
+    let (input, imm) = context(
+        "Expected label",
+        preceded(separator, branch_target(constants)),
+    )(input)?;
     Ok((
         input,
-        Line::Instruction(vec![
+        Line::Instruction(with_branch_slots(vec![
             Instruction::R {
-                op: Opcode::Funct(0x2a), //slt
+                op: if unsigned {
+                    Opcode::Funct(0x2b) // sltu
+                } else {
+                    Opcode::Funct(0x2a) // slt
+                },
                 rd: AT,
                 rs: if less_than != equal { rt } else { rs }, // != is used as an XOR
                 rt: if less_than != equal { rs } else { rt },
@@ -270,13 +827,7 @@ pub fn multi_branch(input: &str, less_than: bool, equal: bool) -> ParserOutput {
                 rt: ZERO,
                 imm,
             },
-            Instruction::Literal {
-                data: vec![0, 0, 0, 0],
-            },
-            Instruction::Literal {
-                data: vec![0, 0, 0, 0],
-            },
-        ]),
+        ])),
     ))
 }
 
@@ -290,7 +841,7 @@ pub fn move_ins(input: &str) -> ParserOutput {
     Ok((
         input,
         Line::Instruction(vec![Instruction::R {
-            op: Opcode::Funct(0x20), // add
+            op: Opcode::Funct(0x21), // addu
             rd,
             rs,
             rt: ZERO,
@@ -299,89 +850,72 @@ pub fn move_ins(input: &str) -> ParserOutput {
     ))
 }
 
-/// Parses a mov.s pseudo instruction
-pub fn mov_s_ins(input: &str) -> ParserOutput {
-    let (input, rd) = context("Expected destination register", parser::register)(input)?;
-    let (input, rs) = context(
-        "Expected source register",
-        preceded(separator, parser::register),
+/// Parses mfc0 and mtc0 instructions
+/// `<OP> <rt>, <rd>` where rd is a coprocessor 0 register
+pub fn cop0_move(input: &str, op: Opcode) -> ParserOutput<'_> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, rd) = context(
+        "Coprocessor 0 Register",
+        preceded(separator, parser::cop0_register),
     )(input)?;
     Ok((
         input,
         Line::Instruction(vec![Instruction::R {
-            op: Opcode::Funct(0x11), // add.s
+            op,
             rd,
+            rs: ZERO,
+            rt,
+            shamt: 0,
+        }]),
+    ))
+}
+
+/// Parses trap instructions with an immediate using the format
+/// `<OP> <rs>, <imm>`
+pub fn trap_imm<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rs) = context("Source Register", parser::register)(input)?;
+    let (input, imm) = context(
+        "Expected immediate value",
+        preceded(separator, immediate(constants)),
+    )(input)?;
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::I {
+            op,
+            rt: ZERO,
             rs,
-            rt: rs,
-            shamt: 0x10,
+            imm,
         }]),
     ))
 }
 
-/// Parses li and la instructions
-pub fn li_ins(input: &str) -> ParserOutput {
-    // li and la are the same
-    map(
-        map(
-            tuple((parser::register, preceded(separator, immediate))),
-            |(reg, imm)| match imm {
-                Imm::Label(ref name) => vec![
-                    // TODO: Make loads >16bits work
-                    Instruction::I {
-                        op: Opcode::Op(0x0f),
-                        rt: AT,
-                        rs: ZERO,
-                        imm: Imm::HighHWord(name.clone()),
-                    },
-                    Instruction::I {
-                        op: Opcode::Op(0x0d),
-                        rt: reg,
-                        rs: AT,
-                        imm: Imm::LowHWord(name.clone()),
-                    },
-                ],
-                Imm::HighHWord(_) => vec![Instruction::I {
-                    op: Opcode::Op(0x0f),
-                    rt: reg,
-                    rs: ZERO,
-                    imm,
-                }],
-                Imm::LowHWord(_) => vec![Instruction::I {
-                    op: Opcode::Op(0x08), //addi
-                    rt: reg,
-                    rs: ZERO,
-                    imm,
-                }],
-                Imm::Value(value) => {
-                    if value > u16::MAX as i64 {
-                        vec![
-                            Instruction::I {
-                                op: Opcode::Op(0x0f),
-                                rt: AT,
-                                rs: ZERO,
-                                imm: Imm::Value((value & 0xFFFF0000) >> 16),
-                            },
-                            Instruction::I {
-                                op: Opcode::Op(0x0d),
-                                rt: reg,
-                                rs: AT,
-                                imm: Imm::Value(value & 0xFFFF),
-                            },
-                        ]
-                    } else {
-                        vec![Instruction::I {
-                            op: Opcode::Op(0x08), // addi
-                            rt: reg,
-                            rs: ZERO,
-                            imm,
-                        }]
-                    }
-                }
-                Imm::PcRelative(_) => todo!(),
-            },
-        ),
-        |x| Line::Instruction(x),
-    )(input)
+/// Parses break instructions with an optional code
+/// `break [code]`
+pub fn break_ins<'a>(input: &'a str, op: Opcode, constants: &Constants) -> ParserOutput<'a> {
+    let (input, code) = opt(constant(constants))(input)?;
+    let code = code.unwrap_or(0) as u32;
+    Ok((
+        input,
+        // the code is too wide for the shamt field
+        Line::Instruction(vec![Instruction::Literal {
+            data: (((code & 0xfffff) << 6) | op.value()).to_le_bytes().to_vec(),
+        }]),
+    ))
+}
+
+/// Parses instructions without operands
+/// `<OP>`
+pub fn no_operands(input: &str, op: Opcode) -> ParserOutput<'_> {
+    Ok((
+        input,
+        Line::Instruction(vec![Instruction::R {
+            op,
+            rd: ZERO,
+            rs: ZERO,
+            rt: ZERO,
+            shamt: 0,
+        }]),
+    ))
 }
 
 pub fn syscall(input: &str) -> ParserOutput {
@@ -406,11 +940,14 @@ pub fn nop(input: &str) -> ParserOutput {
     ))
 }
 
-pub fn instruction(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
+pub fn instruction<'a>(
+    input: &'a str,
+    constants: &Constants,
+) -> IResult<&'a str, Line, VerboseError<&'a str>> {
     // grab the opcode
     let (input, parser) = preceded(
         space0,
         terminated(parser::opcode, alt((space1, peek(tag("\n"))))),
     )(input)?;
-    parser.parse(input)
+    parser.parse(input, constants)
 }
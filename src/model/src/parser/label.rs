@@ -1,7 +1,7 @@
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{alpha1, alphanumeric1, multispace0},
+    character::complete::{alpha1, alphanumeric1, char, digit1, multispace0},
     combinator::{map, recognize},
     error::{context, VerboseError},
     multi::many0,
@@ -20,6 +20,13 @@ pub fn identifier(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
     ))(input)
 }
 
+/// Parses the name of a label where it is defined, which is an identifier, a local label
+/// `.<identifier>` that belongs to the label before it or a numeric label `<digits>` that can be
+/// defined any number of times
+pub fn label_name(input: &str) -> IResult<&str, &str, VerboseError<&str>> {
+    alt((identifier, recognize(pair(char('.'), identifier)), digit1))(input)
+}
+
 pub fn label(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
     context(
         "Label",
@@ -27,7 +34,7 @@ pub fn label(input: &str) -> IResult<&str, Line, VerboseError<&str>> {
             preceded(
                 multispace0,
                 terminated(
-                    context("Identifier", identifier),
+                    context("Identifier", label_name),
                     context("Label must be terminated by a colon", tag(":")),
                 ),
             ),
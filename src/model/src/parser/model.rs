@@ -1,4 +1,6 @@
-use std::collections::HashMap;
+use std::{cmp::Reverse, collections::HashMap};
+
+use super::Expr;
 
 mod instruction;
 mod opcode;
@@ -6,51 +8,144 @@ mod opcode;
 pub use instruction::*;
 pub use opcode::Opcode;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Segment {
     Text,
     Data,
+    KText,
+    KData,
 }
 
 pub const TEXT_BASE: u32 = 0x00400000;
 pub const DATA_BASE: u32 = 0x10010000;
 pub const STACK_BASE: u32 = 0x7fffeffc;
+/// Kernel text starts at the exception vector so `.ktext` code is the exception handler
+pub const KTEXT_BASE: u32 = 0x80000180;
+pub const KDATA_BASE: u32 = 0x90000000;
+/// Data declared by `.extern` is placed just below the data segment
+pub const EXTERN_BASE: u32 = 0x10000000;
 
 /// Tracks the current position in each segment
+///
+/// Data is aligned to its size unless `.align 0` turned that off, until the next segment
+/// directive.
 pub struct Segments {
     segments: Vec<u32>,
+    current: Segment,
+    auto_align: bool,
+    /// The end of the data reserved by `.extern`
+    external: u32,
 }
 
 impl Default for Segments {
     fn default() -> Self {
         Self {
-            segments: vec![TEXT_BASE, DATA_BASE],
+            segments: vec![TEXT_BASE, DATA_BASE, KTEXT_BASE, KDATA_BASE],
+            current: Segment::Text,
+            auto_align: true,
+            external: EXTERN_BASE,
         }
     }
 }
 
 impl Segments {
     pub fn switch(&mut self, seg: Segment) -> &mut u32 {
-        match seg {
+        self.current = seg;
+        self.auto_align = true;
+        self.pc()
+    }
+
+    /// The position in the current segment
+    pub fn pc(&mut self) -> &mut u32 {
+        match self.current {
             Segment::Text => &mut self.segments[0],
             Segment::Data => &mut self.segments[1],
+            Segment::KText => &mut self.segments[2],
+            Segment::KData => &mut self.segments[3],
+        }
+    }
+
+    /// Applies `.align <n>`, which aligns to 2^n bytes or turns off automatic alignment for 0
+    pub fn align(&mut self, n: u32) {
+        if n == 0 {
+            self.auto_align = false;
+        } else {
+            let size = 1 << n;
+            let pc = self.pc();
+            *pc = pc.next_multiple_of(size);
         }
     }
+
+    /// Makes room for an instruction, returning the address it is placed at
+    pub fn place(&mut self, ins: &Instruction) -> u32 {
+        let alignment = ins.alignment();
+        let auto_align = self.auto_align;
+        let pc = self.pc();
+        if auto_align {
+            *pc = pc.next_multiple_of(alignment);
+        }
+        let address = *pc;
+        *pc += ins.size() as u32;
+        address
+    }
+
+    /// Reserves `size` bytes for `.extern`, returning the word aligned address they start at
+    pub fn reserve_extern(&mut self, size: u32) -> u32 {
+        let address = self.external.next_multiple_of(4);
+        self.external = address + size;
+        address
+    }
 }
 
 #[derive(Debug)]
 pub enum Line {
     Instruction(Vec<Instruction>),
     Label(String),
+    /// A named constant from `<name> = <expression>`, `.eqv` or `.set`
+    Constant {
+        name: String,
+        expr: Expr,
+        /// Constants made by `.set` can be defined again
+        redefinable: bool,
+    },
     Segment(Segment),
+    /// `.align <n>`
+    Align(u32),
+    /// Labels made visible to other files by `.globl`
+    Global(Vec<String>),
+    /// `.extern <label>, <size>`, a global label for `size` bytes outside the data segment
+    Extern {
+        name: String,
+        size: u32,
+    },
     Comment(String),
     Blank,
 }
 
+impl Line {
+    /// Calls `f` on the name of every label and named constant the line uses, which can rename
+    /// them
+    pub fn symbols_mut(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Line::Instruction(ins) => {
+                for ins in ins {
+                    ins.symbols_mut(f);
+                }
+            }
+            Line::Constant { expr, .. } => expr.symbols_mut(f),
+            _ => {}
+        }
+    }
+}
+
 /// Stores labels
 #[derive(Default, Debug)]
 pub struct LabelTable {
     labels: HashMap<String, u32>,
+    constants: HashMap<String, i64>,
+    /// Labels named by `.globl`, with the source line naming them
+    globals: HashMap<String, usize>,
+    entry: Option<u32>,
 
     // Is kept sorted by PC value
     lines: Vec<(usize, u32)>,
@@ -74,10 +169,83 @@ impl LabelTable {
         self.lines.sort_by_key(|x| x.1);
     }
 
+    /// Changes the source line of every instruction, like to the lines before macros were
+    /// expanded
+    pub fn map_lines(&mut self, f: impl Fn(usize) -> usize) {
+        for (line, _) in &mut self.lines {
+            *line = f(*line);
+        }
+    }
+
+    /// Insert a named constant
+    pub fn insert_constant(&mut self, key: String, v: i64) {
+        self.constants.insert(key, v);
+    }
+
+    /// Marks a label as global, which is named on source line `line`
+    pub fn insert_global(&mut self, key: String, line: usize) {
+        self.globals.entry(key).or_insert(line);
+    }
+
+    /// Iterates over the global labels and the source lines naming them, which either make a
+    /// label of this file visible to the others or use one of another file
+    pub fn globals(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.globals.iter().map(|(name, line)| (name, *line))
+    }
+
+    /// Sets the address the program starts at
+    pub fn set_entry(&mut self, address: u32) {
+        self.entry = Some(address);
+    }
+
+    /// The address the program starts at, which is the start of the text segment unless the
+    /// program has a global `main`
+    pub fn entry(&self) -> u32 {
+        self.entry.unwrap_or(TEXT_BASE)
+    }
+
+    /// Iterates over the names of every label and named constant
+    pub fn label_names(&self) -> impl Iterator<Item = &String> {
+        self.labels.keys().chain(self.constants.keys())
+    }
+
+    /// Iterates over every label and its address
+    pub fn labels(&self) -> impl Iterator<Item = (&String, u32)> {
+        self.labels.iter().map(|(name, address)| (name, *address))
+    }
+
+    /// Iterates over every named constant and its value
+    pub fn constants(&self) -> impl Iterator<Item = (&String, i64)> {
+        self.constants.iter().map(|(name, value)| (name, *value))
+    }
+
     pub fn get_label(&self, key: &str) -> Option<u32> {
         self.labels.get(key).map(|x| *x)
     }
 
+    /// Finds the closest label at or before an address, with how far past it the address is
+    ///
+    /// Numeric labels are skipped since they can't be named again, and labels that are not local
+    /// are preferred when several share an address.
+    pub fn label_before(&self, address: u32) -> Option<(&str, u32)> {
+        self.labels
+            .iter()
+            .filter(|(name, at)| {
+                let last = name.rsplit('.').next().unwrap_or(name);
+                **at <= address && !last.starts_with(|c: char| c.is_ascii_digit())
+            })
+            .max_by_key(|(name, at)| (**at, !name.contains('.'), Reverse(name.as_str())))
+            .map(|(name, at)| (name.as_str(), address - at))
+    }
+
+    /// Gets the value of a named constant or the address of a label
+    pub fn get_symbol(&self, key: &str) -> Option<i64> {
+        match self.constants.get(key) {
+            Some(value) => Some(*value),
+            None => self.get_label(key).map(|address| address as i64),
+        }
+    }
+
     /// Gets the source code line for a given PC
     pub fn get_line(&self, pc: u32) -> Option<usize> {
         if pc < TEXT_BASE {
@@ -1,42 +1,79 @@
 use super::{LabelTable, Opcode};
+use crate::parser::{AsmError, Expr};
 use crate::Register;
 
 #[derive(Debug)]
 pub enum Symbol {
-    Label(String),
     Address(u32),
+    /// An address that depends on a label
+    Expr(Expr),
 }
 
 impl Symbol {
-    pub fn asm(&self, labels: &LabelTable) -> u32 {
-        (match self {
-            Symbol::Label(ref name) => labels.get_label(name).unwrap_or(0),
+    pub fn asm(&self, labels: &LabelTable, pc: u32) -> Result<u32, AsmError> {
+        let target = match self {
             Symbol::Address(x) => *x,
-        } & 0x0FFFFFFF)
-            >> 2
+            Symbol::Expr(expr) => expr.eval(labels)? as u32,
+        };
+
+        // jumps keep the upper bits of the address of the instruction after them
+        if target & 0xF0000000 != pc.wrapping_add(4) & 0xF0000000 {
+            return Err(AsmError::JumpRange(match self {
+                Symbol::Address(x) => format!("0x{x:08x}"),
+                Symbol::Expr(expr) => expr.to_string(),
+            }));
+        }
+        Ok((target & 0x0FFFFFFF) >> 2)
     }
 }
 
 #[derive(Debug)]
 pub enum Imm {
-    Label(String),
-    HighHWord(String),
-    LowHWord(String),
     Value(i64),
-    PcRelative(String),
+    /// A value that depends on a label
+    Expr(Expr),
+    /// The address a branch jumps to, encoded as the number of instructions it skips
+    PcRelative(Expr),
 }
 
 impl Imm {
-    pub fn asm(&self, labels: &LabelTable, pc: u32) -> u32 {
-        match self {
-            Imm::Label(ref name) => labels.get_label(name).unwrap_or(0),
-            Imm::HighHWord(ref name) => (labels.get_label(name).unwrap_or(0) & 0xFFFF0000) >> 16,
-            Imm::LowHWord(ref name) => labels.get_label(name).unwrap_or(0) & 0xFFFF,
-            Imm::Value(x) => *x as u32,
-            Imm::PcRelative(ref name) => {
-                let offset = (labels.get_label(name).unwrap_or(0)).wrapping_sub(pc + 4 as u32) >> 2;
+    /// Gets the value of the immediate before it is cut down to the 16 bits of an instruction
+    pub fn asm(&self, labels: &LabelTable, pc: u32) -> Result<i64, AsmError> {
+        Ok(match self {
+            Imm::Value(x) => *x,
+            Imm::Expr(expr) => expr.eval(labels)?,
+            Imm::PcRelative(expr) => {
+                let target = expr.eval(labels)? as u32;
+                let offset = (target.wrapping_sub(pc.wrapping_add(4)) as i32 >> 2) as i64;
+                if !(i16::MIN as i64..=i16::MAX as i64).contains(&offset) {
+                    return Err(AsmError::BranchRange {
+                        label: expr.to_string(),
+                        offset,
+                    });
+                }
                 offset
             }
+        })
+    }
+
+    /// Checks if the value of a constant or label fits in the immediate of an instruction
+    fn check_range(&self, op: &Opcode, value: i64) -> Result<(), AsmError> {
+        match self {
+            // halves of addresses and branch offsets always fit
+            Imm::Expr(expr) if expr.is_half() => return Ok(()),
+            Imm::PcRelative(_) => return Ok(()),
+            _ => {}
+        }
+        let signed = !op.zero_extends();
+        let range = if signed {
+            i16::MIN as i64..=i16::MAX as i64
+        } else {
+            0..=u16::MAX as i64
+        };
+        if range.contains(&value) {
+            Ok(())
+        } else {
+            Err(AsmError::ImmediateRange { value, signed })
         }
     }
 }
@@ -63,6 +100,21 @@ pub enum Instruction {
     Literal {
         data: Vec<u8>,
     },
+    /// A value that has to be aligned to its own size, like the values of `.word`
+    Data {
+        data: Vec<u8>,
+    },
+    /// A value of `.word`, `.half` or `.byte` that depends on a label, stored in `size` bytes
+    Expr {
+        expr: Expr,
+        size: usize,
+    },
+}
+
+/// Checks if a value can be stored in `size` bytes, either signed or unsigned
+pub fn fits_in_bytes(value: i64, size: usize) -> bool {
+    let bits = 8 * size as u32;
+    (-(1_i64 << (bits - 1))..1_i64 << bits).contains(&value)
 }
 
 fn field(x: u32, start: u32, width: u32) -> u32 {
@@ -70,8 +122,50 @@ fn field(x: u32, start: u32, width: u32) -> u32 {
 }
 
 impl Instruction {
-    pub fn asm(&self, labels: &LabelTable, pc: u32) -> (Vec<u8>, usize) {
+    /// Number of bytes the instruction takes up in memory
+    pub fn size(&self) -> usize {
         match self {
+            Instruction::Literal { data } | Instruction::Data { data } => data.len(),
+            Instruction::Expr { size, .. } => *size,
+            _ => 4,
+        }
+    }
+
+    /// The boundary the instruction is placed on when data is aligned automatically
+    pub fn alignment(&self) -> u32 {
+        match self {
+            Instruction::Literal { .. } => 1,
+            _ => self.size() as u32,
+        }
+    }
+
+    /// Calls `f` on the name of every label the instruction uses, which can rename them
+    pub fn symbols_mut(&mut self, f: &mut impl FnMut(&mut String)) {
+        match self {
+            Instruction::I {
+                imm: Imm::Expr(expr) | Imm::PcRelative(expr),
+                ..
+            }
+            | Instruction::J {
+                addr: Symbol::Expr(expr),
+                ..
+            }
+            | Instruction::Expr { expr, .. } => expr.symbols_mut(f),
+            _ => {}
+        }
+    }
+
+    /// Checks if the value of a `.word`, `.half` or `.byte` loses bits when it is stored
+    pub fn truncation(&self, labels: &LabelTable) -> Option<AsmError> {
+        let Instruction::Expr { expr, size } = self else {
+            return None;
+        };
+        let value = expr.eval(labels).ok()?;
+        (!fits_in_bytes(value, *size)).then_some(AsmError::Truncated { value, size: *size })
+    }
+
+    pub fn asm(&self, labels: &LabelTable, pc: u32) -> Result<(Vec<u8>, usize), AsmError> {
+        Ok(match self {
             Instruction::R {
                 op,
                 rd,
@@ -79,31 +173,40 @@ impl Instruction {
                 rt,
                 shamt,
             } => (
-                (field(op.value(), 0, 6)
-                    | field(rd.id(), 11, 5)
-                    | field(rt.id(), 16, 5)
-                    | field(rs.id(), 21, 5)
+                (op.r_bits()
+                    | field(rd.number(), 11, 5)
+                    | field(rt.number(), 16, 5)
+                    | field(rs.number(), 21, 5)
                     | field(*shamt, 6, 5))
                 .to_le_bytes()
                 .to_vec(),
                 4,
             ),
-            Instruction::I { op, rt, rs, imm } => (
-                (field(op.value(), 26, 6)
-                    | field(imm.asm(labels, pc), 0, 16)
-                    | field(rt.id(), 16, 5)
-                    | field(rs.id(), 21, 5))
-                .to_le_bytes()
-                .to_vec(),
-                4,
-            ),
-            Instruction::Literal { data } => (data.clone(), data.len()),
+            Instruction::I { op, rt, rs, imm } => {
+                let value = imm.asm(labels, pc)?;
+                imm.check_range(op, value)?;
+                (
+                    (op.i_bits()
+                        | field(value as u32, 0, 16)
+                        | field(rt.number(), 16, 5)
+                        | field(rs.number(), 21, 5))
+                    .to_le_bytes()
+                    .to_vec(),
+                    4,
+                )
+            }
+            Instruction::Literal { data } | Instruction::Data { data } => {
+                (data.clone(), data.len())
+            }
+            Instruction::Expr { expr, size } => {
+                (expr.eval(labels)?.to_le_bytes()[..*size].to_vec(), *size)
+            }
             Instruction::J { op, addr } => (
-                (field(op.value(), 26, 6) | field(addr.asm(labels), 0, 26))
+                (field(op.value(), 26, 6) | field(addr.asm(labels, pc)?, 0, 26))
                     .to_le_bytes()
                     .to_vec(),
                 4,
             ),
-        }
+        })
     }
 }
@@ -2,6 +2,17 @@
 pub enum Opcode {
     Op(u8),
     Funct(u8),
+    /// REGIMM (opcode 0x01) instructions which are selected by their rt field
+    RegImm(u8),
+    /// SPECIAL2 (opcode 0x1c) instructions which are selected by their funct
+    Special2(u8),
+    /// SPECIAL3 (opcode 0x1f) instructions which are selected by their funct, and by their sa
+    /// field for the byte shuffles
+    Special3 { funct: u8, sa: u8 },
+    /// COP0 (opcode 0x10) instructions which are selected by their rs field and funct
+    Cop0 { rs: u8, funct: u8 },
+    /// COP1 (opcode 0x11) instructions which are selected by their format and funct
+    Cop1 { fmt: u8, funct: u8 },
 }
 
 impl Opcode {
@@ -9,6 +20,40 @@ impl Opcode {
         match self {
             Opcode::Op(op) => *op as u32,
             Opcode::Funct(op) => *op as u32,
+            Opcode::RegImm(op) => *op as u32,
+            Opcode::Special2(op) => *op as u32,
+            Opcode::Special3 { funct, .. } => *funct as u32,
+            Opcode::Cop0 { funct, .. } => *funct as u32,
+            Opcode::Cop1 { funct, .. } => *funct as u32,
+        }
+    }
+
+    /// Whether the immediate of this I-type opcode is zero extended instead of sign extended
+    pub fn zero_extends(&self) -> bool {
+        // andi, ori, xori and lui
+        matches!(self, Opcode::Op(0x0c..=0x0f))
+    }
+
+    /// The bits of an R-type instruction word that select this opcode
+    pub fn r_bits(&self) -> u32 {
+        match self {
+            Opcode::Special2(funct) => (0x1c << 26) | *funct as u32,
+            Opcode::Special3 { funct, sa } => (0x1f << 26) | ((*sa as u32) << 6) | *funct as u32,
+            Opcode::Cop0 { rs, funct } => (0x10 << 26) | ((*rs as u32) << 21) | *funct as u32,
+            Opcode::Cop1 { fmt, funct } => (0x11 << 26) | ((*fmt as u32) << 21) | *funct as u32,
+            op => op.value(),
+        }
+    }
+
+    /// The bits of an I-type instruction word that select this opcode
+    pub fn i_bits(&self) -> u32 {
+        match self {
+            Opcode::RegImm(rt) => (0x01 << 26) | ((*rt as u32) << 16),
+            // COP1 branches keep their true/false bit in the rt field
+            Opcode::Cop1 { fmt, funct } => {
+                (0x11 << 26) | ((*fmt as u32) << 21) | ((*funct as u32) << 16)
+            }
+            op => op.value() << 26,
         }
     }
 }
@@ -1,24 +1,34 @@
-use super::directives::{ascii_lit, asciiz_lit, byte_lit, half_lit, segment, space, word_lit};
+use super::directives::{
+    align, ascii_lit, asciiz_lit, byte_lit, double_lit, external, float_lit, globl, half_lit,
+    segment, space, symbolic_constant, word_lit,
+};
 use super::instruction::{
-    branch_type, i_type, j_type, jr_type, li_ins, load_type, lui, move_ins, multi_branch, nop,
-    r_type, shift_type, syscall,
+    b_ins, bit_field, bit_shuffle, branch_type, branch_zero, count_leading, float_cond_move, i_type,
+    j_type, jalr_type, jr_type, load_type, lui, move_ins, multi_branch, nop, r_type,
+    rotate_type, rotate_variable, shift_type, shift_variable, syscall,
+};
+use super::pseudo::{
+    abs_ins, div_ins, la_ins, mulo_ins, neg_ins, not_ins, rem_ins, set_ins, ulh_ins,
+    unaligned_word, SetCond,
 };
 use super::model::{Line, Opcode, Segment};
 
-use nom::combinator::fail;
 use nom::error::{context, VerboseError};
 use nom::{bytes::complete::take_till, combinator::map_res, IResult};
-use crate::parser::instruction::{mov_s_ins, r_no_dst, hilo};
-
-type InsParser = fn(&str, Opcode) -> IResult<&str, Line, VerboseError<&str>>;
+use crate::parser::instruction::{
+    break_ins, cop0_move, cop1_control_move, cop1_move, float_branch, float_compare, float_r_type,
+    float_unary, hilo, li_s_ins, no_operands, r_no_dst, to_hilo, trap_imm, ParserOutput,
+};
+use crate::parser::Constants;
 
-const NO_PARSER: InsParser = |input, _| context("No parser for instruction", fail)(input);
+/// Parses the arguments of an instruction given its opcode and the named constants defined so far
+type OperandParser = Box<dyn for<'a> Fn(&'a str, Opcode, &Constants) -> ParserOutput<'a>>;
 
 /// Holds a parsed opcode and a nom parser that can parse its arguments and produce an Instruction
 /// object
 pub struct InstructionParser {
     op: Opcode,
-    parser: Box<dyn Fn(&str, Opcode) -> IResult<&str, Line, VerboseError<&str>>>,
+    parser: OperandParser,
 }
 
 impl InstructionParser {
@@ -26,6 +36,14 @@ impl InstructionParser {
     pub fn new<F>(op: Opcode, parser: F) -> Self
     where
         F: Fn(&str, Opcode) -> IResult<&str, Line, VerboseError<&str>> + 'static,
+    {
+        Self::with_constants(op, move |i, op, _| (parser)(i, op))
+    }
+
+    /// Creates a parser that needs to know its own opcode and the named constants defined so far
+    pub fn with_constants<F>(op: Opcode, parser: F) -> Self
+    where
+        F: for<'a> Fn(&'a str, Opcode, &Constants) -> ParserOutput<'a> + 'static,
     {
         Self {
             op,
@@ -38,80 +56,24 @@ impl InstructionParser {
     where
         F: Fn(&str) -> IResult<&str, Line, VerboseError<&str>> + 'static,
     {
-        Self {
-            op: Opcode::Op(0),
-            parser: Box::new(move |i, _| (parser)(i)),
-        }
+        Self::pseudo_with_constants(move |i, _| (parser)(i))
     }
 
-    /// Run the parser contained in this object
-    pub fn parse<'a>(&self, input: &'a str) -> IResult<&'a str, Line, VerboseError<&'a str>> {
-        (self.parser)(input, self.op)
-    }
-}
-
-pub fn opcode_name(input: u32) -> Option<&'static str> {
-    // early return if the instruction is a nop
-    if input == 0 {
-        return Some("nop");
+    /// Creates a pseudo instruction parser that needs the named constants defined so far
+    pub fn pseudo_with_constants<F>(parser: F) -> Self
+    where
+        F: for<'a> Fn(&'a str, &Constants) -> ParserOutput<'a> + 'static,
+    {
+        Self::with_constants(Opcode::Op(0), move |i, _, constants| (parser)(i, constants))
     }
 
-    // copied from src/stages/decode.rs
-    let op_mask = 0b11111100000000000000000000000000;
-    let fn_mask = 0b00000000000000000000000000111111;
-
-    let funct = input & fn_mask;
-    let op = (input & op_mask) >> 26;
-
-    let opcode = if op == 0 {
-        Opcode::Funct(funct as u8)
-    } else {
-        Opcode::Op(op as u8)
-    };
-
-    match opcode {
-        Opcode::Funct(funct) => match funct {
-            0x00 => Some("sll"),
-            0x02 => Some("srl"),
-            0x03 => Some("sra"),
-            0x06 => Some("srlv"),
-            0x08 => Some("jr"),
-            0x0c => Some("syscall"),
-            0x1a => Some("div"),
-            0x1b => Some("divu"),
-            0x20 => Some("add"),
-            0x21 => Some("addu"),
-            0x22 => Some("sub"),
-            0x24 => Some("and"),
-            0x25 => Some("or"),
-            0x26 => Some("xor"),
-            0x27 => Some("nor"),
-            0x2a => Some("slt"),
-
-            0x11 => Some("add.s"),
-            0x10 => Some("mfhi"),
-            0x12 => Some("mflo"),
-
-            _ => None,
-        },
-        Opcode::Op(op) => match op {
-            0x02 => Some("j"),
-            0x03 => Some("jal"),
-            0x04 => Some("beq"),
-            0x05 => Some("bne"),
-            0x08 => Some("addi"),
-            0x09 => Some("addiu"),
-            0x0c => Some("andi"),
-            0x0d => Some("ori"),
-            0x0f => Some("lui"),
-            0x20 => Some("lb"),
-            0x28 => Some("sb"),
-            0x23 => Some("lw"),
-            0x2b => Some("sw"),
-            0x1c => Some("mul"),
-
-            _ => None,
-        },
+    /// Run the parser contained in this object
+    pub fn parse<'a>(
+        &self,
+        input: &'a str,
+        constants: &Constants,
+    ) -> IResult<&'a str, Line, VerboseError<&'a str>> {
+        (self.parser)(input, self.op, constants)
     }
 }
 
@@ -123,61 +85,408 @@ pub fn opcode(input: &str) -> IResult<&str, InstructionParser, VerboseError<&str
             |word: &str| match word.to_lowercase().trim() {
                 "add" => Ok(InstructionParser::new(Opcode::Funct(0x20), r_type)),
                 "sub" => Ok(InstructionParser::new(Opcode::Funct(0x22), r_type)),
-                "addi" => Ok(InstructionParser::new(Opcode::Op(0x08), i_type)),
-                "addiu" => Ok(InstructionParser::new(Opcode::Op(0x09), i_type)),
+                "subu" => Ok(InstructionParser::new(Opcode::Funct(0x23), r_type)),
+                "addi" => Ok(InstructionParser::with_constants(Opcode::Op(0x08), i_type)),
+                "addiu" => Ok(InstructionParser::with_constants(Opcode::Op(0x09), i_type)),
                 "addu" => Ok(InstructionParser::new(Opcode::Funct(0x21), r_type)),
                 "and" => Ok(InstructionParser::new(Opcode::Funct(0x24), r_type)),
-                "andi" => Ok(InstructionParser::new(Opcode::Op(0x0c), i_type)),
-                "beq" => Ok(InstructionParser::new(Opcode::Op(0x04), branch_type)),
-                "bne" => Ok(InstructionParser::new(Opcode::Op(0x05), branch_type)),
-                "blt" => Ok(InstructionParser::pseudo(|i| multi_branch(i, true, false))),
-                "bgt" => Ok(InstructionParser::pseudo(|i| multi_branch(i, false, false))),
-                "ble" => Ok(InstructionParser::pseudo(|i| multi_branch(i, true, true))),
-                "bge" => Ok(InstructionParser::pseudo(|i| multi_branch(i, false, true))),
-                "div" => Ok(InstructionParser::new(Opcode::Funct(0x1a), r_no_dst)),
-                "divu" => Ok(InstructionParser::new(Opcode::Funct(0x1b), NO_PARSER)),
+                "andi" => Ok(InstructionParser::with_constants(Opcode::Op(0x0c), i_type)),
+                "beq" => Ok(InstructionParser::with_constants(Opcode::Op(0x04), branch_type)),
+                "bne" => Ok(InstructionParser::with_constants(Opcode::Op(0x05), branch_type)),
+                "blez" => Ok(InstructionParser::with_constants(Opcode::Op(0x06), branch_zero)),
+                "bgtz" => Ok(InstructionParser::with_constants(Opcode::Op(0x07), branch_zero)),
+                "bltz" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x00), branch_zero)),
+                "bgez" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x01), branch_zero)),
+                "bltzal" => Ok(InstructionParser::with_constants(
+                    Opcode::RegImm(0x10),
+                    branch_zero,
+                )),
+                "bgezal" => Ok(InstructionParser::with_constants(
+                    Opcode::RegImm(0x11),
+                    branch_zero,
+                )),
+                "b" => Ok(InstructionParser::pseudo_with_constants(b_ins)),
+                "beqz" => Ok(InstructionParser::with_constants(Opcode::Op(0x04), branch_zero)),
+                "bnez" => Ok(InstructionParser::with_constants(Opcode::Op(0x05), branch_zero)),
+                "blt" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, true, false, false, c)
+                })),
+                "bgt" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, false, false, false, c)
+                })),
+                "ble" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, true, true, false, c)
+                })),
+                "bge" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, false, true, false, c)
+                })),
+                "bltu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, true, false, true, c)
+                })),
+                "bgtu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, false, false, true, c)
+                })),
+                "bleu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, true, true, true, c)
+                })),
+                "bgeu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    multi_branch(i, false, true, true, c)
+                })),
+                "div" => Ok(InstructionParser::pseudo_with_constants(|i, c| div_ins(i, false, c))),
+                "divu" => Ok(InstructionParser::pseudo_with_constants(|i, c| div_ins(i, true, c))),
+                "mult" => Ok(InstructionParser::new(Opcode::Funct(0x18), r_no_dst)),
+                "multu" => Ok(InstructionParser::new(Opcode::Funct(0x19), r_no_dst)),
+                "madd" => Ok(InstructionParser::new(Opcode::Special2(0x00), r_no_dst)),
+                "maddu" => Ok(InstructionParser::new(Opcode::Special2(0x01), r_no_dst)),
+                "msub" => Ok(InstructionParser::new(Opcode::Special2(0x04), r_no_dst)),
+                "msubu" => Ok(InstructionParser::new(Opcode::Special2(0x05), r_no_dst)),
+                "clz" => Ok(InstructionParser::new(Opcode::Special2(0x20), count_leading)),
+                "clo" => Ok(InstructionParser::new(Opcode::Special2(0x21), count_leading)),
+                "movz" => Ok(InstructionParser::new(Opcode::Funct(0x0a), r_type)),
+                "movn" => Ok(InstructionParser::new(Opcode::Funct(0x0b), r_type)),
+                "movf" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    float_cond_move(i, false, c)
+                })),
+                "movt" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    float_cond_move(i, true, c)
+                })),
+                "rotr" => Ok(InstructionParser::with_constants(Opcode::Funct(0x02), rotate_type)),
+                "rotrv" => Ok(InstructionParser::new(Opcode::Funct(0x06), rotate_variable)),
+                "seb" => Ok(InstructionParser::new(
+                    Opcode::Special3 { funct: 0x20, sa: 0x10 },
+                    bit_shuffle,
+                )),
+                "seh" => Ok(InstructionParser::new(
+                    Opcode::Special3 { funct: 0x20, sa: 0x18 },
+                    bit_shuffle,
+                )),
+                "wsbh" => Ok(InstructionParser::new(
+                    Opcode::Special3 { funct: 0x20, sa: 0x02 },
+                    bit_shuffle,
+                )),
+                "ext" => Ok(InstructionParser::with_constants(
+                    Opcode::Special3 { funct: 0x00, sa: 0 },
+                    bit_field,
+                )),
+                "ins" => Ok(InstructionParser::with_constants(
+                    Opcode::Special3 { funct: 0x04, sa: 0 },
+                    bit_field,
+                )),
+
+                "tge" => Ok(InstructionParser::new(Opcode::Funct(0x30), r_no_dst)),
+                "tgeu" => Ok(InstructionParser::new(Opcode::Funct(0x31), r_no_dst)),
+                "tlt" => Ok(InstructionParser::new(Opcode::Funct(0x32), r_no_dst)),
+                "tltu" => Ok(InstructionParser::new(Opcode::Funct(0x33), r_no_dst)),
+                "teq" => Ok(InstructionParser::new(Opcode::Funct(0x34), r_no_dst)),
+                "tne" => Ok(InstructionParser::new(Opcode::Funct(0x36), r_no_dst)),
+                "tgei" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x08), trap_imm)),
+                "tgeiu" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x09), trap_imm)),
+                "tlti" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x0a), trap_imm)),
+                "tltiu" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x0b), trap_imm)),
+                "teqi" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x0c), trap_imm)),
+                "tnei" => Ok(InstructionParser::with_constants(Opcode::RegImm(0x0e), trap_imm)),
+                "break" => Ok(InstructionParser::with_constants(Opcode::Funct(0x0d), break_ins)),
 
                 "mfhi" => Ok(InstructionParser::new(Opcode::Funct(0x10), hilo)),
                 "mflo" => Ok(InstructionParser::new(Opcode::Funct(0x12), hilo)),
+                "mthi" => Ok(InstructionParser::new(Opcode::Funct(0x11), to_hilo)),
+                "mtlo" => Ok(InstructionParser::new(Opcode::Funct(0x13), to_hilo)),
+
+                "mfc0" => Ok(InstructionParser::new(
+                    Opcode::Cop0 { rs: 0x00, funct: 0 },
+                    cop0_move,
+                )),
+                "mtc0" => Ok(InstructionParser::new(
+                    Opcode::Cop0 { rs: 0x04, funct: 0 },
+                    cop0_move,
+                )),
+                "eret" => Ok(InstructionParser::new(
+                    Opcode::Cop0 { rs: 0x10, funct: 0x18 },
+                    no_operands,
+                )),
 
-                "j" => Ok(InstructionParser::new(Opcode::Op(0x02), j_type)),
-                "jal" => Ok(InstructionParser::new(Opcode::Op(0x03), j_type)),
+                "j" => Ok(InstructionParser::with_constants(Opcode::Op(0x02), j_type)),
+                "jal" => Ok(InstructionParser::with_constants(Opcode::Op(0x03), j_type)),
                 "jr" => Ok(InstructionParser::new(Opcode::Funct(0x08), jr_type)),
+                "jalr" => Ok(InstructionParser::new(Opcode::Funct(0x09), jalr_type)),
 
-                "lb" => Ok(InstructionParser::new(Opcode::Op(0x20), load_type)),
-                "sb" => Ok(InstructionParser::new(Opcode::Op(0x28), load_type)),
-                "lw" => Ok(InstructionParser::new(Opcode::Op(0x23), load_type)),
-                "sw" => Ok(InstructionParser::new(Opcode::Op(0x2b), load_type)),
-                "lui" => Ok(InstructionParser::new(Opcode::Op(0x0f), lui)),
+                "lb" => Ok(InstructionParser::with_constants(Opcode::Op(0x20), load_type)),
+                "lh" => Ok(InstructionParser::with_constants(Opcode::Op(0x21), load_type)),
+                "lwl" => Ok(InstructionParser::with_constants(Opcode::Op(0x22), load_type)),
+                "lw" => Ok(InstructionParser::with_constants(Opcode::Op(0x23), load_type)),
+                "lbu" => Ok(InstructionParser::with_constants(Opcode::Op(0x24), load_type)),
+                "lhu" => Ok(InstructionParser::with_constants(Opcode::Op(0x25), load_type)),
+                "lwr" => Ok(InstructionParser::with_constants(Opcode::Op(0x26), load_type)),
+                "sb" => Ok(InstructionParser::with_constants(Opcode::Op(0x28), load_type)),
+                "sh" => Ok(InstructionParser::with_constants(Opcode::Op(0x29), load_type)),
+                "swl" => Ok(InstructionParser::with_constants(Opcode::Op(0x2a), load_type)),
+                "sw" => Ok(InstructionParser::with_constants(Opcode::Op(0x2b), load_type)),
+                "swr" => Ok(InstructionParser::with_constants(Opcode::Op(0x2e), load_type)),
+                "ll" => Ok(InstructionParser::with_constants(Opcode::Op(0x30), load_type)),
+                "sc" => Ok(InstructionParser::with_constants(Opcode::Op(0x38), load_type)),
+                "sync" => Ok(InstructionParser::new(Opcode::Funct(0x0f), no_operands)),
+                "lui" => Ok(InstructionParser::with_constants(Opcode::Op(0x0f), lui)),
                 "slt" => Ok(InstructionParser::new(Opcode::Funct(0x2a), r_type)),
-                "ori" => Ok(InstructionParser::new(Opcode::Op(0x0d), i_type)),
+                "sltu" => Ok(InstructionParser::new(Opcode::Funct(0x2b), r_type)),
+                "slti" => Ok(InstructionParser::with_constants(Opcode::Op(0x0a), i_type)),
+                "sltiu" => Ok(InstructionParser::with_constants(Opcode::Op(0x0b), i_type)),
+                "ori" => Ok(InstructionParser::with_constants(Opcode::Op(0x0d), i_type)),
+                "xori" => Ok(InstructionParser::with_constants(Opcode::Op(0x0e), i_type)),
                 "or" => Ok(InstructionParser::new(Opcode::Funct(0x25), r_type)),
                 "xor" => Ok(InstructionParser::new(Opcode::Funct(0x26), r_type)),
                 "nor" => Ok(InstructionParser::new(Opcode::Funct(0x27), r_type)),
-                "sll" => Ok(InstructionParser::new(Opcode::Funct(0x0), shift_type)),
-                "srl" => Ok(InstructionParser::new(Opcode::Funct(0x2), shift_type)),
-                "sra" => Ok(InstructionParser::new(Opcode::Funct(0x3), shift_type)),
-                "srlv" => Ok(InstructionParser::new(Opcode::Funct(0x6), r_type)),
+                "sll" => Ok(InstructionParser::with_constants(Opcode::Funct(0x0), shift_type)),
+                "srl" => Ok(InstructionParser::with_constants(Opcode::Funct(0x2), shift_type)),
+                "sra" => Ok(InstructionParser::with_constants(Opcode::Funct(0x3), shift_type)),
+                "sllv" => Ok(InstructionParser::new(Opcode::Funct(0x4), shift_variable)),
+                "srlv" => Ok(InstructionParser::new(Opcode::Funct(0x6), shift_variable)),
+                "srav" => Ok(InstructionParser::new(Opcode::Funct(0x7), shift_variable)),
                 "move" => Ok(InstructionParser::pseudo(move_ins)),
-                "li" => Ok(InstructionParser::pseudo(li_ins)),
-                "la" => Ok(InstructionParser::pseudo(li_ins)),
+                "li" => Ok(InstructionParser::pseudo_with_constants(la_ins)),
+                "la" => Ok(InstructionParser::pseudo_with_constants(la_ins)),
+                "not" => Ok(InstructionParser::pseudo(not_ins)),
+                "neg" => Ok(InstructionParser::pseudo(|i| neg_ins(i, false))),
+                "negu" => Ok(InstructionParser::pseudo(|i| neg_ins(i, true))),
+                "abs" => Ok(InstructionParser::pseudo(abs_ins)),
+                "rem" => Ok(InstructionParser::pseudo_with_constants(|i, c| rem_ins(i, false, c))),
+                "remu" => Ok(InstructionParser::pseudo_with_constants(|i, c| rem_ins(i, true, c))),
+                "mulo" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    mulo_ins(i, false, c)
+                })),
+                "mulou" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    mulo_ins(i, true, c)
+                })),
+                "seq" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::Equal, false, c)
+                })),
+                "sne" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::NotEqual, false, c)
+                })),
+                "sge" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::GreaterEqual, false, c)
+                })),
+                "sgeu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::GreaterEqual, true, c)
+                })),
+                "sgt" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::GreaterThan, false, c)
+                })),
+                "sgtu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::GreaterThan, true, c)
+                })),
+                "sle" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::LessEqual, false, c)
+                })),
+                "sleu" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    set_ins(i, SetCond::LessEqual, true, c)
+                })),
+                "ulw" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    unaligned_word(i, false, c)
+                })),
+                "usw" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    unaligned_word(i, true, c)
+                })),
+                "ulh" => Ok(InstructionParser::pseudo_with_constants(ulh_ins)),
                 "syscall" => Ok(InstructionParser::pseudo(syscall)),
                 "nop" => Ok(InstructionParser::pseudo(nop)),
-                ".word" => Ok(InstructionParser::pseudo(word_lit)),
-                ".half" => Ok(InstructionParser::pseudo(half_lit)),
-                ".byte" => Ok(InstructionParser::pseudo(byte_lit)),
-                ".space" => Ok(InstructionParser::pseudo(space)),
+                ".word" => Ok(InstructionParser::pseudo_with_constants(word_lit)),
+                ".half" => Ok(InstructionParser::pseudo_with_constants(half_lit)),
+                ".byte" => Ok(InstructionParser::pseudo_with_constants(byte_lit)),
+                ".float" => Ok(InstructionParser::pseudo_with_constants(float_lit)),
+                ".double" => Ok(InstructionParser::pseudo_with_constants(double_lit)),
+                ".space" => Ok(InstructionParser::pseudo_with_constants(space)),
                 ".ascii" => Ok(InstructionParser::pseudo(ascii_lit)),
                 ".asciiz" => Ok(InstructionParser::pseudo(asciiz_lit)),
                 ".text" => Ok(InstructionParser::pseudo(|i| segment(i, Segment::Text))),
                 ".data" => Ok(InstructionParser::pseudo(|i| segment(i, Segment::Data))),
+                ".ktext" => Ok(InstructionParser::pseudo(|i| segment(i, Segment::KText))),
+                ".kdata" => Ok(InstructionParser::pseudo(|i| segment(i, Segment::KData))),
+                ".align" => Ok(InstructionParser::pseudo_with_constants(align)),
+                ".globl" => Ok(InstructionParser::pseudo(globl)),
+                ".extern" => Ok(InstructionParser::pseudo_with_constants(external)),
+                ".eqv" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    symbolic_constant(i, false, c)
+                })),
+                ".set" => Ok(InstructionParser::pseudo_with_constants(|i, c| {
+                    symbolic_constant(i, true, c)
+                })),
 
-                "mul" => Ok(InstructionParser::new(Opcode::Op(0x1c), r_type)),
+                "mul" => Ok(InstructionParser::new(Opcode::Special2(0x02), r_type)),
 
-                "add.s" => Ok(InstructionParser::new(Opcode::Funct(0x11), r_type)),
+                "add.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x00 },
+                    float_r_type,
+                )),
+                "sub.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x01 },
+                    float_r_type,
+                )),
+                "mul.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x02 },
+                    float_r_type,
+                )),
+                "div.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x03 },
+                    float_r_type,
+                )),
+                "sqrt.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x04 },
+                    float_unary,
+                )),
+                "abs.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x05 },
+                    float_unary,
+                )),
+                "mov.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x06 },
+                    float_unary,
+                )),
+                "neg.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x07 },
+                    float_unary,
+                )),
+                "round.w.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x0c },
+                    float_unary,
+                )),
+                "trunc.w.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x0d },
+                    float_unary,
+                )),
+                "ceil.w.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x0e },
+                    float_unary,
+                )),
+                "floor.w.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x0f },
+                    float_unary,
+                )),
+                "cvt.w.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x24 },
+                    float_unary,
+                )),
+                "cvt.s.w" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x14, funct: 0x20 },
+                    float_unary,
+                )),
+                "c.eq.s" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x32 },
+                    float_compare,
+                )),
+                "c.lt.s" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x3c },
+                    float_compare,
+                )),
+                "c.le.s" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x3e },
+                    float_compare,
+                )),
+                "add.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x00 },
+                    float_r_type,
+                )),
+                "sub.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x01 },
+                    float_r_type,
+                )),
+                "mul.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x02 },
+                    float_r_type,
+                )),
+                "div.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x03 },
+                    float_r_type,
+                )),
+                "sqrt.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x04 },
+                    float_unary,
+                )),
+                "abs.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x05 },
+                    float_unary,
+                )),
+                "mov.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x06 },
+                    float_unary,
+                )),
+                "neg.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x07 },
+                    float_unary,
+                )),
+                "round.w.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x0c },
+                    float_unary,
+                )),
+                "trunc.w.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x0d },
+                    float_unary,
+                )),
+                "ceil.w.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x0e },
+                    float_unary,
+                )),
+                "floor.w.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x0f },
+                    float_unary,
+                )),
+                "cvt.s.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x20 },
+                    float_unary,
+                )),
+                "cvt.w.d" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x24 },
+                    float_unary,
+                )),
+                "c.eq.d" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x32 },
+                    float_compare,
+                )),
+                "c.lt.d" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x3c },
+                    float_compare,
+                )),
+                "c.le.d" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x11, funct: 0x3e },
+                    float_compare,
+                )),
+                "cvt.d.s" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x10, funct: 0x21 },
+                    float_unary,
+                )),
+                "cvt.d.w" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x14, funct: 0x21 },
+                    float_unary,
+                )),
+                "bc1f" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x08, funct: 0 },
+                    float_branch,
+                )),
+                "bc1t" => Ok(InstructionParser::with_constants(
+                    Opcode::Cop1 { fmt: 0x08, funct: 1 },
+                    float_branch,
+                )),
+                "mfc1" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x00, funct: 0 },
+                    cop1_move,
+                )),
+                "mtc1" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x04, funct: 0 },
+                    cop1_move,
+                )),
+                "cfc1" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x02, funct: 0 },
+                    cop1_control_move,
+                )),
+                "ctc1" => Ok(InstructionParser::new(
+                    Opcode::Cop1 { fmt: 0x06, funct: 0 },
+                    cop1_control_move,
+                )),
+                "lwc1" => Ok(InstructionParser::with_constants(Opcode::Op(0x31), load_type)),
+                "swc1" => Ok(InstructionParser::with_constants(Opcode::Op(0x39), load_type)),
+                "ldc1" => Ok(InstructionParser::with_constants(Opcode::Op(0x35), load_type)),
+                "sdc1" => Ok(InstructionParser::with_constants(Opcode::Op(0x3d), load_type)),
 
-                "mov.s" => Ok(InstructionParser::pseudo(mov_s_ins)),
-                "li.s" => Ok(InstructionParser::pseudo(li_ins)),
+                "li.s" => Ok(InstructionParser::pseudo(li_s_ins)),
                 _ => Err(()),
             },
         ),
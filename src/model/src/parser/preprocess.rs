@@ -0,0 +1,569 @@
+//! Expands includes and macros before a script is parsed
+//!
+//! `.include "file.s"` is replaced by the lines of the file, which is found relative to the file
+//! including it.
+//!
+//! Macros are defined like in MARS and can be called once they are defined
+//! ```text
+//! .macro print_int(%value)
+//!     li $v0, 1
+//!     move $a0, %value
+//!     syscall
+//! .end_macro
+//!
+//!     print_int($t0)
+//!     print_int $t1
+//! ```
+//! Macros can be overloaded by their number of parameters. Labels defined inside a macro are
+//! renamed for every expansion, so a macro that defines a label can be called more than once.
+//! They become local labels like `.done_M1`, which don't start a new scope for the local labels
+//! of the code calling the macro.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::diagnostic::{code_span, Diagnostic};
+use super::label::identifier;
+
+/// How deep macros can call other macros and files can include other files, which stops a
+/// macro that calls itself or a file that includes itself
+const MAX_DEPTH: usize = 16;
+
+struct Macro {
+    params: Vec<String>,
+    /// Source line of each line of the body, with its text
+    body: Vec<(usize, String)>,
+    /// Source line of the `.macro` directive
+    line: usize,
+}
+
+/// A macro or an included file a line was expanded from
+#[derive(Debug, Clone)]
+enum Frame {
+    /// A line of a macro, with its source line in the file defining the macro
+    Macro { name: String, line: usize },
+    /// A line of an included file
+    Include { file: String, line: usize },
+}
+
+/// Where a line of the expanded script comes from
+#[derive(Debug, Clone)]
+struct Origin {
+    /// Source line in the script, which is the outermost call or include for expanded lines
+    line: usize,
+    /// What the line was expanded from, innermost first
+    frames: Vec<Frame>,
+}
+
+impl Origin {
+    fn new(line: usize) -> Self {
+        Self {
+            line,
+            frames: vec![],
+        }
+    }
+
+    /// The origin of a line expanded from `frame` on this line
+    fn within(&self, frame: Frame) -> Self {
+        let mut origin = self.clone();
+        origin.frames.insert(0, frame);
+        origin
+    }
+
+    /// Creates a diagnostic for `span` of the expanded line
+    ///
+    /// Lines written in the script keep their span, expanded lines point at the call or include
+    /// in the script and note where they come from instead.
+    fn diagnostic(
+        &self,
+        script: &str,
+        span: Range<usize>,
+        message: String,
+        mut notes: Vec<String>,
+    ) -> Diagnostic {
+        let start = line_start(script, self.line);
+        let text = script[start..].split('\n').next().unwrap_or("");
+        let span = if self.frames.is_empty() {
+            span.start.min(text.len())..span.end.min(text.len())
+        } else {
+            code_span(text)
+        };
+        for frame in &self.frames {
+            let note = match frame {
+                Frame::Macro { name, line } => {
+                    format!("expanded from line {} in macro `{name}`", line + 1)
+                }
+                Frame::Include { file, line } => {
+                    format!("in line {} of the included file `{file}`", line + 1)
+                }
+            };
+            // a macro that calls itself would repeat the same line
+            if notes.last() != Some(&note) {
+                notes.push(note);
+            }
+        }
+        Diagnostic::error(script, start + span.start..start + span.end, message, notes)
+    }
+}
+
+/// A script with its includes and macros expanded, which remembers where each of its lines
+/// comes from
+pub struct Expansion {
+    pub text: String,
+    origins: Vec<Origin>,
+    includes: Vec<PathBuf>,
+}
+
+impl Expansion {
+    /// The source line a line of the expanded script comes from
+    pub fn source_line(&self, line: usize) -> usize {
+        self.origins.get(line).map_or(line, |origin| origin.line)
+    }
+
+    /// The files that were included
+    pub fn includes(&self) -> &[PathBuf] {
+        &self.includes
+    }
+
+    /// Moves a diagnostic of the expanded script to where it comes from in `script`
+    pub fn locate(&self, script: &str, diagnostic: Diagnostic) -> Diagnostic {
+        let Some(origin) = self.origins.get(diagnostic.line) else {
+            return diagnostic;
+        };
+        let start = line_start(&self.text, diagnostic.line);
+        Diagnostic {
+            severity: diagnostic.severity,
+            ..origin.diagnostic(
+                script,
+                diagnostic.span.start - start..diagnostic.span.end - start,
+                diagnostic.message,
+                diagnostic.notes,
+            )
+        }
+    }
+}
+
+/// Expands every include and macro call in a script
+///
+/// Included files are found relative to `dir`, the directory of the script. Problems with
+/// includes or the definitions and calls of macros are reported as diagnostics and the lines
+/// with them are left out.
+pub fn preprocess(script: &str, dir: &Path) -> (Expansion, Vec<Diagnostic>) {
+    let mut expander = Expander {
+        script,
+        macros: HashMap::new(),
+        expansions: 0,
+        expansion: Expansion {
+            text: String::new(),
+            origins: vec![],
+            includes: vec![],
+        },
+        diagnostics: vec![],
+    };
+    expander.lines(script, dir, &Origin::new, 0);
+
+    (expander.expansion, expander.diagnostics)
+}
+
+struct Expander<'a> {
+    script: &'a str,
+    /// Macros by their name and number of parameters
+    macros: HashMap<(String, usize), Macro>,
+    /// Number of macro calls expanded so far, which makes the labels of each one unique
+    expansions: usize,
+    expansion: Expansion,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Expander<'_> {
+    /// Expands the lines of a script or an included file
+    ///
+    /// `origin` gives where each line comes from and `depth` is how many files include it.
+    fn lines(&mut self, text: &str, dir: &Path, origin: &dyn Fn(usize) -> Origin, depth: usize) {
+        let mut lines = text.lines().enumerate();
+        while let Some((i, text)) = lines.next() {
+            match directive(text) {
+                Some(".macro") => {
+                    let mut body = vec![];
+                    let mut closed = false;
+                    for (j, text) in lines.by_ref() {
+                        match directive(text) {
+                            Some(".end_macro") => {
+                                closed = true;
+                                break;
+                            }
+                            Some(".macro") => self.error(
+                                &origin(j),
+                                code_span(text),
+                                "Macros can't be defined inside other macros".to_string(),
+                                "end the macro above with `.end_macro` first".to_string(),
+                            ),
+                            _ => body.push((j, text.to_string())),
+                        }
+                    }
+                    if !closed {
+                        self.error(
+                            &origin(i),
+                            code_span(text),
+                            "Macro is missing `.end_macro`".to_string(),
+                            "end the macro with `.end_macro` on its own line".to_string(),
+                        );
+                    }
+                    self.define(origin(i), i, text, body);
+                }
+                Some(".end_macro") => self.error(
+                    &origin(i),
+                    code_span(text),
+                    "`.end_macro` without a `.macro`".to_string(),
+                    "start the macro with `.macro <name>(<parameters>)`".to_string(),
+                ),
+                Some(".include") => self.include(text, dir, origin(i), depth),
+                _ => self.expand(text, origin(i), 0),
+            }
+        }
+    }
+
+    /// Expands the lines of the file included by `.include "<file>"`
+    fn include(&mut self, text: &str, dir: &Path, origin: Origin, depth: usize) {
+        let code = strip_comment(text)
+            .trim()
+            .trim_start_matches(".include")
+            .trim();
+        let Some(file) = code
+            .strip_prefix('"')
+            .and_then(|rest| rest.strip_suffix('"'))
+        else {
+            return self.error(
+                &origin,
+                code_span(text),
+                "Expected the path of the file in quotes".to_string(),
+                "files are included like `.include \"file.s\"`".to_string(),
+            );
+        };
+        if depth == MAX_DEPTH {
+            return self.error(
+                &origin,
+                code_span(text),
+                format!("`{file}` is included too deeply"),
+                "check that the file does not include itself".to_string(),
+            );
+        }
+
+        let path = dir.join(file);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents.replace("\r\n", "\n"),
+            Err(error) => {
+                return self.error(
+                    &origin,
+                    code_span(text),
+                    format!("Can't read `{file}`: {error}"),
+                    format!("the path is relative to {}", dir.display()),
+                )
+            }
+        };
+        let dir = path.parent().unwrap_or(dir).to_path_buf();
+        self.expansion
+            .includes
+            .push(path.canonicalize().unwrap_or(path));
+
+        let inner = |line| {
+            origin.within(Frame::Include {
+                file: file.to_string(),
+                line,
+            })
+        };
+        self.lines(&contents, &dir, &inner, depth + 1);
+    }
+
+    fn error(&mut self, origin: &Origin, span: Range<usize>, message: String, note: String) {
+        let diagnostic = origin.diagnostic(self.script, span, message, vec![note]);
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn push(&mut self, text: &str, origin: Origin) {
+        self.expansion.text.push_str(text);
+        self.expansion.text.push('\n');
+        self.expansion.origins.push(origin);
+    }
+
+    /// Defines a macro from its `.macro <name>(<parameters>)` line, which is source line `line`
+    /// of its file, and its body
+    fn define(&mut self, origin: Origin, line: usize, text: &str, body: Vec<(usize, String)>) {
+        let header = text.split('#').next().unwrap_or("").trim_start();
+        let header = header.trim_start_matches(".macro").trim_start();
+        let (rest, name) = match identifier(header) {
+            Ok(parsed) => parsed,
+            Err(_) => {
+                return self.error(
+                    &origin,
+                    code_span(text),
+                    "Expected the name of the macro".to_string(),
+                    "macros are defined like `.macro <name>(%a, %b)`".to_string(),
+                )
+            }
+        };
+
+        let rest = rest.trim();
+        let rest = match rest.strip_prefix('(') {
+            Some(inner) => inner.strip_suffix(')').unwrap_or(inner),
+            None => rest,
+        };
+        let mut params = vec![];
+        for param in rest
+            .split([',', ' ', '\t'])
+            .filter(|param| !param.is_empty())
+        {
+            match param.strip_prefix('%').map(identifier) {
+                Some(Ok(("", name))) => params.push(name.to_string()),
+                _ => {
+                    return self.error(
+                        &origin,
+                        code_span(text),
+                        format!("Invalid macro parameter `{param}`"),
+                        "parameters are written like `%name`".to_string(),
+                    )
+                }
+            }
+        }
+
+        let key = (name.to_string(), params.len());
+        if let Some(first) = self.macros.get(&key) {
+            let message = format!(
+                "Macro `{name}` with {} parameter(s) is already defined on line {}",
+                params.len(),
+                first.line + 1
+            );
+            return self.error(
+                &origin,
+                code_span(text),
+                message,
+                "rename one of the macros".to_string(),
+            );
+        }
+        self.macros.insert(key, Macro { params, body, line });
+    }
+
+    /// Adds a line to the expansion, expanding it if it calls a macro
+    fn expand(&mut self, text: &str, origin: Origin, depth: usize) {
+        // a label can come before the call
+        let code = text.trim_start();
+        let (label, call) = match identifier(code) {
+            Ok((rest, _)) if rest.starts_with(':') => {
+                let end = text.len() - rest.len() + 1;
+                (&text[..end], &text[end..])
+            }
+            _ => ("", text),
+        };
+
+        let Some((name, args)) = self.call(call, &origin) else {
+            return self.push(text, origin);
+        };
+        if !label.is_empty() {
+            self.push(label, origin.clone());
+        }
+        let Some(args) = args else {
+            // the problem with the call is already reported
+            return;
+        };
+
+        let Some(mac) = self.macros.get(&(name.to_string(), args.len())) else {
+            let counts: Vec<String> = self
+                .macros
+                .keys()
+                .filter(|(other, _)| other == name)
+                .map(|(_, count)| count.to_string())
+                .collect();
+            return self.error(
+                &origin,
+                code_span(text),
+                format!("Macro `{name}` does not take {} argument(s)", args.len()),
+                format!("it takes {} argument(s)", counts.join(" or ")),
+            );
+        };
+        if depth == MAX_DEPTH {
+            return self.error(
+                &origin,
+                code_span(text),
+                format!("Macro `{name}` is expanded too deeply"),
+                "check that the macro does not call itself".to_string(),
+            );
+        }
+
+        self.expansions += 1;
+        let suffix = format!("_M{}", self.expansions);
+        let params: HashMap<&str, &str> = mac
+            .params
+            .iter()
+            .map(String::as_str)
+            .zip(args.iter().copied())
+            .collect();
+        let labels: HashSet<&str> = mac
+            .body
+            .iter()
+            .filter_map(|(_, text)| match identifier(text.trim_start()) {
+                Ok((rest, label)) if rest.starts_with(':') => Some(label),
+                _ => None,
+            })
+            .collect();
+        let body: Vec<(usize, String)> = mac
+            .body
+            .iter()
+            .map(|(line, text)| (*line, substitute(text, &params, &labels, &suffix)))
+            .collect();
+
+        for (line, text) in body {
+            let name = name.to_string();
+            self.expand(&text, origin.within(Frame::Macro { name, line }), depth + 1);
+        }
+    }
+
+    /// Finds the name and arguments of a macro call
+    ///
+    /// The arguments are `None` when they could not be read, which is reported.
+    fn call<'t>(
+        &mut self,
+        text: &'t str,
+        origin: &Origin,
+    ) -> Option<(&'t str, Option<Vec<&'t str>>)> {
+        let code = strip_comment(text).trim();
+        let (rest, name) = identifier(code).ok()?;
+        let called = self.macros.keys().any(|(other, _)| other == name);
+        // names of macros can't run into the rest of the word, like `add` in `add.s`
+        if !called || !(rest.is_empty() || rest.starts_with([' ', '\t', '('])) {
+            return None;
+        }
+
+        let rest = rest.trim_start();
+        let args = match rest.strip_prefix('(') {
+            Some(inner) => match inner.strip_suffix(')') {
+                Some(inner) => split_args(inner),
+                None => {
+                    self.error(
+                        origin,
+                        code_span(text),
+                        "Expected ')' to close the arguments of the macro".to_string(),
+                        "macros are called like `<name>(<arguments>)`".to_string(),
+                    );
+                    return Some((name, None));
+                }
+            },
+            None => split_args(rest),
+        };
+        Some((name, Some(args)))
+    }
+}
+
+/// Finds the directive a line starts with
+fn directive(text: &str) -> Option<&str> {
+    let word = strip_comment(text).split_whitespace().next()?;
+    let word = word.split('(').next().unwrap_or(word);
+    [".macro", ".end_macro", ".include"]
+        .into_iter()
+        .find(|directive| *directive == word)
+}
+
+/// Removes a comment from the end of a line
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            // an escaped quote does not end the string
+            _ if escaped => escaped = false,
+            ('\\', Some(_)) => escaped = true,
+            ('#', None) => return &text[..i],
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            _ => {}
+        }
+    }
+    text
+}
+
+/// Splits the arguments of a macro call on the commas or spaces between them
+fn split_args(text: &str) -> Vec<&str> {
+    let mut args = vec![];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            _ if escaped => escaped = false,
+            ('\\', Some(_)) => escaped = true,
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('(', None) => depth += 1,
+            (')', None) => depth -= 1,
+            (',' | ' ' | '\t', None) if depth == 0 => {
+                args.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    args.push(&text[start..]);
+    args.into_iter().filter(|arg| !arg.is_empty()).collect()
+}
+
+/// Replaces the parameters in a line of a macro with their arguments and renames its labels
+fn substitute(
+    text: &str,
+    params: &HashMap<&str, &str>,
+    labels: &HashSet<&str>,
+    suffix: &str,
+) -> String {
+    let code = strip_comment(text);
+    let mut out = String::new();
+    let mut rest = code;
+    let mut quote = None;
+    while let Some(c) = rest.chars().next() {
+        // the character before decides if an identifier starts here
+        let previous = out.chars().last();
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            ('\\', Some(_)) => {
+                let escaped = rest[1..].chars().next().map_or(0, char::len_utf8);
+                out.push_str(&rest[..1 + escaped]);
+                rest = &rest[1 + escaped..];
+                continue;
+            }
+            (c, Some(open)) if c == open => quote = None,
+            ('%', None) => {
+                if let Ok((after, name)) = identifier(&rest[1..]) {
+                    if let Some(arg) = params.get(name) {
+                        out.push_str(arg);
+                        rest = after;
+                        continue;
+                    }
+                }
+            }
+            (_, None) if !previous.is_some_and(|p| p.is_alphanumeric() || "_$%.".contains(p)) => {
+                if let Ok((after, name)) = identifier(rest) {
+                    // renamed labels are local, so they belong to the label before the call
+                    if labels.contains(name) {
+                        out.push('.');
+                        out.push_str(name);
+                        out.push_str(suffix);
+                    } else {
+                        out.push_str(name);
+                    }
+                    rest = after;
+                    continue;
+                }
+            }
+            _ => {}
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out.push_str(&text[code.len()..]);
+    out
+}
+
+/// Finds where a line starts in a script
+fn line_start(script: &str, line: usize) -> usize {
+    script.split_inclusive('\n').take(line).map(str::len).sum()
+}
@@ -0,0 +1,344 @@
+//! MARS compatible pseudo instructions, which expand into real instructions that use $at as a
+//! scratch register
+
+use nom::{
+    branch::alt,
+    combinator::{map, opt},
+    error::{context, VerboseError},
+    sequence::preceded,
+    IResult,
+};
+
+use crate::parser::{self, model::Opcode};
+
+use crate::Register;
+use crate::Register::{AT, ZERO};
+
+use super::{
+    instruction::{
+        fits_immediate, memory_operand, separator, word_expression, Offset, ParserOutput,
+    },
+    model::{Imm, Instruction, Line},
+    Constants, Expr,
+};
+
+/// Builds an R-type instruction selected by its funct field
+fn special(funct: u8, rd: Register, rs: Register, rt: Register) -> Instruction {
+    Instruction::R {
+        op: Opcode::Funct(funct),
+        rd,
+        rs,
+        rt,
+        shamt: 0,
+    }
+}
+
+/// Builds an R-type shift by a constant amount
+fn shift(funct: u8, rd: Register, rt: Register, shamt: u32) -> Instruction {
+    Instruction::R {
+        op: Opcode::Funct(funct),
+        rd,
+        rs: ZERO,
+        rt,
+        shamt,
+    }
+}
+
+/// Builds an I-type instruction selected by its opcode
+fn immediate(op: u8, rt: Register, rs: Register, imm: Imm) -> Instruction {
+    Instruction::I {
+        op: Opcode::Op(op),
+        rt,
+        rs,
+        imm,
+    }
+}
+
+/// Loads a 32 bit constant into a register with the shortest sequence of instructions
+pub fn load_immediate(rt: Register, value: i64) -> Vec<Instruction> {
+    let value = value as u32;
+    if fits_immediate(value as i32 as i64) {
+        vec![immediate(0x09, rt, ZERO, Imm::Value(value as i32 as i64))] // addiu
+    } else if value <= 0xFFFF {
+        vec![immediate(0x0d, rt, ZERO, Imm::Value(value as i64))] // ori
+    } else if value & 0xFFFF == 0 {
+        vec![immediate(0x0f, rt, ZERO, Imm::Value((value >> 16) as i64))] // lui
+    } else {
+        vec![
+            immediate(0x0f, AT, ZERO, Imm::Value((value >> 16) as i64)), // lui
+            immediate(0x0d, rt, AT, Imm::Value((value & 0xFFFF) as i64)), // ori
+        ]
+    }
+}
+
+/// Loads a value that depends on a label into a register through $at
+fn load_address(rt: Register, expr: Expr) -> Vec<Instruction> {
+    vec![
+        immediate(0x0f, AT, ZERO, Imm::Expr(Expr::high(expr.clone()))), // lui
+        immediate(0x09, rt, AT, Imm::Expr(Expr::low(expr))),            // addiu
+    ]
+}
+
+/// Parses the last operand of a pseudo instruction, which is either a register or a constant
+/// loaded into $at by the returned instructions
+fn source_operand(
+    constants: &Constants,
+) -> impl Fn(&str) -> IResult<&str, (Register, Vec<Instruction>), VerboseError<&str>> + '_ {
+    move |input| {
+        alt((
+            map(parser::register, |reg| (reg, vec![])),
+            map(word_expression(constants), |expr| match expr {
+                Expr::Value(value) => (AT, load_immediate(AT, value)),
+                expr => (AT, load_address(AT, expr)),
+            }),
+        ))(input)
+    }
+}
+
+/// Parses pseudo instructions using the format
+/// `<OP> <rd>, <rs>`
+pub fn two_operands<F>(input: &str, expand: F) -> ParserOutput<'_>
+where
+    F: Fn(Register, Register) -> Vec<Instruction>,
+{
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    Ok((input, Line::Instruction(expand(rd, rs))))
+}
+
+/// Parses pseudo instructions using the format
+/// `<OP> <rd>, <rs>, <rt|imm>` where an immediate is loaded into $at
+pub fn three_operands<'a, F>(input: &'a str, constants: &Constants, expand: F) -> ParserOutput<'a>
+where
+    F: Fn(Register, Register, Register) -> Vec<Instruction>,
+{
+    let (input, rd) = context("Destination Register", parser::register)(input)?;
+    let (input, rs) = context("Source Register", preceded(separator, parser::register))(input)?;
+    let (input, (rt, mut ins)) =
+        context("Target Register", preceded(separator, source_operand(constants)))(input)?;
+    ins.extend(expand(rd, rs, rt));
+    Ok((input, Line::Instruction(ins)))
+}
+
+/// Parses li and la instructions, which load a constant or an address
+/// `la <rt>, <address>` where the address takes any of the forms accepted by loads and stores
+pub fn la_ins<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rt) = context("Destination Register", parser::register)(input)?;
+    let (input, (offset, base)) =
+        context("Expected address", preceded(separator, memory_operand(constants)))(input)?;
+
+    let ins = match (offset, base) {
+        (Offset::Value(value), Some(rs)) if fits_immediate(value) => {
+            vec![immediate(0x09, rt, rs, Imm::Value(value))] // addiu
+        }
+        (offset, base) => {
+            // the constant goes through $at when a base register still has to be added
+            let target = if base.is_some() { AT } else { rt };
+            let mut ins = match offset {
+                Offset::Value(value) => load_immediate(target, value),
+                Offset::Expr(expr) => load_address(target, expr),
+            };
+            if let Some(rs) = base {
+                ins.push(special(0x21, rt, AT, rs)); // addu
+            }
+            ins
+        }
+    };
+    Ok((input, Line::Instruction(ins)))
+}
+
+/// Expands the three operand division into rd, which traps when dividing by zero like the code
+/// compilers emit
+fn divide(
+    rd: Register,
+    rs: Register,
+    rt: Register,
+    unsigned: bool,
+    remainder: bool,
+) -> Vec<Instruction> {
+    vec![
+        special(0x34, ZERO, rt, ZERO),                                // teq
+        special(if unsigned { 0x1b } else { 0x1a }, ZERO, rs, rt),    // divu or div
+        special(if remainder { 0x10 } else { 0x12 }, rd, ZERO, ZERO), // mfhi or mflo
+    ]
+}
+
+/// Parses div and divu, which divide into hi and lo with two operands or into rd with three
+/// `<OP> <rs>, <rt>` or `<OP> <rd>, <rs>, <rt|imm>`
+pub fn div_ins<'a>(input: &'a str, unsigned: bool, constants: &Constants) -> ParserOutput<'a> {
+    let (input, first) = context("Source Register", parser::register)(input)?;
+    let (input, second) = context("Target Register", preceded(separator, parser::register))(input)?;
+    let (input, third) = opt(preceded(separator, source_operand(constants)))(input)?;
+    let ins = match third {
+        Some((rt, mut ins)) => {
+            ins.extend(divide(first, second, rt, unsigned, false));
+            ins
+        }
+        None => vec![special(
+            if unsigned { 0x1b } else { 0x1a },
+            ZERO,
+            first,
+            second,
+        )],
+    };
+    Ok((input, Line::Instruction(ins)))
+}
+
+/// Parses rem and remu
+/// `<OP> <rd>, <rs>, <rt|imm>`
+pub fn rem_ins<'a>(input: &'a str, unsigned: bool, constants: &Constants) -> ParserOutput<'a> {
+    three_operands(input, constants, |rd, rs, rt| divide(rd, rs, rt, unsigned, true))
+}
+
+/// Parses mulo and mulou, which trap when the product doesn't fit in rd
+/// `<OP> <rd>, <rs>, <rt|imm>`
+pub fn mulo_ins<'a>(input: &'a str, unsigned: bool, constants: &Constants) -> ParserOutput<'a> {
+    three_operands(input, constants, |rd, rs, rt| {
+        if unsigned {
+            vec![
+                special(0x19, ZERO, rs, rt),   // multu
+                special(0x10, AT, ZERO, ZERO), // mfhi
+                special(0x36, ZERO, AT, ZERO), // tne
+                special(0x12, rd, ZERO, ZERO), // mflo
+            ]
+        } else {
+            // the upper word has to repeat the sign of the lower word
+            vec![
+                special(0x18, ZERO, rs, rt),   // mult
+                special(0x10, AT, ZERO, ZERO), // mfhi
+                special(0x12, rd, ZERO, ZERO), // mflo
+                shift(0x03, rd, rd, 31),       // sra
+                special(0x36, ZERO, AT, rd),   // tne
+                special(0x12, rd, ZERO, ZERO), // mflo
+            ]
+        }
+    })
+}
+
+/// Comparison computed by a set pseudo instruction
+#[derive(Clone, Copy)]
+pub enum SetCond {
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    GreaterThan,
+    LessEqual,
+}
+
+/// Parses the set pseudo instructions seq, sne, sge, sgt, sle and their unsigned variants
+/// `<OP> <rd>, <rs>, <rt|imm>`
+pub fn set_ins<'a>(
+    input: &'a str,
+    cond: SetCond,
+    unsigned: bool,
+    constants: &Constants,
+) -> ParserOutput<'a> {
+    let slt = if unsigned { 0x2b } else { 0x2a };
+    three_operands(input, constants, |rd, rs, rt| match cond {
+        SetCond::Equal => vec![
+            special(0x23, rd, rs, rt),              // subu
+            immediate(0x0b, rd, rd, Imm::Value(1)), // sltiu
+        ],
+        SetCond::NotEqual => vec![
+            special(0x23, rd, rs, rt),   // subu
+            special(0x2b, rd, ZERO, rd), // sltu
+        ],
+        SetCond::GreaterEqual => vec![
+            special(slt, rd, rs, rt),
+            immediate(0x0e, rd, rd, Imm::Value(1)), // xori
+        ],
+        SetCond::GreaterThan => vec![special(slt, rd, rt, rs)],
+        SetCond::LessEqual => vec![
+            special(slt, rd, rt, rs),
+            immediate(0x0e, rd, rd, Imm::Value(1)), // xori
+        ],
+    })
+}
+
+/// Parses the not pseudo instruction
+pub fn not_ins(input: &str) -> ParserOutput<'_> {
+    two_operands(input, |rd, rs| vec![special(0x27, rd, rs, ZERO)]) // nor
+}
+
+/// Parses neg and negu
+pub fn neg_ins(input: &str, unsigned: bool) -> ParserOutput<'_> {
+    two_operands(input, |rd, rs| {
+        vec![special(if unsigned { 0x23 } else { 0x22 }, rd, ZERO, rs)] // subu or sub
+    })
+}
+
+/// Parses the abs pseudo instruction, which flips the bits of negative values through a mask of
+/// their sign and adds one
+pub fn abs_ins(input: &str) -> ParserOutput<'_> {
+    two_operands(input, |rd, rs| {
+        vec![
+            shift(0x03, AT, rs, 31),   // sra
+            special(0x26, rd, rs, AT), // xor
+            special(0x23, rd, rd, AT), // subu
+        ]
+    })
+}
+
+/// Resolves the memory operand of an unaligned access into a base register and an offset that
+/// still fits once `extra` is added, computing the full address in $at when it doesn't
+fn unaligned_base(
+    offset: Offset,
+    base: Option<Register>,
+    extra: i64,
+) -> (Vec<Instruction>, Register, i64) {
+    let mut ins = match offset {
+        Offset::Value(value) if fits_immediate(value) && fits_immediate(value + extra) => {
+            return (vec![], base.unwrap_or(ZERO), value);
+        }
+        Offset::Value(value) => load_immediate(AT, value),
+        Offset::Expr(expr) => load_address(AT, expr),
+    };
+    if let Some(rs) = base {
+        ins.push(special(0x21, AT, AT, rs)); // addu
+    }
+    (ins, AT, 0)
+}
+
+/// Parses unaligned word loads and stores, which are split into their left and right parts
+/// `<OP> <rt>, <address>`
+pub fn unaligned_word<'a>(input: &'a str, store: bool, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, (offset, base)) =
+        context("Expected address", preceded(separator, memory_operand(constants)))(input)?;
+    let (mut ins, base, offset) = unaligned_base(offset, base, 3);
+
+    if store {
+        ins.push(immediate(0x2a, rt, base, Imm::Value(offset + 3))); // swl
+        ins.push(immediate(0x2e, rt, base, Imm::Value(offset))); // swr
+    } else {
+        // loading into the base register would move it between the two halves
+        let target = if rt == base { AT } else { rt };
+        ins.push(immediate(0x22, target, base, Imm::Value(offset + 3))); // lwl
+        ins.push(immediate(0x26, target, base, Imm::Value(offset))); // lwr
+        if target != rt {
+            ins.push(special(0x21, rt, AT, ZERO)); // addu
+        }
+    }
+    Ok((input, Line::Instruction(ins)))
+}
+
+/// Parses unaligned half word loads, which combine two byte loads
+/// `ulh <rt>, <address>`
+pub fn ulh_ins<'a>(input: &'a str, constants: &Constants) -> ParserOutput<'a> {
+    let (input, rt) = context("Target Register", parser::register)(input)?;
+    let (input, (offset, base)) =
+        context("Expected address", preceded(separator, memory_operand(constants)))(input)?;
+    let (mut ins, base, offset) = unaligned_base(offset, base, 1);
+
+    // both bytes are loaded before the base register is overwritten
+    let low = immediate(0x24, AT, base, Imm::Value(offset)); // lbu
+    let high = immediate(0x20, rt, base, Imm::Value(offset + 1)); // lb
+    if base == AT {
+        ins.extend([high, low]);
+    } else {
+        ins.extend([low, high]);
+    }
+    ins.push(shift(0x00, rt, rt, 8)); // sll
+    ins.push(special(0x25, rt, rt, AT)); // or
+    Ok((input, Line::Instruction(ins)))
+}
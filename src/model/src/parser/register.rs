@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 
 use nom::{
     bytes::complete::{tag, take_till},
-    combinator::map_res,
+    combinator::{map_opt, map_res},
     error::{context, VerboseError},
     IResult,
 };
@@ -14,7 +14,11 @@ pub fn register_name(input: &str) -> IResult<&str, Register, VerboseError<&str>>
         "Unknown register",
         map_res(
             take_till(|c: char| c.is_whitespace() || c == ',' || c == ')' || c == '#'),
-            |name: &str| Register::try_from(name),
+            |name: &str| match name.parse::<u32>() {
+                // registers can also be named by number
+                Ok(id) if id < 32 => Ok(Register::from(id)),
+                _ => Register::try_from(name),
+            },
         ),
     )(input)
 }
@@ -24,3 +28,27 @@ pub fn register(input: &str) -> IResult<&str, Register, VerboseError<&str>> {
     let (input, reg) = register_name(input)?;
     Ok((input, reg))
 }
+
+/// Parses a coprocessor 0 register by name or by number, e.g. `$status` or `$12`
+pub fn cop0_register(input: &str) -> IResult<&str, Register, VerboseError<&str>> {
+    context(
+        "Unknown coprocessor 0 register",
+        map_opt(register, |reg| match reg.cop0_number() {
+            Some(_) => Some(reg),
+            None if reg.id() < 32 => Register::from_cop0(reg.id()),
+            None => None,
+        }),
+    )(input)
+}
+
+/// Parses a floating point control register by name or by number, e.g. `$fcsr` or `$31`
+pub fn fcr_register(input: &str) -> IResult<&str, Register, VerboseError<&str>> {
+    context(
+        "Unknown floating point control register",
+        map_opt(register, |reg| match reg.fcr_number() {
+            Some(_) => Some(reg),
+            None if reg.id() < 32 => Register::from_fcr(reg.id()),
+            None => None,
+        }),
+    )(input)
+}
@@ -1,9 +1,13 @@
 use crate::stages;
-use crate::stages::execute::IdEx;
+use crate::stages::execute::{op_ctrl::OP_COP1_D, IdEx};
 use crate::stages::inputs::*;
 use crate::stages::writeback::PipelineOutput;
 use crate::syscall::{handle_syscall, Syscall};
-use crate::{Memory, Register, RegisterFile};
+use crate::model::KTEXT_BASE;
+use crate::{
+    record_fp_cause, AccessWidth, Exception, Memory, Register, RegisterFile, RuntimeError,
+    CAUSE_BD, CAUSE_EXC_CODE, CAUSE_IP, CAUSE_TIMER, STATUS_EXL, STATUS_IE, STATUS_IM,
+};
 
 use anyhow::Result;
 
@@ -14,8 +18,9 @@ use anyhow::Result;
 pub fn _single_cycle(pc: &mut u32, regs: &mut RegisterFile<u32>, mem: &mut Memory) -> Option<Syscall> {
     // should never forward
     let fwd_unit = ForwardingUnit {
-        ex_mem: (false, Register::ZERO, (0, 0), false),
-        mem_wb: (false, Register::ZERO, (0, 0), false),
+        ex_mem: (false, Register::ZERO, (0, 0), false, false),
+        mem_wb: (false, Register::ZERO, (0, 0), false, false),
+        fcsr: (None, None),
     };
 
     let if_id = stages::fetch(pc, mem);
@@ -42,12 +47,129 @@ pub struct PipelineState {
     pub ex_mem: ExMem,
     pub mem_wb: MemWb,
     pub pipe_out: PipelineOutput,
+    /// The pc of the last taken branch or jump until the second of its delay slots finishes
+    pub branch_shadow: Option<u32>,
 }
 
+/// Results of the instructions in the memory and writeback stages that can be forwarded.
+///
+/// Each tuple holds (reg_write, write_register, result, use_hilo, write_pair).
 #[derive(Debug, Clone, Copy)]
 pub struct ForwardingUnit {
-    pub ex_mem: (bool, Register, (u32, u32), bool),
-    pub mem_wb: (bool, Register, (u32, u32), bool),
+    pub ex_mem: (bool, Register, (u32, u32), bool, bool),
+    pub mem_wb: (bool, Register, (u32, u32), bool, bool),
+    /// FCSR updated alongside the result of a floating point operation in (ex_mem, mem_wb)
+    pub fcsr: (Option<u32>, Option<u32>),
+}
+
+impl ForwardingUnit {
+    /// Gets the newest value of a register, or `value` if no forwarded result writes it
+    pub fn forward(&self, reg: Register, value: u32) -> u32 {
+        // the younger instruction in the memory stage takes priority
+        [(self.ex_mem, self.fcsr.0), (self.mem_wb, self.fcsr.1)]
+            .iter()
+            .find_map(|&((reg_write, write_register, (lo, hi), _, write_pair), fcsr)| {
+                if reg == Register::FCSR && fcsr.is_some() {
+                    fcsr
+                } else if !reg_write {
+                    None
+                } else if write_register == reg {
+                    Some(lo)
+                } else if write_pair && write_register.next() == reg {
+                    Some(hi)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(value)
+    }
+}
+
+/// Converts an exception raised by a stage into a bubble that carries it to writeback.
+///
+/// Any other error stops the machine.
+fn catch<T>(
+    result: Result<T>,
+    pc: u32,
+    faulted: fn(Exception, u32) -> T,
+) -> Result<T, RuntimeError> {
+    result.or_else(|err| match err.downcast::<Exception>() {
+        Ok(exception) => Ok(faulted(exception, pc)),
+        Err(err) => Err(RuntimeError::new(err, pc)),
+    })
+}
+
+/// Checks if an enabled interrupt is waiting to be taken
+fn interrupt_pending(regs: &RegisterFile<u32>) -> bool {
+    let status = regs.value_or_default(Register::STATUS);
+    let cause = regs.value_or_default(Register::CAUSE);
+    let enabled = status & STATUS_IE != 0 && status & STATUS_EXL == 0;
+    enabled && status & STATUS_IM & cause & CAUSE_IP != 0
+}
+
+/// Records an exception in coprocessor 0 and jumps to the kernel exception handler
+///
+/// An exception raised in a delay slot of the taken branch or jump at `branch` returns to the
+/// branch, so the instructions after it still go to its target, and sets the BD bit of Cause.
+/// Stops the machine if there is no handler or the handler itself raised an exception.
+fn take_exception(
+    pc: &mut u32,
+    regs: &mut RegisterFile<u32>,
+    mem: &mut Memory,
+    exception: Exception,
+    epc: u32,
+    branch: Option<u32>,
+) -> Result<(), RuntimeError> {
+    let status = regs.value_or_default(Register::STATUS);
+    if status & STATUS_EXL != 0 || mem.get(KTEXT_BASE).is_err() {
+        return Err(RuntimeError::new(exception.into(), epc));
+    }
+
+    let (epc, delay_slot) = match branch {
+        Some(branch) => (branch, CAUSE_BD),
+        None => (epc, 0),
+    };
+    let cause = regs.value_or_default(Register::CAUSE) & !(CAUSE_EXC_CODE | CAUSE_BD);
+    regs.set_value(Register::CAUSE, cause | delay_slot | (exception.code() << 2));
+    if let Some(address) = exception.bad_address() {
+        regs.set_value(Register::BADVADDR, address);
+    }
+    // the handler finds the exceptions that trapped in the FCSR cause field
+    if let Exception::FloatingPoint(raised) = exception {
+        let fcsr = regs.value_or_default(Register::FCSR);
+        regs.set_value(Register::FCSR, record_fp_cause(fcsr, raised));
+    }
+    regs.set_value(Register::EPC, epc);
+    regs.set_value(Register::STATUS, status | STATUS_EXL);
+    mem.clear_link();
+    *pc = KTEXT_BASE;
+    Ok(())
+}
+
+/// Checks if an instruction has to stall until the value loaded by the instruction ahead of it
+/// reaches the forwarding unit
+///
+/// This includes the result of a store conditional, which is only known after the memory stage.
+fn load_use_hazard(load: &IdEx, next: &IdEx) -> bool {
+    if !load.mem_to_reg {
+        return false;
+    }
+
+    let mut loaded = vec![load.rt];
+    if load.write_pair {
+        loaded.push(load.rt.next());
+    }
+
+    // double precision operands also read the odd register of their pair
+    let mut read = vec![next.rs, next.rt];
+    if next.alu_op == OP_COP1_D {
+        read.extend([next.rs.next(), next.rt.next()]);
+    }
+    if next.mem_write && next.access_width == AccessWidth::Double {
+        read.push(next.rt.next());
+    }
+
+    loaded.iter().any(|reg| read.contains(reg))
 }
 
 /// Steps the machine forward in a pipelined manner.
@@ -59,7 +181,7 @@ pub fn pipe_cycle(
     regs: &mut RegisterFile<u32>,
     mem: &mut Memory,
     state: PipelineState,
-) -> Result<(PipelineState, Option<Syscall>)> {
+) -> Result<(PipelineState, Option<Syscall>), RuntimeError> {
     // contruct forwarding unit
     let fwd_unit = ForwardingUnit {
         ex_mem: (
@@ -67,20 +189,77 @@ pub fn pipe_cycle(
             state.ex_mem.write_register,
             state.ex_mem.alu_result,
             state.ex_mem.use_hilo,
+            state.ex_mem.write_pair,
         ),
         mem_wb: (
             state.mem_wb.reg_write,
             state.mem_wb.write_register,
             if state.mem_wb.mem_to_reg {
-                (state.mem_wb.mem_data, 0)
+                state.mem_wb.mem_data
             } else {
                 state.mem_wb.alu_data
             },
             state.mem_wb.use_hilo,
+            state.mem_wb.write_pair,
         ),
+        fcsr: (state.ex_mem.fcsr, state.mem_wb.fcsr),
     };
 
+    // count cycles and raise the timer interrupt when count reaches compare
+    let count = regs.value_or_default(Register::COUNT).wrapping_add(1);
+    regs.set_value(Register::COUNT, count);
+    if count == regs.value_or_default(Register::COMPARE) {
+        let cause = regs.value_or_default(Register::CAUSE);
+        regs.set_value(Register::CAUSE, cause | CAUSE_TIMER);
+    }
+
     let pipe_out = stages::writeback(regs, state.mem_wb);
+    let in_shadow = state.branch_shadow.filter(|&branch| {
+        [branch.wrapping_add(4), branch.wrapping_add(8)].contains(&pipe_out.pc)
+    });
+    let mut branch_shadow = state
+        .branch_shadow
+        .filter(|&branch| pipe_out.pc != branch.wrapping_add(8));
+
+    // breakpoints pause the machine after the break instruction
+    if pipe_out.exception == Some(Exception::Breakpoint) {
+        *pc = pipe_out.pc.wrapping_add(4);
+        return Ok((
+            PipelineState {
+                pipe_out,
+                ..Default::default()
+            },
+            Some(Syscall::Breakpoint),
+        ));
+    }
+
+    // every older instruction has finished so exceptions are taken precisely by flushing the
+    // younger instructions
+    if let Some(exception) = pipe_out.exception {
+        take_exception(pc, regs, mem, exception, pipe_out.pc, in_shadow)?;
+        return Ok((
+            PipelineState {
+                pipe_out,
+                ..Default::default()
+            },
+            None,
+        ));
+    }
+
+    // return from the exception handler
+    if pipe_out.eret {
+        let status = regs.value_or_default(Register::STATUS);
+        regs.set_value(Register::STATUS, status & !STATUS_EXL);
+        mem.clear_link();
+        *pc = regs.value_or_default(Register::EPC);
+        return Ok((
+            PipelineState {
+                pipe_out,
+                ..Default::default()
+            },
+            None,
+        ));
+    }
 
     // pretend we jumped to the syscall vector
     if pipe_out.syscall {
@@ -92,15 +271,28 @@ pub fn pipe_cycle(
             PipelineState {
                 pipe_out,
                 mem_wb: MemWb::default(),
+                branch_shadow,
                 ..state
             },
             syscall,
         ));
     }
 
-    let mem_wb = stages::memory(pc, mem, state.ex_mem.clone())?;
+    let mem_wb = catch(
+        stages::memory(pc, mem, state.ex_mem.clone()),
+        state.ex_mem.pc,
+        MemWb::faulted,
+    )?;
+    // the instructions in decode and execute are the delay slots of a branch that redirects
+    if state.ex_mem.branch || state.ex_mem.jump {
+        branch_shadow = Some(state.ex_mem.pc);
+    }
 
-    let ex_mem = stages::execute(state.id_ex.clone(), fwd_unit)?;
+    let ex_mem = catch(
+        stages::execute(state.id_ex.clone(), fwd_unit),
+        state.id_ex.pc,
+        ExMem::faulted,
+    )?;
 
     // stall in case of syscall
     // TODO: Maybe not the best solution but ¯\_(ツ)_/¯
@@ -112,49 +304,156 @@ pub fn pipe_cycle(
                 ex_mem,
                 mem_wb,
                 pipe_out,
+                branch_shadow,
             },
             None,
         ));
     }
-    let id_ex = stages::decode(regs, state.if_id.clone())?;
+    let id_ex = catch(
+        stages::decode(regs, state.if_id.clone()),
+        state.if_id.pc,
+        IdEx::faulted,
+    )?;
     // hazard detector
-    if state.id_ex.mem_read {
-        if state.id_ex.rt == id_ex.rs {
-            return Ok((
-                PipelineState {
-                    if_id: state.if_id,
-                    id_ex: IdEx::default(),
-                    ex_mem,
-                    mem_wb,
-                    pipe_out,
-                },
-                None,
-            ));
-        }
-        if state.id_ex.rt == id_ex.rt {
-            return Ok((
-                PipelineState {
-                    if_id: state.if_id,
-                    id_ex: IdEx::default(),
-                    ex_mem,
-                    mem_wb,
-                    pipe_out,
-                },
-                None,
-            ));
-        }
+    if load_use_hazard(&state.id_ex, &id_ex) {
+        return Ok((
+            PipelineState {
+                if_id: state.if_id,
+                id_ex: IdEx::default(),
+                ex_mem,
+                mem_wb,
+                pipe_out,
+                branch_shadow,
+            },
+            None,
+        ));
     }
 
-    let if_id = stages::fetch(pc, mem);
+    // interrupts take the place of the next instruction so it is where the handler returns to,
+    // which is only known once every older branch and jump has redirected the PC
+    let in_flight = [id_ex.exception, ex_mem.exception, mem_wb.exception]
+        .iter()
+        .any(Option::is_some);
+    let redirecting = id_ex.branch || id_ex.jump || ex_mem.branch || ex_mem.jump;
+    let if_id = if interrupt_pending(regs) && !in_flight && !redirecting {
+        IfId::faulted(Exception::Interrupt, *pc)
+    } else {
+        let fetch_pc = *pc;
+        catch(stages::fetch(pc, mem), fetch_pc, IfId::faulted)?
+    };
 
     Ok((
         PipelineState {
-            if_id: if_id?,
+            if_id,
             id_ex,
             ex_mem,
             mem_wb,
             pipe_out,
+            branch_shadow,
         },
         None,
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{assembler, Machine, Register};
+
+    /// Runs a program until it stops, giving up after `limit` cycles
+    fn run(script: &str, limit: usize) -> Machine {
+        let (memory, labels) = assembler(script.to_string()).unwrap();
+        let mut machine = Machine::default();
+        machine.flash(memory, labels);
+        for _ in 0..limit {
+            if machine.cycle().is_break() {
+                return machine;
+            }
+        }
+        panic!("program did not stop within {limit} cycles");
+    }
+
+    #[test]
+    fn interrupt_during_taken_branch_resumes_at_target() {
+        // the timer goes off on a different cycle each run so it arrives with the branch in
+        // every stage of the pipeline, after the nops have let interrupts be enabled
+        for compare in 1..40 {
+            let script = format!(
+                "
+.text
+main:
+    li $t0, {compare}
+    mtc0 $t0, $compare
+    mtc0 $zero, $count
+    li $t0, 0x8001
+    mtc0 $t0, $status
+    nop
+    nop
+    nop
+    nop
+    nop
+    nop
+    beq $zero, $zero, taken
+    nop
+    nop
+    li $s0, 1
+taken:
+    beq $s7, $zero, taken
+    nop
+    nop
+    li $v0, 10
+    syscall
+
+.ktext
+    li $s7, 1
+    mtc0 $zero, $12
+    mtc0 $zero, $11
+    eret
+"
+            );
+            let machine = run(&script, 10_000);
+            let regs = machine.register_file();
+            assert_eq!(machine.error().map(ToString::to_string), None);
+            assert_eq!(regs.value_or_default(Register::S7), 1, "compare {compare}");
+            assert_eq!(regs.value_or_default(Register::S0), 0, "compare {compare}");
+        }
+    }
+
+    #[test]
+    fn exception_in_delay_slot_returns_to_branch() {
+        // the branches are written as words so the faulting load takes the place of a nop
+        let cases = [
+            ("0x10000003", "lw $t0, 1($zero)\n    nop", true),
+            ("0x10000003", "nop\n    lw $t0, 1($zero)", true),
+            ("0x14000003", "lw $t0, 1($zero)\n    nop", false),
+        ];
+        for (branch, slots, taken) in cases {
+            let script = format!(
+                "
+.text
+main:
+    la $s2, branch
+branch:
+    .word {branch}
+    {slots}
+    li $s3, 1
+    li $v0, 10
+    syscall
+
+.ktext
+    mfc0 $s0, $14
+    mfc0 $s1, $13
+    li $v0, 10
+    syscall
+"
+            );
+            let machine = run(&script, 1_000);
+            let regs = machine.register_file();
+            let epc = regs.value_or_default(Register::S0);
+            let cause = regs.value_or_default(Register::S1);
+            let branch = regs.value_or_default(Register::S2);
+            let load = branch + 4 + 4 * slots.starts_with("nop") as u32;
+            assert_eq!(epc, if taken { branch } else { load }, "{slots}");
+            assert_eq!(cause >> 31 == 1, taken, "{slots}");
+        }
+    }
+}
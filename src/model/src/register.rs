@@ -76,12 +76,68 @@ pub enum Register {
 
     LO = 64,
     HI = 65,
+
+    // coprocessor 0
+    BADVADDR = 66,
+    COUNT = 67,
+    COMPARE = 68,
+    STATUS = 69,
+    CAUSE = 70,
+    EPC = 71,
+
+    // coprocessor 1 control
+    FCSR = 72,
+    FIR = 73,
 }
 
 impl Register {
     pub fn id(&self) -> u32 {
         *self as u32
     }
+
+    /// The number that selects this register in an instruction field
+    pub fn number(&self) -> u32 {
+        self.cop0_number()
+            .or(self.fcr_number())
+            .unwrap_or(self.id() & 0x1f)
+    }
+
+    /// The register after this one, which holds the upper half of a double held in this register
+    pub fn next(&self) -> Register {
+        Register::from(self.id() + 1)
+    }
+
+    /// The coprocessor 0 register number if this is a coprocessor 0 register
+    pub fn cop0_number(&self) -> Option<u32> {
+        match self {
+            Register::BADVADDR => Some(8),
+            Register::COUNT => Some(9),
+            Register::COMPARE => Some(11),
+            Register::STATUS => Some(12),
+            Register::CAUSE => Some(13),
+            Register::EPC => Some(14),
+            _ => None,
+        }
+    }
+
+    /// Gets the coprocessor 0 register with the given number if it is modelled
+    pub fn from_cop0(number: u32) -> Option<Register> {
+        Register::iter().find(|reg| reg.cop0_number() == Some(number))
+    }
+
+    /// The floating point control register number if this is a coprocessor 1 control register
+    pub fn fcr_number(&self) -> Option<u32> {
+        match self {
+            Register::FIR => Some(0),
+            Register::FCSR => Some(31),
+            _ => None,
+        }
+    }
+
+    /// Gets the floating point control register with the given number if it is modelled
+    pub fn from_fcr(number: u32) -> Option<Register> {
+        Register::iter().find(|reg| reg.fcr_number() == Some(number))
+    }
 }
 
 impl fmt::Display for Register {
@@ -3,7 +3,7 @@ use std::hash::Hash;
 use indexmap::IndexMap;
 use strum::IntoEnumIterator;
 use crate::model::STACK_BASE;
-use crate::Register;
+use crate::{Register, FIR_VALUE};
 
 /**
 A struct wrapping the IndexMap type, representing a register file for storing generic types.
@@ -135,6 +135,51 @@ impl<T: Default, S: Into<String>> From<Vec<S>> for RegisterFile<T> {
     }
 }
 
+/** Implementation for register files holding 32 bit registers. */
+impl RegisterFile<u32> {
+    /**
+    Gets the double held by an even/odd pair of float registers.
+
+    # Arguments
+    - `register` - The even register of the pair, which holds the low word.
+     */
+    pub fn double_value(&self, register: Register) -> f64 {
+        let low = self.value_or_default(register) as u64;
+        let high = self.value_or_default(register.next()) as u64;
+        f64::from_bits(high << 32 | low)
+    }
+
+    /**
+    Stores a double in an even/odd pair of float registers.
+
+    # Arguments
+    - `register` - The even register of the pair, which receives the low word.
+    - `value` - The double to store.
+     */
+    pub fn set_double_value(&mut self, register: Register, value: f64) {
+        let bits = value.to_bits();
+        self.set_value(register, bits as u32);
+        self.set_value(register.next(), (bits >> 32) as u32);
+    }
+
+    /**
+    Gets a paired view of the float registers, reading each even/odd pair as a double.
+
+    # Returns
+    - A list of the pairs named after both registers (e.g. `f0/f1`) with the double they hold.
+     */
+    pub fn double_pairs(&self) -> Vec<(String, f64)> {
+        Register::iter()
+            .filter(|reg| (Register::F0.id()..=Register::F31.id()).contains(&reg.id()))
+            .step_by(2)
+            .map(|reg| {
+                let name = format!("{}/{}", String::from(reg), String::from(reg.next()));
+                (name, self.double_value(reg))
+            })
+            .collect()
+    }
+}
+
 /** Implementation of Default for RegisterFile<u32>. */
 impl Default for RegisterFile<u32> {
     fn default() -> Self {
@@ -147,6 +192,7 @@ impl Default for RegisterFile<u32> {
         // Create a RegisterFile from the list of register names
         let mut reg_file = RegisterFile::from(names);
         reg_file.set_value(Register::SP, STACK_BASE);
+        reg_file.set_value(Register::FIR, FIR_VALUE);
 
         reg_file
     }
@@ -1,4 +1,7 @@
-use crate::{stages::execute::{op_ctrl::*, IdEx}, Register, RegisterFile, Align, opcode};
+use crate::{
+    stages::execute::{op_ctrl::*, BranchCond, IdEx},
+    AccessWidth, Exception, Register, RegisterFile,
+};
 use anyhow::{bail, Result};
 use crate::Register::ZERO;
 
@@ -7,10 +10,39 @@ use crate::Register::ZERO;
 pub struct IfId {
     pub instruction: u32,
     pub pc: u32,
+    pub exception: Option<Exception>,
+}
+
+impl IfId {
+    /// A bubble carrying an exception raised by the instruction at `pc`
+    pub fn faulted(exception: Exception, pc: u32) -> Self {
+        Self {
+            pc,
+            exception: Some(exception),
+            ..Default::default()
+        }
+    }
+}
+
+/// Selects the condition of a trap instruction from the low bits of its funct or rt field
+fn trap_condition(select: u32) -> Option<BranchCond> {
+    match select & 0x7 {
+        0 => Some(BranchCond::GreaterEqual),
+        1 => Some(BranchCond::GreaterEqualUnsigned),
+        2 => Some(BranchCond::LessThan),
+        3 => Some(BranchCond::LessThanUnsigned),
+        4 => Some(BranchCond::Equal),
+        6 => Some(BranchCond::NotEqual),
+        _ => None,
+    }
 }
 
 /// Decodes and instruction
 pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
+    if let Some(exception) = input.exception {
+        return Ok(IdEx::faulted(exception, input.pc));
+    }
+
     // instruction masks
     let fn_mask = 0b00000000000000000000000000111111;
     let sh_mask = 0b00000000000000000000011111000000;
@@ -31,24 +63,31 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
     let mut imm = input.instruction & imm_mask;
     let j_imm = input.instruction & j_mask;
 
-    // sign extend the imm value
-    imm = ((imm << 16) as i32 >> 16) as u32;
+    // sign extend the imm value, logical instructions zero extend it instead
+    if !matches!(op, 0x0c..=0x0e) {
+        imm = ((imm << 16) as i32 >> 16) as u32;
+    }
 
-    // select proper registers based on the funct
-    match funct {
-        0x11 => {
-            // float regs
-            rd += 32;
-            rt += 32;
-            rs += 32;
-        }
-        0x10 | 0x12 => {
-            // hilo
-            if op == 0 {
-                rs += 64;
+    // coprocessor 1 instructions order their fields as fmt, ft, fs, fd
+    let fmt = rs;
+    if op == 0x11 {
+        // select float regs
+        match fmt {
+            // MFC1 and MTC1 move between rt and fs
+            0x00 | 0x04 => rd += 32,
+            // CFC1 and CTC1 select a control register with fs, BC1 branches select a condition
+            // flag with rt
+            0x02 | 0x06 | 0x08 => {}
+            _ => {
+                rs = rd + 32;
+                rt += 32;
+                rd = shamt + 32;
             }
         }
-        _ => {}
+    }
+    if matches!(op, 0x31 | 0x35 | 0x39 | 0x3d) {
+        // LWC1, LDC1, SWC1 and SDC1 transfer a float register
+        rt += 32;
     }
 
     // make registers typed
@@ -60,6 +99,15 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
     let mut read_rs = reg_file.value_or_default(rs);
     let mut read_rt = reg_file.value_or_default(rt);
 
+    // read hi and lo
+    let hilo = (
+        reg_file.value_or_default(Register::LO),
+        reg_file.value_or_default(Register::HI),
+    );
+
+    // read the floating point condition flags
+    let fcsr = reg_file.value_or_default(Register::FCSR);
+
     // handle controls
     let mut reg_dst; // determines destination register (0: rt, 1: rd)
     let mut alu_src; // if enabled use immediate value as alu arg2
@@ -70,14 +118,32 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
     let mut mem_read; // if enabled read from alu result
     let mut alu_op; // alu operation
     let mut branch; // enable branching
-    let mut branch_not; // enable branch not equal
+    let mut branch_cond = BranchCond::Equal; // condition tested by branches
     let mut jump; // enable jumping
+    let mut link = false; // if enabled the alu computes the return address
+    let mut jump_reg = false; // if enabled jump to the address in rs
+    let mut move_cond = false; // if enabled only write back when the branch condition holds
+    let linked = matches!(op, 0x30 | 0x38); // if enabled the memory access uses the reservation
     let mut syscall = false;
-    let mut word_align = true;
+    let mut eret = false; // if enabled return from an exception once this reaches writeback
+    let mut trap = false; // if enabled raise a trap when the branch condition holds
+    let mut write_pair = false; // if enabled write a double to an even/odd register pair
+
+    // select the width and extension of memory accesses
+    let (access_width, mem_unsigned) = match op {
+        0x20 | 0x28 => (AccessWidth::Byte, false),      // lb, sb
+        0x24 => (AccessWidth::Byte, true),              // lbu
+        0x21 | 0x29 => (AccessWidth::Half, false),      // lh, sh
+        0x25 => (AccessWidth::Half, true),              // lhu
+        0x22 | 0x2a => (AccessWidth::WordLeft, false),  // lwl, swl
+        0x26 | 0x2e => (AccessWidth::WordRight, false), // lwr, swr
+        0x35 | 0x3d => (AccessWidth::Double, false),    // ldc1, sdc1
+        _ => (AccessWidth::Word, false),
+    };
 
     // This is where instructions are defined
     match op {
-        0 | 1 | 0x1c | 0x2a => {
+        0 | 0x1c => {
             syscall = funct == 0x0c;
             // R-type instruction
             reg_dst = true;
@@ -87,55 +153,200 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
-            alu_op = OP_R;
-
-            if op == 0 {
-                match funct {
-                    0x1a => {
-                        // div
-                        reg_write = false;
-                        use_hilo = true;
-                    }
-                    0x10 | 0x12 => {
-                        // mfhi, mflo
-                        use_hilo = true;
-                    }
-                    _ => {}
-                }
+            alu_op = if op == 0 { OP_R } else { OP_SPECIAL2 };
+
+            let writes_hilo = if op == 0 {
+                // mthi, mtlo, mult, multu, div, divu
+                matches!(funct, 0x11 | 0x13 | 0x18..=0x1b)
+            } else {
+                // madd, maddu, msub, msubu
+                matches!(funct, 0x00 | 0x01 | 0x04 | 0x05)
+            };
+            if writes_hilo {
+                reg_write = false;
+                use_hilo = true;
+            }
+
+            if op == 0 && (0x30..=0x36).contains(&funct) {
+                // TGE, TGEU, TLT, TLTU, TEQ and TNE instructions
+                let Some(cond) = trap_condition(funct) else {
+                    bail!(Exception::ReservedInstruction)
+                };
+                reg_write = false;
+                trap = true;
+                branch_cond = cond;
+            }
+
+            if op == 0 && funct == 0x0d {
+                // BREAK instruction
+                bail!(Exception::Breakpoint)
             }
         }
-        0x20 => {
-            // LB instruction
-            reg_dst = false;
-            alu_src = true;
-            mem_to_reg = true;
+        0x1f => {
+            // SPECIAL3 instruction
+            reg_dst = true;
+            alu_src = false;
+            mem_to_reg = false;
             reg_write = true;
-            mem_read = true;
+            mem_read = false;
             mem_write = false;
-            word_align = false;
             branch = false;
-            branch_not = false;
             jump = false;
-            alu_op = OP_ADD;
+            alu_op = OP_SPECIAL3;
+
+            // the rd field holds the upper end of the bit field and sa its lower end
+            let msb = rd.id();
+            match funct {
+                // EXT and INS write rt
+                0x00 if shamt + msb < 32 => reg_dst = false,
+                0x04 if msb >= shamt => reg_dst = false,
+                // SEB, SEH and WSBH are selected by sa
+                0x20 if matches!(shamt, 0x02 | 0x10 | 0x18) => {}
+                _ => bail!(Exception::ReservedInstruction),
+            }
+        }
+        0x11 => {
+            // COP1 instruction
+            reg_dst = true;
+            alu_src = false;
+            mem_to_reg = false;
+            reg_write = true;
+            mem_read = false;
+            mem_write = false;
+            branch = false;
+            jump = false;
+
+            // the fmt field selects the operation
+            match fmt {
+                0x00 => {
+                    // MFC1 moves fs into rt
+                    reg_dst = false;
+                    alu_op = OP_ADD;
+                    rs = rd;
+                    read_rs = reg_file.value_or_default(rd);
+                    alu_src = true;
+                    imm = 0;
+                }
+                0x04 => {
+                    // MTC1 moves rt into fs
+                    alu_op = OP_ADD;
+                    rs = ZERO;
+                    read_rs = 0;
+                }
+                0x02 | 0x06 => {
+                    let Some(fcr) = Register::from_fcr(rd.id()) else {
+                        bail!(Exception::ReservedInstruction)
+                    };
+                    alu_op = OP_ADD;
+
+                    if fmt == 0x02 {
+                        // CFC1 moves the control register into rt
+                        reg_dst = false;
+                        rs = fcr;
+                        read_rs = reg_file.value_or_default(fcr);
+                        alu_src = true;
+                        imm = 0;
+                    } else {
+                        // CTC1 moves rt into the control register, the FIR is read only
+                        reg_write = fcr == Register::FCSR;
+                        rd = fcr;
+                        rs = ZERO;
+                        read_rs = 0;
+                    }
+                }
+                0x08 => {
+                    // BC1F and BC1T test the condition flag selected by the upper bits of rt
+                    let cc = rt.id() >> 2;
+                    reg_dst = false;
+                    reg_write = false;
+                    branch = true;
+                    branch_cond = if rt.id() & 1 == 0 {
+                        BranchCond::FpFalse(cc)
+                    } else {
+                        BranchCond::FpTrue(cc)
+                    };
+                    alu_op = OP_ADD;
+                    rs = Register::FCSR;
+                    read_rs = fcsr;
+                    rt = ZERO;
+                    read_rt = 0;
+                }
+                // single, double and word formats
+                0x10 | 0x11 | 0x14 => {
+                    alu_op = match fmt {
+                        0x10 => OP_COP1,
+                        0x11 => OP_COP1_D,
+                        _ => OP_COP1_W,
+                    };
+
+                    // doubles are produced by every double operation except conversions and
+                    // compares, and by the conversions to double
+                    write_pair = if fmt == 0x11 {
+                        !matches!(funct, 0x0d | 0x20 | 0x24 | 0x30..=0x3f)
+                    } else {
+                        funct == 0x21
+                    };
+
+                    // doubles must be held in an even/odd register pair
+                    let odd_double = (fmt == 0x11 && (rs.id() | rt.id()) & 1 != 0)
+                        || (write_pair && rd.id() & 1 != 0);
+                    if odd_double {
+                        bail!(Exception::ReservedInstruction)
+                    }
+
+                    if funct >= 0x30 {
+                        // C.cond.fmt sets the condition flag selected by the upper bits of fd
+                        rd = Register::FCSR;
+                    }
+                }
+                _ => bail!(Exception::ReservedInstruction),
+            }
         }
-        0x28 => {
-            // SB instruction
+        0x10 => {
+            // COP0 instruction
             reg_dst = false;
-            alu_src = true;
+            alu_src = false;
             mem_to_reg = false;
             reg_write = false;
             mem_read = false;
-            mem_write = true;
-            word_align = false;
+            mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
             alu_op = OP_ADD;
+
+            // the rs field selects the operation
+            match fmt {
+                0x00 | 0x04 => {
+                    let Some(cop0) = Register::from_cop0(rd.id()) else {
+                        bail!("Unrecognized coprocessor 0 register {}", rd.id())
+                    };
+                    reg_write = true;
+
+                    if fmt == 0x00 {
+                        // MFC0 moves the coprocessor 0 register into rt
+                        rs = cop0;
+                        read_rs = reg_file.value_or_default(cop0);
+                        alu_src = true;
+                        imm = 0;
+                    } else {
+                        // MTC0 moves rt into the coprocessor 0 register
+                        reg_dst = true;
+                        rd = cop0;
+                        rs = ZERO;
+                        read_rs = 0;
+                    }
+                }
+                0x10 if funct == 0x18 => {
+                    // ERET
+                    eret = true;
+                }
+                _ => bail!(Exception::ReservedInstruction),
+            }
         }
-        0x23 => {
-            // LW instruction
+        0x20..=0x26 | 0x30 | 0x31 | 0x35 => {
+            // LB, LH, LWL, LW, LBU, LHU, LWR, LL, LWC1 and LDC1 instructions
+            write_pair = op == 0x35;
             reg_dst = false;
             alu_src = true;
             mem_to_reg = true;
@@ -143,12 +354,11 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = true;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
             alu_op = OP_ADD;
         }
-        0x2b => {
-            // SW instruction
+        0x28 | 0x29 | 0x2a | 0x2b | 0x2e | 0x39 | 0x3d => {
+            // SB, SH, SWL, SW, SWR, SWC1 and SDC1 instructions
             reg_dst = false;
             alu_src = true;
             mem_to_reg = false;
@@ -156,12 +366,23 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = true;
             branch = false;
-            branch_not = false;
             jump = false;
             alu_op = OP_ADD;
         }
-        0x8 => {
-            // ADDI instruction
+        0x38 => {
+            // SC instruction, which also writes whether it stored to rt
+            reg_dst = false;
+            alu_src = true;
+            mem_to_reg = true;
+            reg_write = true;
+            mem_read = false;
+            mem_write = true;
+            branch = false;
+            jump = false;
+            alu_op = OP_ADD;
+        }
+        0x8 | 0x9 => {
+            // ADDI and ADDIU instructions
             reg_dst = false;
             alu_src = true;
             mem_to_reg = false;
@@ -169,9 +390,8 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
-            alu_op = OP_ADD;
+            alu_op = if op == 0x8 { OP_ADD_TRAP } else { OP_ADD };
         }
 
         0xc => {
@@ -183,7 +403,6 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
             alu_op = OP_AND;
         }
@@ -198,10 +417,22 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             alu_op = OP_UPPER;
             branch = false;
-            branch_not = false;
             jump = false;
         }
 
+        0xa | 0xb => {
+            // SLTI and SLTIU instructions
+            reg_dst = false;
+            alu_src = true;
+            mem_to_reg = false;
+            reg_write = true;
+            mem_read = false;
+            mem_write = false;
+            branch = false;
+            jump = false;
+            alu_op = if op == 0xa { OP_SLT } else { OP_SLTU };
+        }
+
         0xd => {
             // ORI instruction
             reg_dst = false;
@@ -211,10 +442,22 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = false;
             alu_op = OP_OR;
         }
+
+        0xe => {
+            // XORI instruction
+            reg_dst = false;
+            alu_src = true;
+            mem_to_reg = false;
+            reg_write = true;
+            mem_read = false;
+            mem_write = false;
+            branch = false;
+            jump = false;
+            alu_op = OP_XOR;
+        }
         0x4 => {
             // BEQ instruction
             reg_dst = false;
@@ -224,7 +467,6 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = true;
-            branch_not = false;
             jump = false;
             alu_op = OP_SUB;
         }
@@ -237,10 +479,73 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = true;
-            branch_not = true;
+            branch_cond = BranchCond::NotEqual;
+            jump = false;
+            alu_op = OP_SUB;
+        }
+        0x6 | 0x7 => {
+            // BLEZ and BGTZ instructions
+            reg_dst = false;
+            alu_src = false;
+            mem_to_reg = false;
+            reg_write = false;
+            mem_read = false;
+            mem_write = false;
+            branch = true;
+            branch_cond = if op == 0x6 {
+                BranchCond::LessEqualZero
+            } else {
+                BranchCond::GreaterThanZero
+            };
             jump = false;
             alu_op = OP_SUB;
         }
+        0x1 if (0x08..=0x0e).contains(&rt.id()) => {
+            // TGEI, TGEIU, TLTI, TLTIU, TEQI and TNEI instructions
+            let Some(cond) = trap_condition(rt.id()) else {
+                bail!(Exception::ReservedInstruction)
+            };
+            reg_dst = false;
+            alu_src = true;
+            mem_to_reg = false;
+            reg_write = false;
+            mem_read = false;
+            mem_write = false;
+            branch = false;
+            jump = false;
+            alu_op = OP_ADD;
+            trap = true;
+            branch_cond = cond;
+            rt = ZERO;
+            read_rt = 0;
+        }
+        0x1 => {
+            // BLTZ, BGEZ, BLTZAL and BGEZAL instructions
+            // the rt field selects the condition instead of a register
+            let cond = rt.id();
+            if cond & !0x11 != 0 {
+                bail!("Unrecognized REGIMM instruction 0x{:x}", cond)
+            }
+
+            reg_dst = true;
+            alu_src = false;
+            mem_to_reg = false;
+            reg_write = cond & 0x10 != 0;
+            mem_read = false;
+            mem_write = false;
+            branch = true;
+            branch_cond = if cond & 1 == 0 {
+                BranchCond::LessThanZero
+            } else {
+                BranchCond::GreaterEqualZero
+            };
+            jump = false;
+            link = reg_write;
+            alu_op = OP_ADD;
+            rt = ZERO;
+            rd = Register::RA;
+            read_rt = 0;
+        }
         0x02 | 0x03 => {
             // J instruction
             reg_dst = false;
@@ -250,41 +555,78 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
             mem_read = false;
             mem_write = false;
             branch = false;
-            branch_not = false;
             jump = true;
             alu_op = OP_ADD;
-            imm = j_imm;
+            // the target keeps the upper bits of the pc
+            imm = (input.pc.wrapping_add(4) & 0xF000_0000) >> 2 | j_imm;
 
             if op == 0x03 {
+                // JAL instruction
                 reg_dst = true;
                 reg_write = true;
-                rs = ZERO;
-                rt = ZERO;
+                link = true;
                 rd = Register::RA;
-                read_rs = input.pc;
-                read_rt = 4;
             }
         }
         _ => {
-            bail!("Unrecognized instruction opcode 0x{:x}", op)
+            bail!(Exception::ReservedInstruction)
         }
     }
 
-    if op == 0 && funct == 0x8 {
-        // JR instruction
-        reg_dst = false;
+    if op == 0 && (funct == 0x8 || funct == 0x9) {
+        // JR and JALR instructions, the target is read from rs once it has been forwarded
+        reg_dst = true;
         alu_src = false;
         mem_to_reg = false;
-        reg_write = false;
+        reg_write = funct == 0x9;
         mem_read = false;
         mem_write = false;
         branch = false;
-        branch_not = false;
         jump = true;
+        jump_reg = true;
+        link = funct == 0x9;
+        alu_op = OP_ADD;
+    }
+
+    if op == 0 && matches!(funct, 0x01 | 0x0a | 0x0b) {
+        // MOVF, MOVT, MOVZ and MOVN instructions move rs into rd when their condition holds
         alu_op = OP_ADD;
-        imm = read_rs >> 2;
+        alu_src = true;
+        imm = 0;
+        move_cond = true;
+        branch_cond = match funct {
+            0x0a => BranchCond::Equal,
+            0x0b => BranchCond::NotEqual,
+            _ => {
+                // the upper bits of rt select a floating point condition flag
+                let cc = rt.id() >> 2;
+                let true_cond = rt.id() & 1 != 0;
+                rt = Register::FCSR;
+                read_rt = fcsr;
+                if true_cond {
+                    BranchCond::FpTrue(cc)
+                } else {
+                    BranchCond::FpFalse(cc)
+                }
+            }
+        };
     }
 
+    if op == 0 && funct == 0x0f {
+        // SYNC instruction, the pipeline already completes memory accesses in order
+        reg_write = false;
+        alu_op = OP_ADD;
+    }
+
+    if op == 0 && ((funct == 0x02 && rs.id() == 1) || (funct == 0x06 && shamt == 1)) {
+        // ROTR and ROTRV instructions are selected by a bit that is clear for SRL and SRLV
+        alu_op = OP_ROTATE;
+    }
+
+    // read the odd halves of double precision operands
+    let read_rs_hi = reg_file.value_or_default(rs.next());
+    let read_rt_hi = reg_file.value_or_default(rt.next());
+
     Ok(IdEx {
         alu_src,
         reg_dst,
@@ -292,6 +634,10 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
         op_funct: funct as u8,
         reg_1: read_rs,
         reg_2: read_rt,
+        reg_1_hi: read_rs_hi,
+        reg_2_hi: read_rt_hi,
+        hilo,
+        fcsr,
         imm,
         shamt,
         rt,
@@ -299,15 +645,24 @@ pub fn decode(reg_file: &mut RegisterFile<u32>, input: IfId) -> Result<IdEx> {
         rd,
         mem_write,
         mem_read,
-        word_align,
+        access_width,
+        mem_unsigned,
         mem_to_reg,
         reg_write,
         use_hilo,
+        write_pair,
         branch,
-        branch_not,
+        branch_cond,
         jump,
+        jump_reg,
+        link,
+        move_cond,
+        linked,
         pc: input.pc,
         syscall,
+        eret,
+        trap,
+        exception: None,
         instruction: input.instruction,
     })
 }
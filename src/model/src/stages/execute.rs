@@ -1,6 +1,9 @@
 use super::memory::ExMem;
 use crate::pipeline::ForwardingUnit;
-use crate::{Register, RegisterFile};
+use crate::{
+    fp_condition, fp_traps, record_fp_exceptions, round_to_word, set_fp_condition, AccessWidth,
+    Exception, Register, RoundingMode, Unrounded, FP_INVALID,
+};
 use anyhow::{bail, Result};
 
 /// Struct representing this stages input
@@ -13,28 +16,94 @@ pub struct IdEx {
     pub op_funct: u8,
     pub reg_1: u32,
     pub reg_2: u32,
+    // upper halves of double precision operands
+    pub reg_1_hi: u32,
+    pub reg_2_hi: u32,
+    pub hilo: (u32, u32),
+    pub fcsr: u32,
     pub imm: u32,
     pub shamt: u32,
     pub rt: Register,
     pub rd: Register,
     pub syscall: bool,
+    pub eret: bool,
+    pub trap: bool,
+    pub exception: Option<Exception>,
     // forwarded data
     pub branch: bool,
-    pub branch_not: bool,
+    pub branch_cond: BranchCond,
     pub jump: bool,
+    pub jump_reg: bool,
+    pub link: bool,
+    pub move_cond: bool,
+    pub linked: bool,
     pub pc: u32,
     pub mem_write: bool,
     pub mem_read: bool,
-    pub word_align: bool,
+    pub access_width: AccessWidth,
+    pub mem_unsigned: bool,
     pub mem_to_reg: bool,
     pub reg_write: bool,
     pub use_hilo: bool,
+    pub write_pair: bool,
     pub rs: Register,
 
     // demo thing
     pub instruction: u32,
 }
 
+impl IdEx {
+    /// A bubble carrying an exception raised by the instruction at `pc`
+    pub fn faulted(exception: Exception, pc: u32) -> Self {
+        Self {
+            pc,
+            exception: Some(exception),
+            ..Default::default()
+        }
+    }
+}
+
+/// Condition a branch or trap tests its source registers against
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BranchCond {
+    #[default]
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanUnsigned,
+    GreaterEqual,
+    GreaterEqualUnsigned,
+    LessThanZero,
+    GreaterEqualZero,
+    LessEqualZero,
+    GreaterThanZero,
+    /// The floating point condition flag is set, rs holds the FCSR
+    FpTrue(u32),
+    /// The floating point condition flag is clear, rs holds the FCSR
+    FpFalse(u32),
+}
+
+impl BranchCond {
+    /// Checks if a branch with this condition is taken
+    pub fn taken(&self, rs: u32, rt: u32) -> bool {
+        let rs_signed = rs as i32;
+        match self {
+            BranchCond::Equal => rs == rt,
+            BranchCond::NotEqual => rs != rt,
+            BranchCond::LessThan => rs_signed < rt as i32,
+            BranchCond::LessThanUnsigned => rs < rt,
+            BranchCond::GreaterEqual => rs_signed >= rt as i32,
+            BranchCond::GreaterEqualUnsigned => rs >= rt,
+            BranchCond::LessThanZero => rs_signed < 0,
+            BranchCond::GreaterEqualZero => rs_signed >= 0,
+            BranchCond::LessEqualZero => rs_signed <= 0,
+            BranchCond::GreaterThanZero => rs_signed > 0,
+            BranchCond::FpTrue(cc) => fp_condition(rs, *cc),
+            BranchCond::FpFalse(cc) => !fp_condition(rs, *cc),
+        }
+    }
+}
+
 pub mod op_ctrl {
     pub const OP_R: u8 = 0;
     pub const OP_AND: u8 = 1;
@@ -42,37 +111,68 @@ pub mod op_ctrl {
     pub const OP_ADD: u8 = 3;
     pub const OP_SUB: u8 = 4;
     pub const OP_UPPER: u8 = 5;
+    pub const OP_SLT: u8 = 6;
+    pub const OP_SLTU: u8 = 7;
+    pub const OP_SPECIAL2: u8 = 8;
+    pub const OP_COP1: u8 = 9;
+    pub const OP_ADD_TRAP: u8 = 10;
+    pub const OP_COP1_D: u8 = 11;
+    pub const OP_COP1_W: u8 = 12;
+    pub const OP_XOR: u8 = 13;
+    pub const OP_ROTATE: u8 = 14;
+    pub const OP_SPECIAL3: u8 = 15;
 }
 use op_ctrl::*;
 
 /// Runs execute stage
 pub fn execute(input: IdEx, fwd_unit: ForwardingUnit) -> Result<ExMem> {
+    if let Some(exception) = input.exception {
+        return Ok(ExMem::faulted(exception, input.pc));
+    }
+
     let syscall = input.syscall;
     let mut use_shamt = false;
+    let mut shift_variable = false;
     // compute ALU control lines
     let alu_ctrl = match input.alu_op {
         OP_R => {
             // get info from instruction funct
             match input.op_funct {
-                0x20 => (false, false, ALU_ADD), // add
-                0x22 => (false, true, ALU_ADD),  // sub
+                0x20 => (false, false, ALU_ADD_TRAP), // add
+                0x21 => (false, false, ALU_ADD), // addu
+                0x22 => (false, true, ALU_ADD_TRAP), // sub
+                0x23 => (false, true, ALU_ADD),  // subu
                 0x24 => (false, false, ALU_AND), // and
                 0x2a => (false, false, ALU_SLT), // slt
+                0x2b => (false, false, ALU_SLTU), // sltu
                 0x25 => (false, false, ALU_OR),  // or
                 0x27 => (true, true, ALU_AND),   // nor
                 0x0c => (false, false, ALU_ADD), // syscall
-                0x06 => (false, false, ALU_SRL), // srlv
+                0x30..=0x36 => (false, false, ALU_ADD), // traps
+                0x04 => {
+                    // sllv
+                    shift_variable = true;
+                    (false, false, ALU_SLL)
+                }
+                0x06 => {
+                    // srlv
+                    shift_variable = true;
+                    (false, false, ALU_SRL)
+                }
+                0x07 => {
+                    // srav
+                    shift_variable = true;
+                    (false, false, ALU_SRA)
+                }
                 0x26 => (false, false, ALU_XOR), // xor
-                0x1c => (false, false, ALU_MUL), // mul
+                0x10 => (false, false, ALU_MFHI), // mfhi
+                0x11 => (false, false, ALU_MTHI), // mthi
+                0x12 => (false, false, ALU_MFLO), // mflo
+                0x13 => (false, false, ALU_MTLO), // mtlo
+                0x18 => (false, false, ALU_MULT), // mult
+                0x19 => (false, false, ALU_MULTU), // multu
                 0x1a => (false, false, ALU_DIV), // div
-
-                0x10 | 0x12 => (false, false, ALU_ADD), // mflo & mfhi
-
-                0x11 => {
-                    // add.s
-                    use_shamt = true;
-                    (false, false, ALU_ADD_S)
-                }
+                0x1b => (false, false, ALU_DIVU), // divu
                 0x00 => {
                     // sll
                     use_shamt = true;
@@ -94,10 +194,96 @@ pub fn execute(input: IdEx, fwd_unit: ForwardingUnit) -> Result<ExMem> {
             }
         }
         OP_ADD => (false, false, ALU_ADD),
+        OP_ADD_TRAP => (false, false, ALU_ADD_TRAP),
         OP_SUB => (false, true, ALU_ADD),
         OP_AND => (false, false, ALU_AND),
         OP_OR => (false, false, ALU_OR),
+        OP_XOR => (false, false, ALU_XOR),
         OP_UPPER => (false, false, ALU_UPPER),
+        OP_SLT => (false, false, ALU_SLT),
+        OP_SLTU => (false, false, ALU_SLTU),
+        OP_SPECIAL2 => match input.op_funct {
+            0x00 => (false, false, ALU_MADD),  // madd
+            0x01 => (false, false, ALU_MADDU), // maddu
+            0x02 => (false, false, ALU_MUL),   // mul
+            0x04 => (false, false, ALU_MSUB),  // msub
+            0x05 => (false, false, ALU_MSUBU), // msubu
+            0x20 => (false, false, ALU_CLZ),   // clz
+            0x21 => (false, false, ALU_CLO),   // clo
+            _ => {
+                bail!("Unkown SPECIAL2 funct: {}", input.op_funct)
+            }
+        },
+        OP_ROTATE => {
+            // rotr keeps its amount in shamt while rotrv reads it from rs
+            if input.op_funct == 0x02 {
+                use_shamt = true;
+            } else {
+                shift_variable = true;
+            }
+            (false, false, ALU_ROTR)
+        }
+        OP_SPECIAL3 => match (input.op_funct, input.shamt) {
+            (0x00, _) => (false, false, ALU_EXT),     // ext
+            (0x04, _) => (false, false, ALU_INS),     // ins
+            (0x20, 0x02) => (false, false, ALU_WSBH), // wsbh
+            (0x20, 0x10) => (false, false, ALU_SEB),  // seb
+            (0x20, 0x18) => (false, false, ALU_SEH),  // seh
+            _ => {
+                bail!("Unkown SPECIAL3 funct: {}", input.op_funct)
+            }
+        },
+        OP_COP1 => match input.op_funct {
+            0x00 => (false, false, ALU_ADD_S),     // add.s
+            0x01 => (false, false, ALU_SUB_S),     // sub.s
+            0x02 => (false, false, ALU_MUL_S),     // mul.s
+            0x03 => (false, false, ALU_DIV_S),     // div.s
+            0x04 => (false, false, ALU_SQRT_S),    // sqrt.s
+            0x05 => (false, false, ALU_ABS_S),     // abs.s
+            0x06 => (false, false, ALU_MOV),       // mov.s
+            0x07 => (false, false, ALU_NEG_S),     // neg.s
+            0x0c => (false, false, ALU_ROUND_W_S), // round.w.s
+            0x0d => (false, false, ALU_TRUNC_W_S), // trunc.w.s
+            0x0e => (false, false, ALU_CEIL_W_S),  // ceil.w.s
+            0x0f => (false, false, ALU_FLOOR_W_S), // floor.w.s
+            0x21 => (false, false, ALU_CVT_D_S),   // cvt.d.s
+            0x24 => (false, false, ALU_CVT_W_S),   // cvt.w.s
+            0x32 => (false, false, ALU_C_EQ_S),    // c.eq.s
+            0x3c => (false, false, ALU_C_LT_S),    // c.lt.s
+            0x3e => (false, false, ALU_C_LE_S),    // c.le.s
+            _ => {
+                bail!("Unkown COP1 funct: {}", input.op_funct)
+            }
+        },
+        OP_COP1_D => match input.op_funct {
+            0x00 => (false, false, ALU_ADD_D),     // add.d
+            0x01 => (false, false, ALU_SUB_D),     // sub.d
+            0x02 => (false, false, ALU_MUL_D),     // mul.d
+            0x03 => (false, false, ALU_DIV_D),     // div.d
+            0x04 => (false, false, ALU_SQRT_D),    // sqrt.d
+            0x05 => (false, false, ALU_ABS_D),     // abs.d
+            0x06 => (false, false, ALU_MOV_D),     // mov.d
+            0x07 => (false, false, ALU_NEG_D),     // neg.d
+            0x0c => (false, false, ALU_ROUND_W_D), // round.w.d
+            0x0d => (false, false, ALU_TRUNC_W_D), // trunc.w.d
+            0x0e => (false, false, ALU_CEIL_W_D),  // ceil.w.d
+            0x0f => (false, false, ALU_FLOOR_W_D), // floor.w.d
+            0x20 => (false, false, ALU_CVT_S_D),   // cvt.s.d
+            0x24 => (false, false, ALU_CVT_W_D),   // cvt.w.d
+            0x32 => (false, false, ALU_C_EQ_D),    // c.eq.d
+            0x3c => (false, false, ALU_C_LT_D),    // c.lt.d
+            0x3e => (false, false, ALU_C_LE_D),    // c.le.d
+            _ => {
+                bail!("Unkown COP1 double funct: {}", input.op_funct)
+            }
+        },
+        OP_COP1_W => match input.op_funct {
+            0x20 => (false, false, ALU_CVT_S_W), // cvt.s.w
+            0x21 => (false, false, ALU_CVT_D_W), // cvt.d.w
+            _ => {
+                bail!("Unkown COP1 word funct: {}", input.op_funct)
+            }
+        },
         _ => {
             bail!("Unknown Instruction")
         }
@@ -118,49 +304,50 @@ pub fn execute(input: IdEx, fwd_unit: ForwardingUnit) -> Result<ExMem> {
      */
 
     // check forwarding unit on first register
-    if fwd_unit.mem_wb.0 && input.rs == fwd_unit.mem_wb.1 {
-        arg1 = fwd_unit.mem_wb.2.0;
-    }
-    if fwd_unit.ex_mem.0 && input.rs == fwd_unit.ex_mem.1 {
-        arg1 = fwd_unit.ex_mem.2.0;
-    }
+    arg1 = fwd_unit.forward(input.rs, arg1);
 
     // check forwarding unit on second register
-    if fwd_unit.mem_wb.0 && input.rt == fwd_unit.mem_wb.1 {
-        arg2 = fwd_unit.mem_wb.2.0;
+    arg2 = fwd_unit.forward(input.rt, arg2);
+
+    // check forwarding unit on the odd halves of double precision operands
+    let arg1_hi = fwd_unit.forward(input.rs.next(), input.reg_1_hi);
+    let arg2_hi = fwd_unit.forward(input.rt.next(), input.reg_2_hi);
+
+    // check forwarding unit on lo & hi registers
+    let mut hilo = input.hilo;
+    if fwd_unit.mem_wb.3 {
+        hilo = fwd_unit.mem_wb.2;
     }
-    if fwd_unit.ex_mem.0 && input.rt == fwd_unit.ex_mem.1 {
-        arg2 = fwd_unit.ex_mem.2.0;
+    if fwd_unit.ex_mem.3 {
+        hilo = fwd_unit.ex_mem.2;
     }
 
-    // check forwarding unit on lo & hi registers
-    match input.rs {
-        HI => {
-            if fwd_unit.mem_wb.3 {
-                arg1 = fwd_unit.mem_wb.2.1;
-            } else if fwd_unit.ex_mem.3 {
-                arg1 = fwd_unit.ex_mem.2.1;
-            }
-        }
+    // check forwarding unit on the floating point control and status register
+    let fcsr = fwd_unit.forward(Register::FCSR, input.fcsr);
 
-        LO => {
-            if fwd_unit.mem_wb.3 {
-                arg1 = fwd_unit.mem_wb.2.0;
-            } else if fwd_unit.ex_mem.3 {
-                arg1 = fwd_unit.ex_mem.2.0;
-            }
-        }
+    let fwd_rt = arg2;
 
-        _ => {}
-    }
+    // register jumps target the forwarded rs
+    let jump_pc = if input.jump_reg { arg1 } else { input.imm << 2 };
 
-    let fwd_rt = arg2;
+    // compare the forwarded registers to resolve branches
+    let branch = input.branch && input.branch_cond.taken(arg1, fwd_rt);
 
     // Handle immediate arguments
     if input.alu_src {
         arg2 = input.imm;
     }
 
+    if input.trap && input.branch_cond.taken(arg1, arg2) {
+        bail!(Exception::Trap)
+    }
+
+    // linking instructions store the return address
+    if input.link {
+        arg1 = input.pc;
+        arg2 = 4;
+    }
+
     // check if we are using a shift operation.
     // and load the shamt if so
     if use_shamt {
@@ -168,25 +355,61 @@ pub fn execute(input: IdEx, fwd_unit: ForwardingUnit) -> Result<ExMem> {
         arg2 = input.shamt;
     }
 
-    let result = alu(arg1, arg2, alu_ctrl)?;
+    // variable shifts move rt by the amount in rs
+    if shift_variable {
+        (arg1, arg2) = (arg2, arg1);
+    }
+
+    let mut fcsr_write = None;
+    let result = if matches!(input.alu_op, OP_COP1 | OP_COP1_D | OP_COP1_W) {
+        let mode = RoundingMode::from_fcsr(fcsr);
+        let (result, raised) = fpu((arg1, arg1_hi), (arg2, arg2_hi), alu_ctrl.2, mode)?;
+
+        // enabled exceptions trap instead of being recorded
+        if fp_traps(fcsr, raised) != 0 {
+            bail!(Exception::FloatingPoint(raised))
+        }
+        let fcsr = record_fp_exceptions(fcsr, raised);
+
+        if input.rd == Register::FCSR {
+            // comparisons set the condition flag selected by the upper bits of fd
+            (set_fp_condition(fcsr, input.shamt >> 2, result.0 != 0), 0)
+        } else {
+            // other operations update the FCSR alongside their result
+            fcsr_write = Some(fcsr);
+            result
+        }
+    } else if matches!(alu_ctrl.2, ALU_EXT | ALU_INS) {
+        // the rd field holds the upper end of the bit field
+        (bit_field(arg1, fwd_rt, input.shamt, input.rd.id(), alu_ctrl.2)?, 0)
+    } else {
+        alu(arg1, arg2, hilo, alu_ctrl)?
+    };
+
+    // conditional moves only write back when their condition holds
+    let reg_write = input.reg_write && (!input.move_cond || input.branch_cond.taken(fwd_rt, 0));
 
     Ok(ExMem {
         alu_result: result,
-        zero: result.0 == 0,
-        write_data: fwd_rt,
+        write_data: (fwd_rt, arg2_hi),
         write: input.mem_write,
         read: input.mem_read,
-        word_align: input.word_align,
+        access_width: input.access_width,
+        mem_unsigned: input.mem_unsigned,
         mem_to_reg: input.mem_to_reg,
         write_register: if input.reg_dst { input.rd } else { input.rt },
-        reg_write: input.reg_write,
+        reg_write,
         use_hilo: input.use_hilo,
-        branch: input.branch,
-        branch_not: input.branch_not,
+        write_pair: input.write_pair,
+        linked: input.linked,
+        fcsr: fcsr_write,
+        branch,
         jump: input.jump,
-        jump_pc: input.imm << 2,
-        branch_pc: input.pc.wrapping_add((input.imm << 2) as i16 as u32) + 4, // casts are for sign extension
+        jump_pc,
+        branch_pc: input.pc.wrapping_add(input.imm << 2).wrapping_add(4), // imm is sign extended in decode
         syscall,
+        eret: input.eret,
+        exception: None,
         instruction: input.instruction,
         pc: input.pc,
     })
@@ -206,13 +429,183 @@ pub mod alu_signals {
     pub const ALU_ADD_S: u8 = 9;
     pub const ALU_MUL: u8 = 10;
     pub const ALU_DIV: u8 = 11;
+    pub const ALU_SLTU: u8 = 12;
+    pub const ALU_MFHI: u8 = 13;
+    pub const ALU_MTHI: u8 = 14;
+    pub const ALU_MFLO: u8 = 15;
+    pub const ALU_MTLO: u8 = 16;
+    pub const ALU_MULT: u8 = 17;
+    pub const ALU_MULTU: u8 = 18;
+    pub const ALU_DIVU: u8 = 19;
+    pub const ALU_MADD: u8 = 20;
+    pub const ALU_MADDU: u8 = 21;
+    pub const ALU_MSUB: u8 = 22;
+    pub const ALU_MSUBU: u8 = 23;
+    pub const ALU_MOV: u8 = 24;
+    pub const ALU_ADD_TRAP: u8 = 25;
+    pub const ALU_SUB_S: u8 = 26;
+    pub const ALU_MUL_S: u8 = 27;
+    pub const ALU_DIV_S: u8 = 28;
+    pub const ALU_SQRT_S: u8 = 29;
+    pub const ALU_ABS_S: u8 = 30;
+    pub const ALU_NEG_S: u8 = 31;
+    pub const ALU_CVT_S_W: u8 = 32;
+    pub const ALU_CVT_W_S: u8 = 33;
+    pub const ALU_TRUNC_W_S: u8 = 34;
+    pub const ALU_C_EQ_S: u8 = 35;
+    pub const ALU_C_LT_S: u8 = 36;
+    pub const ALU_C_LE_S: u8 = 37;
+    pub const ALU_CVT_D_S: u8 = 38;
+    pub const ALU_CVT_D_W: u8 = 39;
+    pub const ALU_ADD_D: u8 = 40;
+    pub const ALU_SUB_D: u8 = 41;
+    pub const ALU_MUL_D: u8 = 42;
+    pub const ALU_DIV_D: u8 = 43;
+    pub const ALU_SQRT_D: u8 = 44;
+    pub const ALU_ABS_D: u8 = 45;
+    pub const ALU_MOV_D: u8 = 46;
+    pub const ALU_NEG_D: u8 = 47;
+    pub const ALU_CVT_S_D: u8 = 48;
+    pub const ALU_CVT_W_D: u8 = 49;
+    pub const ALU_TRUNC_W_D: u8 = 50;
+    pub const ALU_C_EQ_D: u8 = 51;
+    pub const ALU_C_LT_D: u8 = 52;
+    pub const ALU_C_LE_D: u8 = 53;
+    pub const ALU_ROUND_W_S: u8 = 54;
+    pub const ALU_CEIL_W_S: u8 = 55;
+    pub const ALU_FLOOR_W_S: u8 = 56;
+    pub const ALU_ROUND_W_D: u8 = 57;
+    pub const ALU_CEIL_W_D: u8 = 58;
+    pub const ALU_FLOOR_W_D: u8 = 59;
+    pub const ALU_CLZ: u8 = 60;
+    pub const ALU_CLO: u8 = 61;
+    pub const ALU_ROTR: u8 = 62;
+    pub const ALU_SEB: u8 = 63;
+    pub const ALU_SEH: u8 = 64;
+    pub const ALU_WSBH: u8 = 65;
+    pub const ALU_EXT: u8 = 66;
+    pub const ALU_INS: u8 = 67;
 }
 use alu_signals::*;
-use crate::Register::{HI, LO};
+
+/// Joins a (lo, hi) pair into a 64 bit value
+fn join_hilo((lo, hi): (u32, u32)) -> u64 {
+    (hi as u64) << 32 | lo as u64
+}
+
+/// Splits a 64 bit value into a (lo, hi) pair
+fn split_hilo(value: u64) -> (u32, u32) {
+    (value as u32, (value >> 32) as u32)
+}
+
+/// Floating point unit for single, double and word operands which are each held in an
+/// (even, odd) register pair, single precision and word operands only use the even register.
+///
+/// Results are rounded in the given mode and returned with the IEEE-754 exceptions they raised.
+/// Comparisons return 1 if the condition holds.
+pub fn fpu(a: (u32, u32), b: (u32, u32), op: u8, mode: RoundingMode) -> Result<((u32, u32), u32)> {
+    let (single_a, single_b) = (f32::from_bits(a.0) as f64, f32::from_bits(b.0) as f64);
+    let (double_a, double_b) = (f64::from_bits(join_hilo(a)), f64::from_bits(join_hilo(b)));
+    let word_a = a.0 as i32 as f64;
+
+    let single = |result: Unrounded| {
+        let (result, raised) = result.round_single(mode);
+        ((result.to_bits(), 0), raised)
+    };
+    let double = |result: Unrounded| {
+        let (result, raised) = result.round_double(mode);
+        (split_hilo(result.to_bits()), raised)
+    };
+    let word = |value: f64, mode: RoundingMode| {
+        let (result, raised) = round_to_word(value, mode);
+        ((result, 0), raised)
+    };
+    // comparisons with NaN are unordered so never hold, only the quiet equality doesn't signal
+    let compare = |holds: bool, unordered: bool, signals: bool| {
+        ((holds as u32, 0), if unordered && signals { FP_INVALID } else { 0 })
+    };
+
+    Ok(match op {
+        ALU_ADD_S => single(Unrounded::add(single_a, single_b)),
+        ALU_SUB_S => single(Unrounded::sub(single_a, single_b)),
+        ALU_MUL_S => single(Unrounded::mul(single_a, single_b)),
+        ALU_DIV_S => single(Unrounded::div(single_a, single_b)),
+        ALU_SQRT_S => single(Unrounded::sqrt(single_a)),
+        // abs and neg only touch the sign bit
+        ALU_ABS_S => ((a.0 & !0x8000_0000, 0), 0),
+        ALU_NEG_S => ((a.0 ^ 0x8000_0000, 0), 0),
+        ALU_MOV => ((a.0, 0), 0),
+
+        ALU_ADD_D => double(Unrounded::add(double_a, double_b)),
+        ALU_SUB_D => double(Unrounded::sub(double_a, double_b)),
+        ALU_MUL_D => double(Unrounded::mul(double_a, double_b)),
+        ALU_DIV_D => double(Unrounded::div(double_a, double_b)),
+        ALU_SQRT_D => double(Unrounded::sqrt(double_a)),
+        ALU_ABS_D => ((a.0, a.1 & !0x8000_0000), 0),
+        ALU_NEG_D => ((a.0, a.1 ^ 0x8000_0000), 0),
+        ALU_MOV_D => (a, 0),
+
+        // conversions round in the FCSR mode unless they name their own
+        ALU_CVT_S_W => single(Unrounded::exact(word_a)),
+        ALU_CVT_S_D => single(Unrounded::exact(double_a)),
+        ALU_CVT_D_S => double(Unrounded::exact(single_a)),
+        ALU_CVT_D_W => double(Unrounded::exact(word_a)),
+        ALU_CVT_W_S => word(single_a, mode),
+        ALU_CVT_W_D => word(double_a, mode),
+        ALU_ROUND_W_S => word(single_a, RoundingMode::Nearest),
+        ALU_ROUND_W_D => word(double_a, RoundingMode::Nearest),
+        ALU_TRUNC_W_S => word(single_a, RoundingMode::Zero),
+        ALU_TRUNC_W_D => word(double_a, RoundingMode::Zero),
+        ALU_CEIL_W_S => word(single_a, RoundingMode::Up),
+        ALU_CEIL_W_D => word(double_a, RoundingMode::Up),
+        ALU_FLOOR_W_S => word(single_a, RoundingMode::Down),
+        ALU_FLOOR_W_D => word(double_a, RoundingMode::Down),
+
+        ALU_C_EQ_S | ALU_C_LT_S | ALU_C_LE_S => {
+            let unordered = single_a.is_nan() || single_b.is_nan();
+            match op {
+                ALU_C_EQ_S => compare(single_a == single_b, unordered, false),
+                ALU_C_LT_S => compare(single_a < single_b, unordered, true),
+                _ => compare(single_a <= single_b, unordered, true),
+            }
+        }
+        ALU_C_EQ_D | ALU_C_LT_D | ALU_C_LE_D => {
+            let unordered = double_a.is_nan() || double_b.is_nan();
+            match op {
+                ALU_C_EQ_D => compare(double_a == double_b, unordered, false),
+                ALU_C_LT_D => compare(double_a < double_b, unordered, true),
+                _ => compare(double_a <= double_b, unordered, true),
+            }
+        }
+        _ => bail!("Unknown floating point instruction: {}", op),
+    })
+}
+
+/// Bit field unit for ext and ins.
+///
+/// `ext` extracts the `msbd + 1` bits of rs starting at `lsb`, `ins` replaces bits `lsb` to
+/// `msb` of rt with the low bits of rs.
+pub fn bit_field(rs: u32, rt: u32, lsb: u32, msb: u32, op: u8) -> Result<u32> {
+    // a mask of the low `size` bits
+    let low_bits = |size: u32| ((1u64 << size) - 1) as u32;
+
+    Ok(match op {
+        ALU_EXT => (rs >> lsb) & low_bits(msb + 1),
+        ALU_INS => {
+            let mask = low_bits(msb - lsb + 1) << lsb;
+            (rt & !mask) | ((rs << lsb) & mask)
+        }
+        _ => bail!("Unknown bit field instruction: {}", op),
+    })
+}
 
 /// Simple ALU implementation.
+/// `hilo` holds the current (lo, hi) registers for instructions that operate on them.
+///
+/// `ALU_ADD_TRAP` raises [`Exception::Overflow`] on signed overflow, every other operation wraps.
+/// Dividing by zero is unpredictable on MIPS, here it leaves hi and lo unchanged.
 /// TODO: Handle carry flag
-pub fn alu(a: u32, b: u32, op: (bool, bool, u8)) -> Result<(u32, u32)> {
+pub fn alu(a: u32, b: u32, hilo: (u32, u32), op: (bool, bool, u8)) -> Result<(u32, u32)> {
     //println!("{} {} {:?}", a, b, op);
 
     let a = if op.0 { !a } else { a };
@@ -226,13 +619,49 @@ pub fn alu(a: u32, b: u32, op: (bool, bool, u8)) -> Result<(u32, u32)> {
         ALU_AND => (a & b, 0),
         ALU_OR => (a | b, 0),
         ALU_ADD => (arith_a.overflowing_add(arith_b).0, 0),
+        ALU_ADD_TRAP => {
+            let sum = arith_a.wrapping_add(arith_b);
+            // the adder overflows when both inputs share a sign the sum doesn't
+            if !(a ^ b) & (a ^ sum) & 0x8000_0000 != 0 {
+                bail!(Exception::Overflow)
+            }
+            (sum, 0)
+        }
         ALU_SLL => (a.overflowing_shl(b).0, 0),
         ALU_XOR => (a ^ b, 0),
 
-        ALU_MUL => (a * b, 0),
-        ALU_DIV => (a / b, a % b),
+        ALU_MUL => (a.wrapping_mul(b), 0),
+        ALU_DIV | ALU_DIVU if b == 0 => hilo,
+        ALU_DIV => (
+            (a as i32).wrapping_div(b as i32) as u32,
+            (a as i32).wrapping_rem(b as i32) as u32,
+        ),
+        ALU_DIVU => (a / b, a % b),
+
+        // 64 bit results are stored in (lo, hi)
+        ALU_MULT => split_hilo((a as i32 as i64 * b as i32 as i64) as u64),
+        ALU_MULTU => split_hilo(a as u64 * b as u64),
+        ALU_MADD => split_hilo(
+            (join_hilo(hilo) as i64).wrapping_add(a as i32 as i64 * b as i32 as i64) as u64,
+        ),
+        ALU_MADDU => split_hilo(join_hilo(hilo).wrapping_add(a as u64 * b as u64)),
+        ALU_MSUB => split_hilo(
+            (join_hilo(hilo) as i64).wrapping_sub(a as i32 as i64 * b as i32 as i64) as u64,
+        ),
+        ALU_MSUBU => split_hilo(join_hilo(hilo).wrapping_sub(a as u64 * b as u64)),
+
+        ALU_MFHI => (hilo.1, 0),
+        ALU_MFLO => (hilo.0, 0),
+        ALU_MTHI => (hilo.0, a),
+        ALU_MTLO => (a, hilo.1),
 
-        ALU_ADD_S => ((f32::from_bits(a) + f32::from_bits(b)).to_bits(), 0),
+        ALU_CLZ => (a.leading_zeros(), 0),
+        ALU_CLO => (a.leading_ones(), 0),
+        ALU_ROTR => (a.rotate_right(b), 0),
+        ALU_SEB => (b as u8 as i8 as i32 as u32, 0),
+        ALU_SEH => (b as u16 as i16 as i32 as u32, 0),
+        // swaps the bytes within each half word
+        ALU_WSBH => (((b & 0x00ff_00ff) << 8) | ((b >> 8) & 0x00ff_00ff), 0),
 
         // Rust uses signedness to select between logical and arithmetic right shifts
         ALU_SRL => (a.overflowing_shr(b).0, 0),
@@ -240,7 +669,8 @@ pub fn alu(a: u32, b: u32, op: (bool, bool, u8)) -> Result<(u32, u32)> {
 
         ALU_UPPER => (b << 16, 0),
 
-        ALU_SLT => (if a < b { 1 } else { 0 }, 0),
+        ALU_SLT => (if (a as i32) < (b as i32) { 1 } else { 0 }, 0),
+        ALU_SLTU => (if a < b { 1 } else { 0 }, 0),
         _ => bail!("Unknown ALU instruction: {:?}", op),
     })
 }
@@ -1,11 +1,15 @@
 use super::decode::IfId;
-use crate::Memory;
-use anyhow::{Context, Result};
+use crate::{Exception, Memory};
+use anyhow::{bail, Context, Result};
 
 /// Instruction fetch pipeline stage
 ///
 /// Fetches the currently pointed to instruction and increments the PC
 pub fn fetch(pc: &mut u32, mem: &mut Memory) -> Result<IfId> {
+    if !pc.is_multiple_of(4) {
+        bail!(Exception::AddressLoad(*pc))
+    }
+
     // fetch instruction and increment pc
     let instruction = mem.get(*pc)?;//.context("In instruction fetch stage")?;
     *pc += 4;
@@ -13,5 +17,6 @@ pub fn fetch(pc: &mut u32, mem: &mut Memory) -> Result<IfId> {
     Ok(IfId {
         instruction,
         pc: *pc - 4,
+        exception: None,
     })
 }
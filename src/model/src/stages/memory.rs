@@ -1,21 +1,20 @@
 use std::fs::read;
 use super::writeback::MemWb;
-use crate::{Memory, Register};
-use anyhow::{Context, Result};
+use crate::{AccessWidth, Exception, Memory, Register};
+use anyhow::{bail, Context, Result};
 
 /// Struct representing this stages input
 #[derive(Debug, Default, Clone)]
 pub struct ExMem {
     // stage data
     pub alu_result: (u32, u32),
-    pub zero: bool,
     pub branch: bool,
-    pub branch_not: bool,
     pub jump: bool,
-    pub write_data: u32,
+    pub write_data: (u32, u32),
     pub write: bool,
     pub read: bool,
-    pub word_align: bool,
+    pub access_width: AccessWidth,
+    pub mem_unsigned: bool,
     pub branch_pc: u32,
     pub jump_pc: u32,
     // forwarded data
@@ -23,40 +22,138 @@ pub struct ExMem {
     pub write_register: Register,
     pub reg_write: bool,
     pub use_hilo: bool,
+    pub write_pair: bool,
+    // if enabled the load reserves its word or the store checks the reservation
+    pub linked: bool,
+    // FCSR updated by a floating point operation alongside its result
+    pub fcsr: Option<u32>,
     pub syscall: bool,
+    pub eret: bool,
+    pub exception: Option<Exception>,
 
     // demo thing
     pub instruction: u32,
     pub pc: u32,
 }
 
+impl ExMem {
+    /// A bubble carrying an exception raised by the instruction at `pc`
+    pub fn faulted(exception: Exception, pc: u32) -> Self {
+        Self {
+            pc,
+            exception: Some(exception),
+            ..Default::default()
+        }
+    }
+}
+
 /// Memory access pipeline stage
 pub fn memory(pc: &mut u32, memory: &mut Memory, input: ExMem) -> Result<MemWb> {
-    let mut read_data = 0;
+    if let Some(exception) = input.exception {
+        return Ok(MemWb::faulted(exception, input.pc));
+    }
+
+    let mut read_data = (0, 0);
+    let (write_data, write_data_hi) = input.write_data;
 
     // handle memory accesses
-    if input.write {
-        if input.word_align {
-            *memory.get_mut(input.alu_result.0)? = input.write_data;
+    let address = input.alu_result.0;
+    let alignment = match input.access_width {
+        AccessWidth::Half => 2,
+        AccessWidth::Word => 4,
+        AccessWidth::Double => 8,
+        _ => 1,
+    };
+    if (input.read || input.write) && !address.is_multiple_of(alignment) {
+        if input.write {
+            bail!(Exception::AddressStore(address))
         } else {
-            memory.set_byte(input.alu_result.0, input.write_data as u8)?;
+            bail!(Exception::AddressLoad(address))
+        }
+    }
+
+    // sc only stores while the reservation made by ll holds and tells rt whether it did
+    let store = input.write && (!input.linked || memory.store_conditional(address));
+    if input.linked && input.write {
+        read_data.0 = store as u32;
+    }
+    if store {
+        // swl and swr only write the bytes of the word holding the address
+        let size = match input.access_width {
+            AccessWidth::Half => 2,
+            AccessWidth::Word => 4,
+            AccessWidth::Double => 8,
+            _ => 1,
+        };
+        memory.invalidate_link(address, size);
+
+        match input.access_width {
+            AccessWidth::Byte => memory.set_byte(address, write_data as u8)?,
+            AccessWidth::Half => memory.set_half(address, write_data as u16)?,
+            AccessWidth::Word => *memory.get_mut(address)? = write_data,
+            AccessWidth::Double => {
+                // the even register holds the low word
+                *memory.get_mut(address)? = write_data;
+                *memory.get_mut(address + 4)? = write_data_hi;
+            }
+            AccessWidth::WordLeft => {
+                // store the upper bytes of the register into the lower bytes of the word
+                let shift = (3 - address % 4) * 8;
+                let word = memory.get_mut(address & !3)?;
+                *word = (*word & !(u32::MAX >> shift)) | (write_data >> shift);
+            }
+            AccessWidth::WordRight => {
+                // store the lower bytes of the register into the upper bytes of the word
+                let shift = (address % 4) * 8;
+                let word = memory.get_mut(address & !3)?;
+                *word = (*word & ((1 << shift) - 1)) | (write_data << shift);
+            }
         }
         //println!("writing: {} to {:#x}", input.write_data, input.alu_result);
     }
+    if input.linked && input.read {
+        memory.load_linked(address);
+    }
     if input.read {
-        if input.word_align {
-            read_data = memory.get(input.alu_result.0)?;//.context("In memory stage")?;
-        } else {
-            read_data = memory.get_byte(input.alu_result.0)? as u32;
-        }
+        read_data.0 = match input.access_width {
+            AccessWidth::Byte => {
+                let byte = memory.get_byte(address)?;
+                if input.mem_unsigned {
+                    byte as u32
+                } else {
+                    byte as i8 as u32 // casts are for sign extension
+                }
+            }
+            AccessWidth::Half => {
+                let half = memory.get_half(address)?;
+                if input.mem_unsigned {
+                    half as u32
+                } else {
+                    half as i16 as u32 // casts are for sign extension
+                }
+            }
+            AccessWidth::Word => memory.get(address)?, //.context("In memory stage")?;
+            AccessWidth::WordLeft => {
+                // merge the lower bytes of the word into the upper bytes of the register
+                let shift = (3 - address % 4) * 8;
+                (memory.get(address & !3)? << shift) | (write_data & ((1 << shift) - 1))
+            }
+            AccessWidth::WordRight => {
+                // merge the upper bytes of the word into the lower bytes of the register
+                let shift = (address % 4) * 8;
+                (memory.get(address & !3)? >> shift) | (write_data & !(u32::MAX >> shift))
+            }
+            AccessWidth::Double => {
+                read_data.1 = memory.get(address + 4)?;
+                memory.get(address)?
+            }
+        };
         //println!("reading: {} from {:#x}", input.write_data, input.alu_result);
     }
 
     if input.branch {
-        if (!input.branch_not && input.zero) || (input.branch_not && !input.zero) {
-            // branch to PC copmuted in execute stage
-            *pc = input.branch_pc;
-        }
+        // branch to PC copmuted in execute stage
+        *pc = input.branch_pc;
     }
 
     if input.jump {
@@ -70,7 +167,11 @@ pub fn memory(pc: &mut u32, memory: &mut Memory, input: ExMem) -> Result<MemWb>
         write_register: input.write_register,
         reg_write: input.reg_write,
         use_hilo: input.use_hilo,
+        write_pair: input.write_pair,
+        fcsr: input.fcsr,
         syscall: input.syscall,
+        eret: input.eret,
+        exception: None,
         instruction: input.instruction,
         pc: input.pc,
     })
@@ -1,34 +1,71 @@
-use crate::{Register, RegisterFile};
+use crate::{Exception, Register, RegisterFile, CAUSE_TIMER};
 
 /// struct representing this structs input
 #[derive(Debug, Default, Clone)]
 pub struct MemWb {
     pub mem_to_reg: bool,
-    pub mem_data: u32,
+    pub mem_data: (u32, u32),
     pub alu_data: (u32, u32),
     pub write_register: Register,
     pub reg_write: bool,
     pub use_hilo: bool,
+    pub write_pair: bool,
+    // FCSR updated by a floating point operation alongside its result
+    pub fcsr: Option<u32>,
     pub syscall: bool,
+    pub eret: bool,
+    pub exception: Option<Exception>,
     // demo thing
     pub instruction: u32,
     pub pc: u32,
 }
 
+impl MemWb {
+    /// A bubble carrying an exception raised by the instruction at `pc`
+    pub fn faulted(exception: Exception, pc: u32) -> Self {
+        Self {
+            pc,
+            exception: Some(exception),
+            ..Default::default()
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct PipelineOutput {
     pub syscall: bool,
+    pub eret: bool,
+    pub exception: Option<Exception>,
     pub instruction: u32,
     pub pc: u32,
 }
 
 /// Writeback pipeline stage
 pub fn writeback(reg_file: &mut RegisterFile<u32>, input: MemWb) -> PipelineOutput {
-    if input.reg_write {
-        if input.mem_to_reg {
-            reg_file.set_value(input.write_register, input.mem_data);
+    if input.exception.is_some() {
+        // faulting instructions never write back
+    } else if input.reg_write {
+        let (data, data_hi) = if input.mem_to_reg {
+            input.mem_data
         } else {
-            reg_file.set_value(input.write_register, input.alu_data.0);
+            input.alu_data
+        };
+        reg_file.set_value(input.write_register, data);
+
+        // doubles are written to an even/odd register pair
+        if input.write_pair {
+            reg_file.set_value(input.write_register.next(), data_hi);
+        }
+
+        // floating point operations record their exceptions in the FCSR
+        if let Some(fcsr) = input.fcsr {
+            reg_file.set_value(Register::FCSR, fcsr);
+        }
+
+        // writing compare acknowledges the timer interrupt
+        if input.write_register == Register::COMPARE {
+            let cause = reg_file.value_or_default(Register::CAUSE);
+            reg_file.set_value(Register::CAUSE, cause & !CAUSE_TIMER);
         }
     } else if input.use_hilo {
         let (lo, hi) = input.alu_data;
@@ -38,6 +75,8 @@ pub fn writeback(reg_file: &mut RegisterFile<u32>, input: MemWb) -> PipelineOutp
 
     PipelineOutput {
         syscall: input.syscall,
+        eret: input.eret,
+        exception: input.exception,
         instruction: input.instruction,
         pc: input.pc,
     }
@@ -12,7 +12,10 @@ pub enum Syscall {
     ReadAny,
     ReadInt,
     ReadFloat,
+    ReadDouble,
     ReadString,
+    /// A break instruction paused the machine
+    Breakpoint,
 }
 
 pub fn resolve_syscall(reg_file: &mut RegisterFile<u32>, mem: &mut Memory, syscall: &Syscall, value: &str) -> Result<()> {
@@ -36,6 +39,16 @@ pub fn resolve_syscall(reg_file: &mut RegisterFile<u32>, mem: &mut Memory, sysca
             reg_file.set_value(Register::F0, val.to_bits());
         },
 
+        Syscall::ReadDouble => {
+            let buffer = value.trim();
+
+            let val = buffer
+                .parse::<f64>()
+                .with_context(|| format!("Attempting to parse '{}'", buffer))?;
+
+            reg_file.set_double_value(Register::F0, val);
+        },
+
         Syscall::ReadString => {
             let buffer = value.trim().to_string().into_bytes();
 
@@ -67,6 +80,11 @@ pub fn handle_syscall(reg_file: &mut RegisterFile<u32>, mem: &mut Memory) -> Res
             let arg = reg_file.value_or_default(Register::F12);
             Ok(Syscall::Print(format!("{}", f32::from_bits(arg))))
         }
+        3 => {
+            // print double
+            let arg = reg_file.double_value(Register::F12);
+            Ok(Syscall::Print(format!("{}", arg)))
+        }
         4 => {
             // print string
             let mut ptr = reg_file.value_or_default(Register::A0);
@@ -86,6 +104,7 @@ pub fn handle_syscall(reg_file: &mut RegisterFile<u32>, mem: &mut Memory) -> Res
 
         5 => Ok(Syscall::ReadInt),
         6 => Ok(Syscall::ReadFloat),
+        7 => Ok(Syscall::ReadDouble),
         8 => Ok(Syscall::ReadString),
         10 => Ok(Syscall::Quit),
 
@@ -114,7 +133,7 @@ pub fn handle_syscall(reg_file: &mut RegisterFile<u32>, mem: &mut Memory) -> Res
             let arg = reg_file.value_or_default(Register::A0);
             Ok(Syscall::Print(format!("{}", arg)))
         }
-        0xFFFFDEAD => {
+        0xDEAD => {
             // failed to exit kernel error
             Ok(Syscall::Error(format!(
                 "program finished (ran into kernel)"
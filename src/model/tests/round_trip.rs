@@ -0,0 +1,208 @@
+//! Checks that disassembling the programs in `scripts` and assembling them again gives back the
+//! same words
+//!
+//! Every word of the text segment of each script is disassembled and assembled again at the same
+//! address. A sweep of generated words covers the operands the scripts don't use.
+
+use std::path::Path;
+
+use model::{
+    assembler, disassemble, link,
+    parser::{
+        model::{LabelTable, Line, TEXT_BASE},
+        parse_string,
+    },
+    Source,
+};
+
+/// The first word of the guard the linker puts after the text segment
+const GUARD: u32 = 0x3402DEAD;
+
+/// Scripts that are meant to fail to assemble
+const BROKEN: &[&str] = &["diagnostics.s", "err.s"];
+
+/// Operands of the instructions in the opcode table, where `{r}` is a register, `{f}` a floating
+/// point register, `{i}` an immediate, `{s}` a shift amount or bit position and `{c}` a
+/// condition flag
+const OPERANDS: &[&str] = &[
+    "",
+    "{r}",
+    "{r}, {r}",
+    "{r}, {r}, {r}",
+    "{r}, {r}, {i}",
+    "{r}, {r}, {s}",
+    "{r}, {i}",
+    "{r}, {r}, {s}, {s}",
+    "{r}, {r}, target",
+    "{r}, target",
+    "target",
+    "{c}, target",
+    "{r}, {i}({r})",
+    "{f}, {i}({r})",
+    "{r}, {f}",
+    "{r}, $fcsr",
+    "{f}, {f}",
+    "{f}, {f}, {f}",
+    "{c}, {f}, {f}",
+    "{r}, {r}, {c}",
+    "{i}",
+];
+
+/// Operands generated for each instruction
+const VARIANTS: usize = 200;
+
+/// Disassembles a word at `pc` and assembles it again, describing the word if it changes
+fn reassemble(word: u32, pc: u32, labels: &LabelTable) -> Option<String> {
+    let assembly = disassemble(word, pc, labels);
+    // branches and jumps are followed by the nops of their delay slots
+    let again = match parse_string(&format!("{assembly}\n")) {
+        (lines, diagnostics) if diagnostics.is_empty() => {
+            lines.into_iter().find_map(|(_, line)| match line {
+                Line::Instruction(ins) => ins.into_iter().next(),
+                _ => None,
+            })
+        }
+        _ => None,
+    }
+    .and_then(|ins| ins.asm(labels, pc).ok())
+    .and_then(|(bytes, _)| bytes.try_into().ok())
+    .map(u32::from_le_bytes);
+
+    if again == Some(word) {
+        return None;
+    }
+    let again = match again {
+        Some(again) => format!("is 0x{again:08x}"),
+        None => "doesn't assemble".to_string(),
+    };
+    Some(format!("0x{pc:08x}: 0x{word:08x} became `{assembly}` which {again}"))
+}
+
+/// Disassembles every word of a script and assembles it again, returning the words that change
+fn round_trip(path: &Path) -> Vec<String> {
+    let text = std::fs::read_to_string(path).unwrap();
+    let (memory, labels, _) = match link(vec![Source::new(Some(path.into()), text)]) {
+        Ok(program) => program,
+        Err(diagnostics) => {
+            return diagnostics.iter().map(ToString::to_string).collect();
+        }
+    };
+
+    let mut changed = vec![];
+    let mut pc = TEXT_BASE;
+    while let Ok(word) = memory.get(pc) {
+        if word == GUARD {
+            break;
+        }
+        changed.extend(reassemble(word, pc, &labels));
+        pc += 4;
+    }
+    changed
+}
+
+#[test]
+fn scripts_round_trip() {
+    let scripts = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../scripts");
+    let mut paths: Vec<_> = std::fs::read_dir(scripts)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "s"))
+        .filter(|path| !BROKEN.iter().any(|name| path.ends_with(name)))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scripts were found");
+
+    let mut failures = vec![];
+    for path in paths {
+        for change in round_trip(&path) {
+            failures.push(format!("{}: {change}", path.display()));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+
+/// Fills in the placeholders of an operand template, with `value` choosing each operand
+fn fill(template: &str, mut value: impl FnMut(char) -> String) -> String {
+    let mut operands = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        operands.push_str(&rest[..start]);
+        let kind = rest[start + 1..].chars().next().unwrap();
+        operands.push_str(&value(kind));
+        rest = &rest[start + 3..];
+    }
+    operands + rest
+}
+
+/// Assembles an instruction followed by a label, giving the first word and the labels
+fn encode(line: &str) -> Option<(u32, LabelTable)> {
+    let (memory, labels) = assembler(format!(".text\n{line}\ntarget: nop\n")).ok()?;
+    Some((memory.get(TEXT_BASE).ok()?, labels))
+}
+
+#[test]
+fn generated_operands_round_trip() {
+    // a xorshift generator with a fixed seed, so every run tries the same operands
+    let mut state = 0x2545_f491_u32;
+    let mut random = move |range: std::ops::Range<i64>| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        range.start + state as i64 % (range.end - range.start)
+    };
+
+    // every instruction of the opcode table, without its pseudo instructions and directives
+    let table = include_str!("../src/parser/opcode.rs");
+    let mnemonics: Vec<_> = table
+        .lines()
+        .filter_map(|line| {
+            let (name, parser) = line.trim().strip_prefix('"')?.split_once("\" => Ok(")?;
+            let real = parser.starts_with("InstructionParser::new")
+                || parser.starts_with("InstructionParser::with_constants");
+            real.then_some(name)
+        })
+        .collect();
+    assert!(mnemonics.len() > 100, "only {} mnemonics were found", mnemonics.len());
+
+    let mut failures = vec![];
+    for name in mnemonics {
+        // the immediate of the probe is too large for a shift, which picks `{s}` instead
+        let probe = |kind| match kind {
+            'r' => "$t0",
+            'f' => "$f2",
+            'i' => "100",
+            _ => "1",
+        };
+        let template = OPERANDS.iter().find(|template| {
+            encode(&format!("{name} {}", fill(template, |kind| probe(kind).into()))).is_some()
+        });
+        let Some(template) = template else {
+            failures.push(format!("`{name}` doesn't assemble with any operands"));
+            continue;
+        };
+
+        let mut assembled = 0;
+        for _ in 0..VARIANTS {
+            let operands = fill(template, |kind| match kind {
+                'r' => format!("${}", random(0..32)),
+                'f' => format!("$f{}", random(0..32)),
+                'i' => random(-0x8000..0x10000).to_string(),
+                's' => random(0..32).to_string(),
+                _ => random(0..8).to_string(),
+            });
+            // operands out of the range of the instruction are left to the diagnostics
+            let line = format!("{name} {operands}");
+            let Some((word, labels)) = encode(&line) else {
+                continue;
+            };
+            assembled += 1;
+            let change = reassemble(word, TEXT_BASE, &labels);
+            failures.extend(change.map(|change| format!("`{line}`: {change}")));
+        }
+        if assembled == 0 {
+            failures.push(format!("`{name}` doesn't assemble with generated operands"));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
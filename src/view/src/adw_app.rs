@@ -17,7 +17,7 @@ use gtk::prelude::*;
 use sourceview5::prelude::*;
 use sourceview5::StyleSchemeManager;
 
-use model::assembler;
+use model::link;
 use model::callback::Callback;
 use model::machine::Machine;
 use model::syscall::SyscallDiscriminants;
@@ -26,6 +26,7 @@ use util::shared::Shared;
 
 use crate::app_window::AppWindow;
 use crate::ensure;
+use crate::project::Project;
 use crate::traits::*;
 
 /// The application's ID
@@ -35,6 +36,7 @@ const APP_ID: &str = "net.shayes.raja";
 pub struct AdwApp {
     app: Application,
     machine: Machine,
+    project: Project,
 }
 
 impl AdwApp {
@@ -52,6 +54,7 @@ impl AdwApp {
             Self {
                 app: app.clone(),
                 machine: Default::default(),
+                project: Default::default(),
             }
         );
 
@@ -90,9 +93,10 @@ impl AdwApp {
         Self::connect_btn_settings(window.clone());
 
         // Connect the file buttons
-        Self::connect_file_new(window.clone());
-        Self::connect_file_open(window.clone());
-        Self::connect_file_save_as(window.clone());
+        Self::connect_file_new(adw_app.clone(), window.clone());
+        Self::connect_file_open(adw_app.clone(), window.clone());
+        Self::connect_folder_open(adw_app.clone(), window.clone());
+        Self::connect_file_save_as(adw_app.clone(), window.clone());
 
         // Connect the view buttons
         Self::connect_register_view(adw_app.clone(), window.clone());
@@ -155,6 +159,18 @@ impl AdwApp {
             }))
         );
 
+        // Breakpoint
+        let _window = window.clone();
+        callbacks.insert(
+            SyscallDiscriminants::Breakpoint,
+            Callback::new(Box::new(move |info| {
+                if let Some(message) = info {
+                    _window.main_view().console().print(&format!("\n[BREAK] {}\n", message));
+                    debug_println!("[CONSOLE] [BREAK] {}", message);
+                }
+            }))
+        );
+
         let _window = window.clone();
         callbacks.insert(
             SyscallDiscriminants::Quit,
@@ -189,13 +205,17 @@ impl AdwApp {
      */
     fn connect_btn_run(adw_app: Shared<AdwApp>, window: AppWindow) {
         window.btn_run().connect_clicked(move |_| {
-            debug_println!("[DEBUG] Assembling and running...");
+            // Resume from a break instruction without reassembling
+            let at_breakpoint = adw_app.borrow_mut().machine.at_breakpoint();
+            if !at_breakpoint {
+                debug_println!("[DEBUG] Assembling and running...");
 
-            // Clear the console
-            window.main_view().console().clear();
+                // Clear the console
+                window.main_view().console().clear();
 
-            // Reset and flash the assembly to the machine
-            Self::reset_flash_machine(&adw_app, &window);
+                // Reset and flash the assembly to the machine
+                Self::reset_flash_machine(&adw_app, &window);
+            }
 
             Self::start_simulator(adw_app.clone(), window.clone());
         });
@@ -294,7 +314,7 @@ impl AdwApp {
         window.add_action(&action);
     }
 
-    fn connect_file_new(window: AppWindow) {
+    fn connect_file_new(adw_app: Shared<AdwApp>, window: AppWindow) {
         Self::connect_simple_action(window.clone(), "file-new", move |_, _| {
             let dialog = MessageDialog::builder()
                 .text("WARNING")
@@ -304,10 +324,12 @@ impl AdwApp {
                 .transient_for(&window)
                 .build();
 
+            let _adw_app = adw_app.clone();
             let _window = window.clone();
             dialog.connect_response(move |dialog, response| {
                 match response {
                     ResponseType::Yes => {
+                        _adw_app.borrow_mut().project = Project::default();
                         _window.main_view().source_view().clear();
                         _window.main_view().console().clear();
                     }
@@ -343,12 +365,13 @@ impl AdwApp {
         });
     }
 
-    fn connect_file_open(window: AppWindow) {
+    fn connect_file_open(adw_app: Shared<AdwApp>, window: AppWindow) {
         Self::connect_simple_action(window.clone(), "file-open", move |_, _| {
             // TODO: Investigate why this filter does not work (maybe only an issue on Windows native)
             let filter = FileFilter::new();
             filter.add_pattern("*.s");
             filter.add_pattern("*.asm");
+            filter.add_pattern("*.elf");
 
             let dialog = FileChooserNative::builder()
                 .title("Open File")
@@ -357,6 +380,7 @@ impl AdwApp {
                 .transient_for(&window)
                 .build();
 
+            let _adw_app = adw_app.clone();
             let _window = window.clone();
             dialog.connect_response(move |dialog, response| {
                 dialog.destroy();
@@ -378,10 +402,21 @@ impl AdwApp {
                     None => return
                 };
 
-                // Read the file into the editor
-                match fs::read_to_string(path) {
+                // Executables are loaded into the simulator when they are built instead of being
+                // edited
+                if fs::read(&path).is_ok_and(|bytes| bytes.starts_with(b"\x7fELF")) {
+                    let note =
+                        format!("# {} is an executable, build it to load it\n", path.display());
+                    _window.main_view().source_view().set_text(note);
+                    _adw_app.borrow_mut().project = Project::elf(path);
+                    return;
+                }
+
+                // Read the file into the editor, it is a program of its own
+                match fs::read_to_string(&path) {
                     Ok(contents) => {
                         _window.main_view().source_view().set_text(contents);
+                        _adw_app.borrow_mut().project = Project::file(path);
                     }
                     Err(_) => {}
                 }
@@ -422,7 +457,68 @@ impl AdwApp {
         });
     }
 
-    fn connect_file_save_as(window: AppWindow) {
+    fn connect_folder_open(adw_app: Shared<AdwApp>, window: AppWindow) {
+        Self::connect_simple_action(window.clone(), "folder-open", move |_, _| {
+            let dialog = FileChooserNative::builder()
+                .title("Open Project Folder")
+                .action(FileChooserAction::SelectFolder)
+                .transient_for(&window)
+                .build();
+
+            let _adw_app = adw_app.clone();
+            let _window = window.clone();
+            dialog.connect_response(move |dialog, response| {
+                dialog.destroy();
+
+                // Return early if response is not "Accept"
+                if response != ResponseType::Accept {
+                    return;
+                }
+
+                // Get the path of the folder
+                let path = match dialog.file().and_then(|folder| folder.path()) {
+                    Some(path) => path,
+                    None => return
+                };
+
+                // Open the main file of the project in the editor, the other files are linked
+                // with it when it is built
+                let project = Project::folder(&path).and_then(|project| {
+                    let contents = match project.main() {
+                        Some(main) => fs::read_to_string(main)?,
+                        None => String::new(),
+                    };
+                    Ok((project, contents))
+                });
+                match project {
+                    Ok((project, contents)) => {
+                        _window.main_view().source_view().set_text(contents);
+                        _adw_app.borrow_mut().project = project;
+                    }
+                    Err(err) => {
+                        // Alert the user if failed
+                        let dialog = MessageDialog::builder()
+                            .text("ERROR: Failed to open the project")
+                            .secondary_text(err.to_string())
+                            .buttons(ButtonsType::Ok)
+                            .message_type(MessageType::Error)
+                            .transient_for(&_window)
+                            .build();
+
+                        dialog.connect_response(|dialog, _| {
+                            dialog.close();
+                        });
+
+                        dialog.present();
+                    }
+                }
+            });
+
+            dialog.show();
+        });
+    }
+
+    fn connect_file_save_as(adw_app: Shared<AdwApp>, window: AppWindow) {
         Self::connect_simple_action(window.clone(), "file-save-as", move |_, _| {
             let dialog = FileChooserNative::builder()
                 .title("Save File As")
@@ -430,6 +526,7 @@ impl AdwApp {
                 .transient_for(&window)
                 .build();
 
+            let _adw_app = adw_app.clone();
             let _window = window.clone();
             dialog.connect_response(move |dialog, response| {
                 // Return early if response is not "Accept"
@@ -452,9 +549,9 @@ impl AdwApp {
                 // Get the contents to write
                 let contents = _window.main_view().source_view().text();
 
-                // Write to the file
-                match fs::write(path, contents) {
-                    Ok(_) => {}
+                // Write to the file, which is now the main file of the project
+                match fs::write(&path, contents) {
+                    Ok(_) => _adw_app.borrow_mut().project.set_main(path),
                     Err(err) => {
                         // Alert the user if failed
                         let dialog = MessageDialog::builder()
@@ -585,31 +682,77 @@ impl AdwApp {
 
             match flow {
                 ControlFlow::Continue(_) => Continue(true),
-                ControlFlow::Break(_) => Continue(false)
+                ControlFlow::Break(_) => {
+                    // Show where a break instruction paused the machine
+                    if let Some(line) = machine.breakpoint_line() {
+                        window.main_view().source_view().highlight_line(line);
+                    }
+                    Continue(false)
+                }
             }
         });
     }
 
     /**
-    Resets the simulator, then assembles and flashes the source assembly.
+    Resets the simulator, then assembles and flashes the source assembly linked with the other
+    files of the project, or loads the executable of the project.
 
     # Arguments
     - `adw_app` - A borrowed reference to a shared instance of AdwApp.
     - `window` - A borrowed reference to the app's window.
      */
     fn reset_flash_machine(adw_app: &Shared<AdwApp>, window: &AppWindow) {
-        let machine = &mut adw_app.borrow_mut().machine;
+        let adw_app = &mut *adw_app.borrow_mut();
+        let machine = &mut adw_app.machine;
 
         // Get the assembly code
-        let mut src = window.main_view().source_view().text();
+        let src = window.main_view().source_view().text();
 
         // Reset the machine
         machine.hard_reset();
 
-        // Flash the machine
-        match assembler(src) {
-            Ok((mem, lbl)) => machine.flash(mem, lbl),
-            Err(err) => window.main_view().console().print_err(&format!("{err}"))
+        // Read the other files of the project
+        let main_view = window.main_view();
+        main_view.clear_diagnostics();
+
+        // Load an executable as it is, there is nothing to assemble
+        if let Some(path) = adw_app.project.executable() {
+            let loaded = fs::read(path)
+                .map_err(|err| err.to_string())
+                .and_then(|bytes| machine.load_elf(&bytes).map_err(|err| err.to_string()));
+            if let Err(err) = loaded {
+                main_view
+                    .console()
+                    .print_err(&format!("[ERROR] Failed to load the executable: {err}"));
+            }
+            return;
+        }
+
+        let sources = match adw_app.project.sources(src) {
+            Ok(sources) => sources,
+            Err(err) => {
+                main_view
+                    .console()
+                    .print_err(&format!("[ERROR] Failed to read the project: {err}"));
+                return;
+            }
+        };
+
+        // Flash the machine, pointing out the problems in the source if it does not assemble
+        match link(sources) {
+            Ok((mem, lbl, warnings)) => {
+                machine.flash(mem, lbl);
+                if !warnings.is_empty() {
+                    let message = format!("\n[WARNING] Assembled with {} warning(s)", warnings.len());
+                    main_view.console().print(&message);
+                    main_view.show_diagnostics(warnings);
+                }
+            }
+            Err(diagnostics) => {
+                let message = format!("[ERROR] Failed to assemble, found {} problem(s)", diagnostics.len());
+                main_view.console().print_err(&message);
+                main_view.show_diagnostics(diagnostics);
+            }
         };
     }
 
@@ -689,4 +832,15 @@ impl Source for sourceview5::View {
     fn clear(&self) {
         self.buffer().set_text("");
     }
+
+    fn highlight_line(&self, line: usize) {
+        let buffer = self.buffer();
+
+        if let Some(mut start) = buffer.iter_at_line(line as i32) {
+            let mut end = start.clone();
+            end.forward_to_line_end();
+            buffer.select_range(&start, &end);
+            self.scroll_to_iter(&mut start, 0.1, false, 0.0, 0.0);
+        }
+    }
 }
\ No newline at end of file
@@ -4,5 +4,6 @@ pub mod adw_app;
 pub mod gtk_console;
 pub mod register_view;
 pub mod main_view;
+pub mod project;
 mod ensure;
 mod macros;
@@ -1,10 +1,19 @@
 mod template;
 
 use gtk::gio;
+use gtk::pango::Underline;
+use gtk::prelude::*;
 use glib::subclass::prelude::ObjectSubclassIsExt;
+use model::{Diagnostic, Severity};
 use crate::gtk_console::GtkConsole;
+use crate::traits::Source;
 use crate::widget;
 
+/// The page of the bottom panel holding the console
+const PAGE_CONSOLE: u32 = 0;
+/// The page of the bottom panel holding the problems found by a build
+const PAGE_PROBLEMS: u32 = 1;
+
 glib::wrapper! {
     pub struct MainView(ObjectSubclass<template::MainViewTemplate>)
         @extends gtk::Box, gtk::Window, gtk::Widget,
@@ -15,4 +24,123 @@ glib::wrapper! {
 impl MainView {
     widget!(source_view, sourceview5::View);
     widget!(console, GtkConsole);
+    widget!(panel, gtk::Notebook);
+    widget!(problems, gtk::ListBox);
+
+    /**
+    Underlines the diagnostics of a build in the source view and lists them in the problems panel.
+
+    # Arguments
+    - `diagnostics` - The diagnostics returned by the assembler for the text of the source view.
+     */
+    pub fn show_diagnostics(&self, diagnostics: Vec<Diagnostic>) {
+        self.clear_diagnostics();
+
+        let source_view = self.source_view();
+        let buffer = source_view.buffer();
+        // The assembler only sees LF line endings
+        let script = source_view.text().replace("\r\n", "\n");
+
+        let mut shown = self.imp().diagnostics.borrow_mut();
+        for diagnostic in diagnostics {
+            let underline = match diagnostic.severity {
+                Severity::Error => Underline::Error,
+                Severity::Warning => Underline::Single,
+            };
+            let tag = buffer.create_tag(None, &[("underline", &underline)]).unwrap();
+
+            // Lines and columns don't depend on line endings, unlike byte offsets. Problems in
+            // the other files of a project are only listed
+            let (line, column) = (diagnostic.line as i32, diagnostic.column as i32);
+            if diagnostic.file.is_none() {
+                if let Some(start) = buffer.iter_at_line_offset(line, column) {
+                    let mut end = start.clone();
+                    end.forward_chars(script[diagnostic.span.clone()].chars().count() as i32);
+                    buffer.apply_tag(&tag, &start, &end);
+                }
+            }
+
+            let file = match &diagnostic.file {
+                Some(file) => format!("{}:", file.display()),
+                None => String::new(),
+            };
+            let label = gtk::Label::new(Some(&format!(
+                "{}{}:{} {}",
+                file,
+                diagnostic.line + 1,
+                diagnostic.column + 1,
+                describe(&diagnostic),
+            )));
+            label.set_xalign(0.0);
+            label.set_margin_start(8);
+            label.set_margin_end(8);
+            label.set_margin_top(4);
+            label.set_margin_bottom(4);
+            self.problems().append(&label);
+
+            shown.push((tag, diagnostic));
+        }
+
+        if !shown.is_empty() {
+            self.panel().set_current_page(Some(PAGE_PROBLEMS));
+        }
+    }
+
+    /// Removes the diagnostics of the last build from the source view and the problems panel.
+    pub fn clear_diagnostics(&self) {
+        let tags = self.source_view().buffer().tag_table();
+        for (tag, _) in self.imp().diagnostics.borrow_mut().drain(..) {
+            tags.remove(&tag);
+        }
+
+        let problems = self.problems();
+        while let Some(row) = problems.last_child() {
+            problems.remove(&row);
+        }
+
+        self.panel().set_current_page(Some(PAGE_CONSOLE));
+    }
+
+    /**
+    Selects the source text of a diagnostic and scrolls it into view.
+
+    # Arguments
+    - `index` - The index of the diagnostic in the problems panel.
+     */
+    pub fn go_to_diagnostic(&self, index: usize) {
+        let source_view = self.source_view();
+        let buffer = source_view.buffer();
+        let diagnostics = self.imp().diagnostics.borrow();
+        let (tag, diagnostic) = match diagnostics.get(index) {
+            Some(shown) => shown,
+            None => return,
+        };
+
+        // Problems in the other files of a project are not in the editor
+        if diagnostic.file.is_some() {
+            return;
+        }
+
+        // The tag follows the text while it is edited
+        let mut start = buffer.start_iter();
+        if !start.starts_tag(Some(tag)) && !start.forward_to_tag_toggle(Some(tag)) {
+            source_view.highlight_line(diagnostic.line);
+            return;
+        }
+        let mut end = start.clone();
+        end.forward_to_tag_toggle(Some(tag));
+
+        buffer.select_range(&start, &end);
+        source_view.scroll_to_iter(&mut start, 0.1, false, 0.0, 0.0);
+        source_view.grab_focus();
+    }
+}
+
+/// Describes a diagnostic followed by its notes
+fn describe(diagnostic: &Diagnostic) -> String {
+    let mut text = format!("{}: {}", diagnostic.severity, diagnostic.message);
+    for note in &diagnostic.notes {
+        text.push_str(&format!("\nnote: {note}"));
+    }
+    text
 }
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+
 use adw::subclass::prelude::AdwApplicationWindowImpl;
 use gtk::prelude::*;
 use gtk::subclass::prelude::*;
@@ -5,6 +7,8 @@ use gtk::subclass::prelude::*;
 use gtk::CompositeTemplate;
 use glib::subclass::InitializingObject;
 
+use model::Diagnostic;
+
 use crate::gtk_console;
 
 /**
@@ -20,6 +24,49 @@ pub struct MainViewTemplate {
     pub source_view: TemplateChild<sourceview5::View>,
     #[template_child]
     pub console: TemplateChild<gtk_console::GtkConsole>,
+    #[template_child]
+    pub panel: TemplateChild<gtk::Notebook>,
+    #[template_child]
+    pub problems: TemplateChild<gtk::ListBox>,
+
+    // The diagnostics of the last build, each with the tag underlining it in the source view
+    pub diagnostics: RefCell<Vec<(gtk::TextTag, Diagnostic)>>,
+}
+
+impl MainViewTemplate {
+    fn connect_problem_handler(&self) {
+        let view = self.obj().clone();
+        self.problems.connect_row_activated(move |_, row| {
+            view.go_to_diagnostic(row.index() as usize);
+        });
+    }
+
+    fn connect_tooltip_handler(&self) {
+        let source_view = self.source_view.get();
+        source_view.set_has_tooltip(true);
+
+        let view = self.obj().clone();
+        source_view.connect_query_tooltip(move |source_view, x, y, _, tooltip| {
+            let (x, y) = source_view.window_to_buffer_coords(gtk::TextWindowType::Widget, x, y);
+            let iter = match source_view.iter_at_location(x, y) {
+                Some(iter) => iter,
+                None => return false,
+            };
+
+            // Show the diagnostics underlined at the pointer
+            let tags = iter.tags();
+            let text: Vec<String> = view.imp().diagnostics.borrow().iter()
+                .filter(|(tag, _)| tags.contains(tag))
+                .map(|(_, diagnostic)| super::describe(diagnostic))
+                .collect();
+            if text.is_empty() {
+                return false;
+            }
+
+            tooltip.set_text(Some(&text.join("\n\n")));
+            true
+        });
+    }
 }
 
 /// gtk-rs boilerplate implementation
@@ -44,6 +91,10 @@ impl ObjectSubclass for MainViewTemplate {
 impl ObjectImpl for MainViewTemplate {
     fn constructed(&self) {
         self.parent_constructed();
+
+        // Connect handlers to point out the diagnostics of a build in the source view
+        self.connect_problem_handler();
+        self.connect_tooltip_handler();
     }
 }
 
@@ -0,0 +1,123 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use model::linker;
+
+/// The files of the program being edited.
+#[derive(Default)]
+pub struct Project {
+    /// The file open in the editor, which is the main script of the program
+    main: Option<PathBuf>,
+    /// The other files of the program, which are read when it is built
+    files: Vec<PathBuf>,
+    /// An executable that is loaded instead of assembling the files
+    executable: Option<PathBuf>,
+}
+
+impl Project {
+    /// Creates a project of a single file.
+    pub fn file(path: PathBuf) -> Self {
+        Self {
+            main: Some(path),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a project of an ELF executable, which is loaded as it is when it is built.
+    pub fn elf(path: PathBuf) -> Self {
+        Self {
+            executable: Some(path),
+            ..Default::default()
+        }
+    }
+
+    /**
+    Creates a project of every assembly file in a folder.
+
+    The main file is the one that makes `main` global, or `main.s` if none does.
+
+    # Arguments
+    - `folder` - The folder of the project.
+     */
+    pub fn folder(folder: &Path) -> io::Result<Self> {
+        let mut files = vec![];
+        for entry in fs::read_dir(folder)? {
+            let path = entry?.path();
+            if matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("s" | "asm")
+            ) {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        if files.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "the folder has no .s or .asm files",
+            ));
+        }
+
+        let main = files
+            .iter()
+            .position(|path| fs::read_to_string(path).map_or(false, |text| declares_main(&text)))
+            .or_else(|| {
+                files
+                    .iter()
+                    .position(|path| path.file_stem() == Some("main".as_ref()))
+            })
+            .unwrap_or(0);
+        let main = files.remove(main);
+
+        Ok(Self {
+            main: Some(main),
+            files,
+            executable: None,
+        })
+    }
+
+    /// Returns the file open in the editor.
+    pub fn main(&self) -> Option<&Path> {
+        self.main.as_deref()
+    }
+
+    /// Changes where the file open in the editor is saved, which makes it the program instead of
+    /// an executable.
+    pub fn set_main(&mut self, path: PathBuf) {
+        self.main = Some(path);
+        self.executable = None;
+    }
+
+    /// Returns the executable of the project if it is one.
+    pub fn executable(&self) -> Option<&Path> {
+        self.executable.as_deref()
+    }
+
+    /**
+    Collects the files of the program to link them.
+
+    # Arguments
+    - `text` - The text of the editor, which is used instead of the saved main file.
+     */
+    pub fn sources(&self, text: String) -> io::Result<Vec<linker::Source>> {
+        let mut sources = vec![linker::Source::new(self.main.clone(), text)];
+        for path in &self.files {
+            sources.push(linker::Source::new(
+                Some(path.clone()),
+                fs::read_to_string(path)?,
+            ));
+        }
+        Ok(sources)
+    }
+}
+
+/// Checks if a script makes `main` global.
+fn declares_main(text: &str) -> bool {
+    text.lines().any(|line| {
+        let code = line.split('#').next().unwrap_or("");
+        let mut words = code.split(|c: char| c.is_whitespace() || c == ',');
+        words.find(|word| !word.is_empty()) == Some(".globl") && words.any(|word| word == "main")
+    })
+}
@@ -8,7 +8,10 @@ use gtk::{Align, Grid, Label, ListBox, ListBoxRow, Orientation, ScrolledWindow,
 use gtk::pango::{AttrFontDesc, Attribute, AttrList, AttrString, FontDescription};
 use gtk::prelude::*;
 
-use model::RegisterFile;
+use model::{
+    fp_conditions, Register, RegisterFile, RoundingMode, FCSR_FLAGS_SHIFT, FP_DIV_ZERO,
+    FP_INEXACT, FP_INVALID, FP_OVERFLOW, FP_UNDERFLOW,
+};
 use crate::widget;
 
 glib::wrapper! {
@@ -34,7 +37,16 @@ impl RegisterView {
             let label_value = grid.child_at(1, i as i32).unwrap().dynamic_cast::<Label>().unwrap();
 
             label_name.set_text(name);
-            label_value.set_text(&format!("{value:#010x}"));
+            label_value.set_text(&Self::format_value(name, *value));
+        }
+
+        // Populate the doubles held by pairs of float registers below the registers
+        let offset = reg_file.map().len();
+        for (i, (_, value)) in reg_file.double_pairs().iter().enumerate() {
+            let row = (offset + i) as i32;
+            let label_value = grid.child_at(1, row).unwrap().dynamic_cast::<Label>().unwrap();
+
+            label_value.set_text(&value.to_string());
         }
     }
 
@@ -51,6 +63,8 @@ impl RegisterView {
 
         // Populate the grid
         for (i, (name, value)) in reg_file.map().iter().enumerate() {
+            let text = Self::format_value(name, *value);
+
             let name = Label::builder()
                 .label(name)
                 .halign(Align::End)
@@ -58,7 +72,7 @@ impl RegisterView {
                 .build();
 
             let value = Label::builder()
-                .label(format!("{value:#010x}"))
+                .label(text)
                 .halign(Align::Start)
                 .attributes(&attrs)
                 .build();
@@ -66,6 +80,59 @@ impl RegisterView {
             grid.attach(&name, 0, i as i32, 1, 1);
             grid.attach(&value, 1, i as i32, 1, 1);
         }
+
+        // Show each pair of float registers as the double it holds
+        let offset = reg_file.map().len();
+        for (i, (name, value)) in reg_file.double_pairs().iter().enumerate() {
+            let row = (offset + i) as i32;
+
+            let name = Label::builder()
+                .label(name)
+                .halign(Align::End)
+                .attributes(&attrs)
+                .build();
+
+            let value = Label::builder()
+                .label(value.to_string())
+                .halign(Align::Start)
+                .attributes(&attrs)
+                .build();
+
+            grid.attach(&name, 0, row, 1, 1);
+            grid.attach(&value, 1, row, 1, 1);
+        }
+    }
+
+    /// Formats a register value, spelling out the condition flags, rounding mode and exception
+    /// flags held by the FCSR
+    fn format_value(name: &str, value: u32) -> String {
+        if name == String::from(Register::FCSR) {
+            // sticky exception flags as VZOUI, with a dash for each clear flag
+            let letters = [
+                (FP_INVALID, 'V'),
+                (FP_DIV_ZERO, 'Z'),
+                (FP_OVERFLOW, 'O'),
+                (FP_UNDERFLOW, 'U'),
+                (FP_INEXACT, 'I'),
+            ];
+            let flags: String = letters
+                .into_iter()
+                .map(|(flag, letter)| {
+                    if (value >> FCSR_FLAGS_SHIFT) & flag != 0 {
+                        letter
+                    } else {
+                        '-'
+                    }
+                })
+                .collect();
+            format!(
+                "{value:#010x} cc {:08b} {:?} {flags}",
+                fp_conditions(value),
+                RoundingMode::from_fcsr(value)
+            )
+        } else {
+            format!("{value:#010x}")
+        }
     }
 
     widget!(grid, Grid);
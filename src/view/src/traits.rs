@@ -20,6 +20,14 @@ pub trait Source {
 
     /// Clears the contents of the source.
     fn clear(&self);
+
+    /**
+    Selects a line of the source and scrolls it into view.
+
+    # Arguments
+    - `line` - The line to highlight (0 indexed).
+     */
+    fn highlight_line(&self, line: usize);
 }
 
 /// A trait defining required functionality for a console (i.e. text I/O).